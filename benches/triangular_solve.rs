@@ -0,0 +1,74 @@
+//! Compares solving `N` right hand sides at once against `N` separate solves.
+//! Run with `cargo bench --bench triangular_solve`.
+//! With `--features parallel`, also compares the serial and parallel solves.
+use sparse_lu::{Csc, F};
+use std::hint::black_box;
+use std::time::Instant;
+
+/// A lower triangular matrix with a few bands below the diagonal.
+fn banded(n: usize, offsets: &[usize]) -> Csc<F> {
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 4.));
+        for &off in offsets {
+            if i + off < n {
+                t.push(([i, i + off], -1. / off as F));
+            }
+        }
+    }
+    Csc::from_triplets(n, n, &mut t).unwrap()
+}
+
+fn bench<const N: usize>(a: &Csc<F>, iters: usize) {
+    let n = a.ncols();
+    let b = vec![[1.; N]; n];
+    let mut out = vec![[0.; N]; n];
+    let start = Instant::now();
+    for _ in 0..iters {
+        a.dense_lower_triangular_solve_arr(black_box(&b), &mut out, false);
+        black_box(&out);
+    }
+    let batched = start.elapsed();
+
+    let b = vec![1.; n];
+    let mut out = vec![0.; n];
+    let start = Instant::now();
+    for _ in 0..iters {
+        for _ in 0..N {
+            a.dense_lower_triangular_solve(black_box(&b), &mut out, false);
+            black_box(&out);
+        }
+    }
+    let separate = start.elapsed();
+    println!("N={N}: batched {batched:?}, separate {separate:?}");
+}
+
+#[cfg(feature = "parallel")]
+fn bench_parallel(a: &Csc<F>, iters: usize) {
+    let n = a.ncols();
+    let b = vec![1.; n];
+    let mut out = vec![0.; n];
+    let start = Instant::now();
+    for _ in 0..iters {
+        a.dense_lower_triangular_solve(black_box(&b), &mut out, false);
+        black_box(&out);
+    }
+    let serial = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..iters {
+        a.par_dense_lower_triangular_solve(black_box(&b), &mut out, false);
+        black_box(&out);
+    }
+    let parallel = start.elapsed();
+    println!("serial {serial:?}, parallel {parallel:?}");
+}
+
+fn main() {
+    let a = banded(100_000, &[1, 7, 31]);
+    bench::<4>(&a, 20);
+    bench::<8>(&a, 20);
+    // without the first band, each level has thousands of independent rows.
+    #[cfg(feature = "parallel")]
+    bench_parallel(&banded(100_000, &[5_000, 20_000]), 20);
+}
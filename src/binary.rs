@@ -0,0 +1,146 @@
+use alloc::vec;
+use std::io::{Read, Write};
+
+use super::F;
+use crate::csc::{Csc, CscBuilder};
+
+/// Identifies the binary format written by [`Csc::write_binary`].
+const MAGIC: [u8; 4] = *b"SPLU";
+const VERSION: u8 = 1;
+
+/// An error when reading a matrix with [`Csc::read_binary`].
+#[derive(Debug)]
+pub enum ReadBinaryError {
+    /// The underlying reader failed, including if it ended early.
+    Io(std::io::Error),
+    /// The input does not start with the expected magic bytes.
+    BadMagic,
+    /// The format version is not supported.
+    UnsupportedVersion(u8),
+    /// The size in bytes of each value, which differs from the size of `F`.
+    ValueSize(u8),
+    /// The offsets or row indices are not a valid sparsity pattern for the dimensions.
+    InvalidStructure,
+}
+
+impl core::fmt::Display for ReadBinaryError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ReadBinaryError::Io(e) => write!(f, "failed to read matrix: {e}"),
+            ReadBinaryError::BadMagic => write!(f, "input is not a binary sparse matrix"),
+            ReadBinaryError::UnsupportedVersion(v) => write!(f, "unsupported format version {v}"),
+            ReadBinaryError::ValueSize(s) => write!(
+                f,
+                "values are {s} bytes, but {} bytes are expected",
+                size_of::<F>()
+            ),
+            ReadBinaryError::InvalidStructure => write!(f, "invalid sparsity pattern"),
+        }
+    }
+}
+
+impl core::error::Error for ReadBinaryError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            ReadBinaryError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ReadBinaryError {
+    fn from(e: std::io::Error) -> Self {
+        ReadBinaryError::Io(e)
+    }
+}
+
+fn read_u64(r: &mut impl Read) -> Result<u64, ReadBinaryError> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_usize(r: &mut impl Read) -> Result<usize, ReadBinaryError> {
+    usize::try_from(read_u64(r)?).map_err(|_| ReadBinaryError::InvalidStructure)
+}
+
+impl Csc<F> {
+    /// Writes this matrix in a compact little endian binary format. The header is the magic
+    /// bytes `SPLU`, a version byte and the size in bytes of each value, followed by `nrows`,
+    /// `ncols` and `nnz` as `u64`. Then the `ncols + 1` column offsets and `nnz` row indices
+    /// are written as `u64`, followed by the `nnz` values.
+    pub fn write_binary<W: Write>(&self, mut w: W) -> std::io::Result<()> {
+        w.write_all(&MAGIC)?;
+        w.write_all(&[VERSION, size_of::<F>() as u8])?;
+        for d in [self.nrows(), self.ncols(), self.nnz()] {
+            w.write_all(&(d as u64).to_le_bytes())?;
+        }
+        let mut offset = 0;
+        w.write_all(&0u64.to_le_bytes())?;
+        for c in 0..self.ncols() {
+            offset += self.col(c).1.len();
+            w.write_all(&(offset as u64).to_le_bytes())?;
+        }
+        for [_, r] in self.pattern().entries() {
+            w.write_all(&(r as u64).to_le_bytes())?;
+        }
+        for v in self.values() {
+            w.write_all(&v.to_le_bytes())?;
+        }
+        w.flush()
+    }
+
+    /// Reads a matrix written by [`write_binary`](Self::write_binary), validating the header
+    /// and that the offsets and row indices form a valid sparsity pattern.
+    pub fn read_binary<R: Read>(mut r: R) -> Result<Self, ReadBinaryError> {
+        let mut header = [0; 6];
+        r.read_exact(&mut header)?;
+        if header[..4] != MAGIC {
+            return Err(ReadBinaryError::BadMagic);
+        }
+        if header[4] != VERSION {
+            return Err(ReadBinaryError::UnsupportedVersion(header[4]));
+        }
+        if usize::from(header[5]) != size_of::<F>() {
+            return Err(ReadBinaryError::ValueSize(header[5]));
+        }
+        let nrows = read_usize(&mut r)?;
+        let ncols = read_usize(&mut r)?;
+        let nnz = read_usize(&mut r)?;
+
+        // offsets are checked while reading, so a corrupt header cannot cause a huge
+        // allocation before the input runs out.
+        let mut offsets = vec![];
+        for c in 0..=ncols {
+            let o = read_usize(&mut r)?;
+            let prev = offsets.last().copied().unwrap_or(0);
+            if (c == 0 && o != 0) || o < prev || o > nnz {
+                return Err(ReadBinaryError::InvalidStructure);
+            }
+            offsets.push(o);
+        }
+        if offsets.last() != Some(&nnz) {
+            return Err(ReadBinaryError::InvalidStructure);
+        }
+        let mut rows = vec![];
+        for _ in 0..nnz {
+            let row = read_usize(&mut r)?;
+            if row >= nrows {
+                return Err(ReadBinaryError::InvalidStructure);
+            }
+            rows.push(row);
+        }
+
+        let mut builder = CscBuilder::new(nrows, ncols);
+        let mut buf = [0; size_of::<F>()];
+        for (c, w) in offsets.windows(2).enumerate() {
+            for &row in &rows[w[0]..w[1]] {
+                r.read_exact(&mut buf)?;
+                builder
+                    .insert(row, c, F::from_le_bytes(buf))
+                    .map_err(|_| ReadBinaryError::InvalidStructure)?;
+            }
+        }
+        Ok(builder.build())
+    }
+}
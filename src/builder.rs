@@ -1,17 +1,139 @@
+use super::{I, to_index, to_usize, try_to_index};
+use alloc::vec;
+use alloc::vec::Vec;
+
 /// An error when adding into the SparsityPatternBuilder
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum BuilderInsertError {
-    ///
+    /// Current Major
     MajorTooLow(usize),
     /// Provided Minor, Current Minor
     MinorTooLow(usize, usize),
+    /// Provided Major, Provided Minor, where one is outside of the pattern's dimensions
+    OutOfBounds(usize, usize),
+    /// Number of Minors, Number of Values, which differ
+    LengthMismatch(usize, usize),
+}
+
+impl core::fmt::Display for BuilderInsertError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BuilderInsertError::MajorTooLow(curr) => {
+                write!(f, "major index is lower than the current major {curr}")
+            }
+            BuilderInsertError::MinorTooLow(provided, curr) => write!(
+                f,
+                "minor index {provided} is not greater than the previous minor {curr}"
+            ),
+            BuilderInsertError::OutOfBounds(maj, min) => {
+                write!(f, "index ({maj}, {min}) is outside of the pattern")
+            }
+            BuilderInsertError::LengthMismatch(minors, values) => {
+                write!(f, "{minors} minor indices were given with {values} values")
+            }
+        }
+    }
+}
+
+impl core::error::Error for BuilderInsertError {}
+
+/// An error when building from an incomplete SparsityPatternBuilder
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BuildError {
+    /// Current Major, Last Major
+    Incomplete(usize, usize),
+}
+
+impl core::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BuildError::Incomplete(curr, last) => write!(
+                f,
+                "builder stopped at major {curr}, before reaching the last major {last}"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for BuildError {}
+
+/// An error when validating the arrays of a compressed sparse matrix.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    /// There must be one more offset than majors.
+    OffsetsLength { expected: usize, found: usize },
+    /// The offsets must start at 0, be non-decreasing, and end at the number of indices.
+    InvalidOffsets { major: usize },
+    /// The minor indices of `major` are not strictly increasing.
+    UnsortedIndices { major: usize },
+    /// A minor index in `major` is not less than the minor dimension.
+    IndexOutOfRange { major: usize, minor: usize },
+    /// The number of values differs from the number of indices.
+    ValuesLength { indices: usize, values: usize },
+    /// A dimension or the number of entries does not fit in the index type [`I`].
+    IndexTypeOverflow { value: usize },
+}
+
+impl core::fmt::Display for PatternError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PatternError::OffsetsLength { expected, found } => {
+                write!(f, "expected {expected} offsets, found {found}")
+            }
+            PatternError::InvalidOffsets { major } => {
+                write!(f, "offsets are invalid at major {major}")
+            }
+            PatternError::UnsortedIndices { major } => {
+                write!(f, "indices of major {major} are not strictly increasing")
+            }
+            PatternError::IndexOutOfRange { major, minor } => {
+                write!(f, "index {minor} in major {major} is out of range")
+            }
+            PatternError::ValuesLength { indices, values } => {
+                write!(f, "{values} values do not match {indices} indices")
+            }
+            PatternError::IndexTypeOverflow { value } => {
+                write!(f, "{value} does not fit in the index type")
+            }
+        }
+    }
+}
+
+impl core::error::Error for PatternError {}
+
+/// An error when restoring a SparsityPatternBuilder to a checkpoint
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RestoreError {
+    /// Entries in the checkpoint, Entries in the builder
+    Ahead(usize, usize),
+}
+
+impl core::fmt::Display for RestoreError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RestoreError::Ahead(checkpoint, curr) => write!(
+                f,
+                "checkpoint with {checkpoint} entries is ahead of the builder with {curr} entries"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for RestoreError {}
+
+/// The state of a SparsityPatternBuilder, which it can later be restored to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Length of the major offsets, which is one more than the current major.
+    majors: usize,
+    entries: usize,
 }
 
 /// How the sparsity for a matrix is laid out
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct SparsityPattern {
-    pub(crate) major_offsets: Vec<usize>,
-    pub(crate) minor_indices: Vec<usize>,
+    pub(crate) major_offsets: Vec<I>,
+    pub(crate) minor_indices: Vec<I>,
     pub minor_dim: usize,
 }
 
@@ -22,10 +144,20 @@ impl SparsityPattern {
     }
 
     #[inline]
-    pub fn lane(&self, i: usize) -> &[usize] {
-        let s = self.major_offsets[i];
-        let e = self.major_offsets[i + 1];
-        &self.minor_indices[s..e]
+    pub fn lane(&self, i: usize) -> &[I] {
+        &self.minor_indices[self.lane_range(i)]
+    }
+
+    /// The range of positions in the stored entries that belong to lane `i`.
+    #[inline]
+    pub(crate) fn lane_range(&self, i: usize) -> core::ops::Range<usize> {
+        to_usize(self.major_offsets[i])..to_usize(self.major_offsets[i + 1])
+    }
+
+    /// The minor index of the `j`th stored entry.
+    #[inline]
+    pub(crate) fn minor(&self, j: usize) -> usize {
+        to_usize(self.minor_indices[j])
     }
 
     #[inline]
@@ -34,21 +166,203 @@ impl SparsityPattern {
     }
 
     pub fn entries(&self) -> impl Iterator<Item = [usize; 2]> + '_ {
-        (0..self.major_dim()).flat_map(move |i| {
-            let s = self.major_offsets[i];
-            let e = self.major_offsets[i + 1];
-            (s..e).map(move |j| [i, self.minor_indices[j]])
+        (0..self.major_dim()).flat_map(move |i| self.lane_range(i).map(move |j| [i, self.minor(j)]))
+    }
+    /// Constructs a pattern from its compressed arrays, after checking that they are valid.
+    /// Where the index type is `usize`, the arrays are reused without copying.
+    pub(crate) fn try_from_arrays(
+        major_offsets: Vec<usize>,
+        minor_indices: Vec<usize>,
+        minor_dim: usize,
+    ) -> Result<Self, PatternError> {
+        let Some(major_dim) = major_offsets.len().checked_sub(1) else {
+            return Err(PatternError::OffsetsLength {
+                expected: 1,
+                found: 0,
+            });
+        };
+        // checked separately, as there are no lanes to check it with if `major_dim` is zero.
+        if major_offsets[0] != 0 {
+            return Err(PatternError::InvalidOffsets { major: 0 });
+        }
+        for major in 0..major_dim {
+            let (start, end) = (major_offsets[major], major_offsets[major + 1]);
+            if end < start || end > minor_indices.len() {
+                return Err(PatternError::InvalidOffsets { major });
+            }
+            let lane = &minor_indices[start..end];
+            if !lane.is_sorted_by(|a, b| a < b) {
+                return Err(PatternError::UnsortedIndices { major });
+            }
+            if let Some(&minor) = lane.last().filter(|&&m| m >= minor_dim) {
+                return Err(PatternError::IndexOutOfRange { major, minor });
+            }
+        }
+        if major_offsets[major_dim] != minor_indices.len() {
+            return Err(PatternError::InvalidOffsets { major: major_dim });
+        }
+        // every offset is at most the number of entries, and every index is below minor_dim.
+        for value in [minor_indices.len(), minor_dim] {
+            if try_to_index(value).is_none() {
+                return Err(PatternError::IndexTypeOverflow { value });
+            }
+        }
+        Ok(SparsityPattern {
+            major_offsets: major_offsets.into_iter().map(to_index).collect(),
+            minor_indices: minor_indices.into_iter().map(to_index).collect(),
+            minor_dim,
         })
     }
+
     /// Creates the sparsity pattern of an identity matrix of size `n`.
     pub fn identity(n: usize) -> Self {
         Self {
-            major_offsets: (0..=n).collect(),
-            minor_indices: (0..n).collect(),
+            major_offsets: (0..=n).map(to_index).collect(),
+            minor_indices: (0..n).map(to_index).collect(),
             minor_dim: n,
         }
     }
 
+    /// Returns true if this pattern is square, and each major lane contains exactly its
+    /// diagonal entry.
+    pub fn is_diagonal(&self) -> bool {
+        self.major_dim() == self.minor_dim
+            && (0..self.major_dim()).all(|i| self.lane(i).iter().map(|&m| to_usize(m)).eq([i]))
+    }
+
+    /// The pattern containing the entries of both `self` and `other`, with each lane merged in
+    /// sorted order. This can be used to allocate the pattern of a sum such as `A + B` before
+    /// assembling its values.
+    pub fn union(&self, other: &SparsityPattern) -> SparsityPattern {
+        self.merge(other, |a, b| a || b)
+    }
+
+    /// The pattern containing the entries which are in both `self` and `other`, such as for
+    /// masking one matrix by the pattern of another.
+    pub fn intersection(&self, other: &SparsityPattern) -> SparsityPattern {
+        self.merge(other, |a, b| a && b)
+    }
+
+    /// The pattern containing the entries of `self` which are not in `other`. For example,
+    /// the difference of the pattern of a factor and the original matrix is its fill.
+    pub fn difference(&self, other: &SparsityPattern) -> SparsityPattern {
+        self.merge(other, |a, b| a && !b)
+    }
+
+    /// Merges each lane of `self` and `other` in sorted order, keeping the entries for which
+    /// `keep(in_self, in_other)` is true.
+    fn merge(&self, other: &SparsityPattern, keep: impl Fn(bool, bool) -> bool) -> SparsityPattern {
+        assert_eq!(self.major_dim(), other.major_dim());
+        assert_eq!(self.minor_dim, other.minor_dim);
+        let mut major_offsets = Vec::with_capacity(self.major_offsets.len());
+        major_offsets.push(0);
+        let mut minor_indices = vec![];
+        for maj in 0..self.major_dim() {
+            let (mut a, mut b) = (
+                self.lane(maj).iter().peekable(),
+                other.lane(maj).iter().peekable(),
+            );
+            loop {
+                let (min, in_a, in_b) = match (a.peek(), b.peek()) {
+                    (None, None) => break,
+                    (Some(&&x), Some(&&y)) if x == y => {
+                        a.next();
+                        b.next();
+                        (x, true, true)
+                    }
+                    (Some(&&x), Some(&&y)) if y < x => {
+                        b.next();
+                        (y, false, true)
+                    }
+                    (Some(&&x), _) => {
+                        a.next();
+                        (x, true, false)
+                    }
+                    (None, Some(&&y)) => {
+                        b.next();
+                        (y, false, true)
+                    }
+                };
+                if keep(in_a, in_b) {
+                    minor_indices.push(min);
+                }
+            }
+            major_offsets.push(to_index(minor_indices.len()));
+        }
+        SparsityPattern {
+            major_offsets,
+            minor_indices,
+            minor_dim: self.minor_dim,
+        }
+    }
+
+    /// For each major, the earlier majors which must be solved before it when treating `self`
+    /// as lower triangular, in ascending order. Major `k` must be solved before `j` if lane `k`
+    /// contains minor `j`. Entries in the upper triangle are ignored.
+    ///
+    /// Majors whose dependencies have all been solved can be solved independently of each
+    /// other, which can be used to schedule a triangular solve in parallel.
+    pub fn column_dependencies(&self) -> Vec<Vec<usize>> {
+        let mut deps = vec![vec![]; self.major_dim()];
+        for [maj, min] in self.entries() {
+            if min > maj {
+                deps[min].push(maj);
+            }
+        }
+        deps
+    }
+
+    /// The size of a maximum matching between majors and minors, which is the largest rank
+    /// any matrix with this pattern can have. Computed using augmenting paths.
+    pub fn structural_rank(&self) -> usize {
+        let mut matched_major: Vec<Option<usize>> = vec![None; self.minor_dim];
+        let mut visited = vec![usize::MAX; self.minor_dim];
+        // (major, next position in its lane) along the current path, and the minors it uses.
+        let mut stack: Vec<(usize, usize)> = vec![];
+        let mut via: Vec<usize> = vec![];
+        let mut rank = 0;
+        for start in 0..self.major_dim() {
+            stack.push((start, 0));
+            while let Some(&mut (maj, ref mut pos)) = stack.last_mut() {
+                let Some(&min) = self.lane(maj).get(*pos) else {
+                    stack.pop();
+                    via.pop();
+                    continue;
+                };
+                *pos += 1;
+                let min = to_usize(min);
+                if visited[min] == start {
+                    continue;
+                }
+                visited[min] = start;
+                match matched_major[min] {
+                    Some(next) => {
+                        via.push(min);
+                        stack.push((next, 0));
+                    }
+                    None => {
+                        // each major on the path takes the minor of the major after it.
+                        via.push(min);
+                        for (&(maj, _), &min) in stack.iter().zip(&via) {
+                            matched_major[min] = Some(maj);
+                        }
+                        rank += 1;
+                        stack.clear();
+                        via.clear();
+                    }
+                }
+            }
+        }
+        rank
+    }
+
+    /// Returns true if every matrix with this pattern is rank deficient, because there is no
+    /// matching between majors and minors which covers the smaller dimension. Such a matrix
+    /// cannot be factorized regardless of its values.
+    pub fn is_structurally_singular(&self) -> bool {
+        self.structural_rank() < self.major_dim().min(self.minor_dim)
+    }
+
     /// Computes the output sparsity pattern of `x` in `Ax = b`.
     /// where A's nonzero pattern is given by `self` and the non-zero indices
     /// of vector `b` are specified as a slice.
@@ -59,27 +373,23 @@ impl SparsityPattern {
         assert!(b.iter().all(|&i| i < self.major_dim()));
         out.clear();
 
-        // From a given starting column, traverses and finds all reachable indices.
-        fn reach(sp: &SparsityPattern, j: usize, out: &mut Vec<usize>) {
-            // TODO this may be slow?
-
-            // already traversed
-            if out.contains(&j) {
-                return;
-            }
-
-            out.push(j);
-            for &i in sp.lane(j) {
-                if i < j {
+        // Depth first traversal from each starting column, with an explicit stack so that long
+        // chains of dependencies cannot overflow the call stack.
+        let mut visited = vec![false; self.major_dim()];
+        let mut stack = vec![];
+        for &i in b {
+            stack.push(i);
+            while let Some(j) = stack.pop() {
+                if visited[j] {
                     continue;
                 }
-                reach(sp, i, out);
+                visited[j] = true;
+                out.push(j);
+                // pushed in reverse, so that lower rows are visited first.
+                let lane = self.lane(j).iter().rev().copied().map(to_usize);
+                stack.extend(lane.filter(|&i| i > j && !visited[i]));
             }
         }
-
-        for &i in b {
-            reach(&self, i, out);
-        }
     }
 
     /// Computes the output sparsity pattern of `x` in `Ax = b`.
@@ -107,7 +417,7 @@ impl SparsityPattern {
                 }
 
                 out[j as usize] = true;
-                for &i in self.lane(j as usize) {
+                for i in self.lane(j as usize).iter().copied().map(to_usize) {
                     if (i as u32) < j {
                         continue;
                     }
@@ -127,25 +437,55 @@ impl SparsityPattern {
         assert!(b.iter().all(|&i| i < self.major_dim()));
         out.clear();
 
-        // From a given starting column, traverses and finds all reachable indices.
-        fn reach(sp: &SparsityPattern, j: usize, out: &mut Vec<usize>) {
-            // already traversed
-            if out.contains(&j) {
-                return;
-            }
-
-            out.push(j);
-            // iteration order here does not matter, but technically it should be rev?
-            for &i in sp.lane(j).iter().rev() {
-                if i > j {
+        // Depth first traversal from each starting column, with an explicit stack so that long
+        // chains of dependencies cannot overflow the call stack.
+        let mut visited = vec![false; self.major_dim()];
+        let mut stack = vec![];
+        for &i in b {
+            stack.push(i);
+            while let Some(j) = stack.pop() {
+                if visited[j] {
                     continue;
                 }
-                reach(sp, i, out);
+                visited[j] = true;
+                out.push(j);
+                // pushed in ascending order, so that higher rows are visited first.
+                let lane = self.lane(j).iter().copied().map(to_usize);
+                stack.extend(lane.filter(|&i| i < j && !visited[i]));
             }
         }
+    }
+
+    /// Computes the output sparsity pattern of `x` in `Ax = b`, marking each index of `out`
+    /// which can be nonzero. This is the same as
+    /// [`sparse_upper_triangular_solve`](Self::sparse_upper_triangular_solve), but without
+    /// allocating.
+    /// Treats `self` as upper triangular, even if there are elements in the lower triangle.
+    pub(crate) fn sparse_upper_triangular_solve_bool(
+        &self,
+        b: &[usize],
+        out: &mut [bool],
+        stack: &mut Vec<u32>,
+    ) {
+        assert!(stack.is_empty());
+        out.fill(false);
 
         for &i in b {
-            reach(&self, i, out);
+            stack.push(i as u32);
+            while let Some(j) = stack.pop() {
+                // already traversed
+                if out[j as usize] {
+                    continue;
+                }
+
+                out[j as usize] = true;
+                for i in self.lane(j as usize).iter().copied().map(to_usize) {
+                    if (i as u32) > j {
+                        break;
+                    }
+                    stack.push(i as u32);
+                }
+            }
         }
     }
 }
@@ -177,10 +517,17 @@ impl SparsityPatternBuilder {
         self.buf.minor_indices.len()
     }
 
+    /// The number of entries inserted into the current major.
+    fn current_lane_len(&self) -> usize {
+        self.num_entries() - to_usize(*self.buf.major_offsets.last().unwrap())
+    }
+
     /// Allows for general assignment of indices
     pub fn insert(&mut self, maj: usize, min: usize) -> Result<(), BuilderInsertError> {
-        assert!(maj < self.major_dim);
-        assert!(min < self.buf.minor_dim);
+        if maj >= self.major_dim || min >= self.buf.minor_dim {
+            return Err(BuilderInsertError::OutOfBounds(maj, min));
+        }
+        let min = to_index(min);
 
         let curr_major = self.buf.major_dim();
 
@@ -190,18 +537,17 @@ impl SparsityPatternBuilder {
         }
         // cannot go backwards in minor
         if maj == curr_major
-            && *self.buf.major_offsets.last().unwrap() < self.buf.minor_indices.len()
-            && !self.buf.minor_indices.is_empty()
+            && self.current_lane_len() > 0
             && min <= *self.buf.minor_indices.last().unwrap()
         {
             return Err(BuilderInsertError::MinorTooLow(
-                min,
-                *self.buf.minor_indices.last().unwrap(),
+                to_usize(min),
+                to_usize(*self.buf.minor_indices.last().unwrap()),
             ));
         }
         // add any advances in row.
         for _ in curr_major..maj {
-            self.buf.major_offsets.push(self.buf.minor_indices.len());
+            self.buf.major_offsets.push(to_index(self.num_entries()));
         }
         self.buf.minor_indices.push(min);
         Ok(())
@@ -210,9 +556,8 @@ impl SparsityPatternBuilder {
     pub fn insert_sum(&mut self, maj: usize, min: usize) -> Result<bool, BuilderInsertError> {
         let curr_major = self.buf.major_dim();
         if maj == curr_major
-            && *self.buf.major_offsets.last().unwrap() < self.buf.minor_indices.len()
-            && !self.buf.minor_indices.is_empty()
-            && min == *self.buf.minor_indices.last().unwrap()
+            && self.current_lane_len() > 0
+            && min == to_usize(*self.buf.minor_indices.last().unwrap())
         {
             return Ok(true);
         }
@@ -236,24 +581,102 @@ impl SparsityPatternBuilder {
     pub fn build(mut self) -> SparsityPattern {
         self.buf
             .major_offsets
-            .resize(self.major_dim + 1, self.buf.minor_indices.len());
+            .resize(self.major_dim + 1, to_index(self.num_entries()));
         debug_assert_eq!(self.buf.major_dim(), self.major_dim);
         self.buf
     }
 
+    /// Consumes self and outputs the constructed `SparsityPattern`, failing if no entries
+    /// were inserted into the last major. Unlike [`build`](Self::build), this distinguishes
+    /// a builder which was not finished from one with intentionally empty trailing majors,
+    /// which must then be built leniently.
+    pub fn build_strict(self) -> Result<SparsityPattern, BuildError> {
+        self.check_complete()?;
+        Ok(self.build())
+    }
+
+    /// Checks that entries have been inserted up to the last major.
+    pub(crate) fn check_complete(&self) -> Result<(), BuildError> {
+        let last = self.major_dim.saturating_sub(1);
+        if self.current_major() < last {
+            return Err(BuildError::Incomplete(self.current_major(), last));
+        }
+        Ok(())
+    }
+
+    /// Captures the current state, so that entries inserted after it can be undone with
+    /// [`restore`](Self::restore).
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            majors: self.buf.major_offsets.len(),
+            entries: self.num_entries(),
+        }
+    }
+
+    /// Restores `self` to `checkpoint`, deleting any entries inserted after it was taken.
+    /// Fails without modifying `self` if the checkpoint is ahead of the current state, such
+    /// as after reverting past it.
+    pub fn restore(&mut self, checkpoint: Checkpoint) -> Result<(), RestoreError> {
+        let Checkpoint { majors, entries } = checkpoint;
+        let offsets = &self.buf.major_offsets;
+        if majors > offsets.len()
+            || entries > self.num_entries()
+            || to_usize(offsets[majors - 1]) > entries
+        {
+            return Err(RestoreError::Ahead(entries, self.num_entries()));
+        }
+        self.buf.major_offsets.truncate(majors);
+        self.buf.minor_indices.truncate(entries);
+        Ok(())
+    }
+
     /// Reverts the major index of `self` back to `maj`, deleting any entries ahead of it.
     /// Preserves entries in `maj`.
     pub fn revert_to_major(&mut self, maj: usize) -> bool {
         // preserve maj + 1 elements in self
-        if self.buf.major_offsets.len() + 1 <= maj {
+        let len = self.buf.major_offsets.len();
+        if len < maj + 1 {
             return false;
         }
-        let last = self.buf.major_offsets[maj + 1];
+        // `maj` is the current major, so there is nothing ahead of it.
+        if len == maj + 1 {
+            return true;
+        }
+        let last = to_usize(self.buf.major_offsets[maj + 1]);
         self.buf.major_offsets.truncate(maj + 1);
-        self.buf.minor_indices.truncate(last + 1);
+        self.buf.minor_indices.truncate(last);
         true
     }
 
+    /// Checks that the offsets start at 0 and are monotonic, and that the minors of each major
+    /// are strictly ascending and within bounds. Only checked in debug builds.
+    pub fn assert_valid(&self) {
+        if !cfg!(debug_assertions) {
+            return;
+        }
+        let offsets = &self.buf.major_offsets;
+        assert_eq!(offsets.first(), Some(&0), "major offsets must start at 0");
+        assert!(
+            offsets.len() <= self.major_dim + 1,
+            "too many major offsets"
+        );
+        assert!(offsets.is_sorted(), "major offsets are not monotonic");
+        let end = to_index(self.num_entries());
+        assert!(
+            *offsets.last().unwrap() <= end,
+            "major offsets exceed the entries"
+        );
+        let ends = offsets.iter().skip(1).chain(core::iter::once(&end));
+        for (&s, &e) in offsets.iter().zip(ends) {
+            let lane = &self.buf.minor_indices[to_usize(s)..to_usize(e)];
+            assert!(lane.is_sorted_by(|a, b| a < b), "minors are not ascending");
+            assert!(
+                lane.iter().all(|&m| to_usize(m) < self.buf.minor_dim),
+                "minor index out of bounds"
+            );
+        }
+    }
+
     /// Allows for rebuilding part of a sparsity pattern, assuming that
     /// items after maj_start have not been filled in.
     pub fn from(sp: SparsityPattern) -> Self {
@@ -0,0 +1,176 @@
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::{F, to_index};
+use crate::csc::{Csc, CscBuilder};
+
+/// An error while computing a Cholesky factorization.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CholeskyError {
+    /// The pivot of `column` was `value`, which is not positive, so the matrix is not
+    /// positive definite.
+    NotPositive { column: usize, value: F },
+    /// Factorizing in place would fill in `(row, col)`, which is not stored in the lower
+    /// triangle of the matrix.
+    MissingEntry { row: usize, col: usize },
+}
+
+impl core::fmt::Display for CholeskyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CholeskyError::NotPositive { column, value } => {
+                write!(
+                    f,
+                    "pivot of column {column} is {value}, which is not positive"
+                )
+            }
+            CholeskyError::MissingEntry { row, col } => {
+                write!(f, "fill entry ({row}, {col}) is not stored")
+            }
+        }
+    }
+}
+
+impl core::error::Error for CholeskyError {}
+
+/// A Cholesky factorization `A = LLᵀ` of a symmetric positive definite matrix, where only the
+/// lower triangle of `A` is read.
+#[derive(Debug, Clone)]
+pub struct CholeskyFactorization {
+    l: Csc<F>,
+}
+
+impl CholeskyFactorization {
+    /// Factorizes `a`, allocating the factor with room for all of its fill.
+    pub fn new(a: &Csc<F>) -> Result<Self, CholeskyError> {
+        let n = a.ncols();
+        assert_eq!(n, a.nrows());
+        // The pattern of column `j` of `L` is the lower part of column `j` of `A`, merged
+        // with the patterns of its children in the elimination tree.
+        let mut cols: Vec<Vec<usize>> = Vec::with_capacity(n);
+        let mut children: Vec<Vec<usize>> = vec![vec![]; n];
+        for j in 0..n {
+            let mut rows: BTreeSet<usize> =
+                a.col_iter(j).map(|(r, _)| r).filter(|&r| r >= j).collect();
+            rows.insert(j);
+            for &k in &children[j] {
+                rows.extend(cols[k][1..].iter().copied().filter(|&r| r != j));
+            }
+            let rows: Vec<usize> = rows.into_iter().collect();
+            if let Some(&parent) = rows.get(1) {
+                children[parent].push(j);
+            }
+            cols.push(rows);
+        }
+
+        let mut builder = CscBuilder::new(n, n);
+        for (c, rows) in cols.iter().enumerate() {
+            for &r in rows {
+                let ins = builder.insert(r, c, a.get(r, c).copied().unwrap_or(0.));
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        let mut l = builder.build();
+        Self::factor_in_place(&mut l)?;
+        Ok(Self { l })
+    }
+
+    /// Overwrites the lower triangle of `a` with `L`, without allocating a separate factor,
+    /// which saves memory for large systems. Entries above the diagonal are left unchanged,
+    /// and are ignored by the lower triangular solves on [`Csc`].
+    ///
+    /// The lower triangle must already store every entry of `L`, so this returns an error if
+    /// a fill entry is missing. Returns an error identifying the column if a pivot is not
+    /// positive, in which case `a` is left partially factorized.
+    pub fn factor_in_place(a: &mut Csc<F>) -> Result<(), CholeskyError> {
+        let n = a.ncols();
+        assert_eq!(n, a.nrows());
+        let (pattern, vals) = a.0.parts_mut();
+
+        // dense accumulator for the current column, and which column last stored each row.
+        let mut x = vec![0.; n];
+        let mut mark = vec![usize::MAX; n];
+        // `next[k]` is the position of the next entry of column `k` to be applied. Each
+        // factorized column is kept in the linked list of the row of that entry.
+        let mut next = vec![0; n];
+        let mut head = vec![usize::MAX; n];
+        let mut link = vec![usize::MAX; n];
+
+        for j in 0..n {
+            let range = pattern.lane_range(j);
+            let diag = match pattern.lane(j).binary_search(&to_index(j)) {
+                Ok(d) => range.start + d,
+                Err(_) => {
+                    return Err(CholeskyError::NotPositive {
+                        column: j,
+                        value: 0.,
+                    });
+                }
+            };
+            for q in diag..range.end {
+                x[pattern.minor(q)] = vals[q];
+                mark[pattern.minor(q)] = j;
+            }
+
+            // subtract L[j.., k] L[j, k] for each column `k` with an entry in row `j`.
+            let mut k = core::mem::replace(&mut head[j], usize::MAX);
+            while k != usize::MAX {
+                let next_k = link[k];
+                let end = pattern.lane_range(k).end;
+                let start = next[k];
+                let l_jk = vals[start];
+                for (q, &v) in vals[start..end].iter().enumerate() {
+                    let r = pattern.minor(start + q);
+                    if mark[r] != j {
+                        return Err(CholeskyError::MissingEntry { row: r, col: j });
+                    }
+                    x[r] -= v * l_jk;
+                }
+                next[k] += 1;
+                if next[k] < end {
+                    let r = pattern.minor(next[k]);
+                    link[k] = head[r];
+                    head[r] = k;
+                }
+                k = next_k;
+            }
+
+            let d = x[j];
+            if !(d > 0. && d.is_finite()) {
+                return Err(CholeskyError::NotPositive {
+                    column: j,
+                    value: d,
+                });
+            }
+            let l_jj = d.sqrt();
+            vals[diag] = l_jj;
+            for q in diag + 1..range.end {
+                vals[q] = x[pattern.minor(q)] / l_jj;
+            }
+            for q in diag..range.end {
+                x[pattern.minor(q)] = 0.;
+            }
+
+            next[j] = diag + 1;
+            if next[j] < range.end {
+                let r = pattern.minor(next[j]);
+                link[j] = head[r];
+                head[r] = j;
+            }
+        }
+        Ok(())
+    }
+
+    /// The lower triangular factor `L`.
+    pub fn l(&self) -> &Csc<F> {
+        &self.l
+    }
+
+    /// Computes `x` in `Ax = b`, where `b` is a dense vector.
+    /// The output will be stored in b, and buf is used as a temporary buffer.
+    pub fn solve(&self, b: &mut [F], buf: &mut [F]) {
+        self.l.dense_lower_triangular_solve(b, buf, false);
+        self.l.dense_lower_triangular_transpose_solve(buf, b, false);
+    }
+}
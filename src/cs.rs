@@ -1,4 +1,9 @@
-use super::{BuilderInsertError, SparsityPattern, SparsityPatternBuilder};
+use super::{
+    BuildError, BuilderInsertError, Checkpoint, I, RestoreError, SparsityPattern,
+    SparsityPatternBuilder, to_index,
+};
+use alloc::vec;
+use alloc::vec::Vec;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CsMatrix<T> {
@@ -7,23 +12,32 @@ pub struct CsMatrix<T> {
 }
 
 impl<T> CsMatrix<T> {
-    pub fn lane(&self, i: usize) -> (&[T], &[usize]) {
-        let s = self.pattern.major_offsets[i];
-        let e = self.pattern.major_offsets[i + 1];
-        (&self.values[s..e], &self.pattern.minor_indices[s..e])
-    }
-    /*
-    pub(crate) fn lane_mut(&mut self, i: usize) -> (&mut [T], &mut [usize]) {
-        let s = self.pattern.major_offsets[i];
-        let e = self.pattern.major_offsets[i + 1];
-        (
-            &mut self.values[s..e],
-            &mut self.pattern.minor_indices[s..e],
-        )
-    }
-    */
+    pub fn lane(&self, i: usize) -> (&[T], &[I]) {
+        let r = self.pattern.lane_range(i);
+        (&self.values[r.clone()], &self.pattern.minor_indices[r])
+    }
+    /// Mutable access to the values of a lane. The minor indices cannot be modified, since
+    /// that could break the ordering of the sparsity pattern.
+    pub fn lane_mut(&mut self, i: usize) -> (&mut [T], &[I]) {
+        let r = self.pattern.lane_range(i);
+        (&mut self.values[r.clone()], &self.pattern.minor_indices[r])
+    }
+
+    /// The sparsity pattern together with mutable access to the values, so that values can
+    /// be updated while looking up the positions of other entries.
+    pub(crate) fn parts_mut(&mut self) -> (&SparsityPattern, &mut [T]) {
+        (&self.pattern, &mut self.values)
+    }
+
+    /// Releases any excess capacity of the stored entries.
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.pattern.major_offsets.shrink_to_fit();
+        self.pattern.minor_indices.shrink_to_fit();
+        self.values.shrink_to_fit();
+    }
 
     pub(crate) fn swap_minor(&mut self, a: usize, b: usize) {
+        let (a, b) = (to_index(a), to_index(b));
         for i in self.pattern.minor_indices.iter_mut() {
             if *i == a {
                 *i = b;
@@ -33,8 +47,7 @@ impl<T> CsMatrix<T> {
         }
         // for each major, resort the indices
         for i in 0..self.pattern.major_dim() {
-            let s = self.pattern.major_offsets[i];
-            let e = self.pattern.major_offsets[i + 1];
+            let core::ops::Range { start: s, end: e } = self.pattern.lane_range(i);
             for idx in s..e.saturating_sub(1) {
                 let mi = &self.pattern.minor_indices;
                 if mi[idx] > mi[idx + 1] {
@@ -52,13 +65,21 @@ impl<T> CsMatrix<T> {
             }
         }
     }
-    pub fn lane_iter(
-        &self,
+    pub fn lane_iter(&self, i: usize) -> impl DoubleEndedIterator<Item = (usize, &T)> + '_ {
+        self.pattern
+            .lane_range(i)
+            .map(|i| (self.pattern.minor(i), &self.values[i]))
+    }
+
+    pub fn lane_iter_mut(
+        &mut self,
         i: usize,
-    ) -> impl Iterator<Item = (usize, &T)> + DoubleEndedIterator + '_ {
-        let s = self.pattern.major_offsets[i];
-        let e = self.pattern.major_offsets[i + 1];
-        (s..e).map(|i| (self.pattern.minor_indices[i], &self.values[i]))
+    ) -> impl DoubleEndedIterator<Item = (usize, &mut T)> + '_ {
+        let (vals, idxs) = self.lane_mut(i);
+        idxs.iter()
+            .copied()
+            .map(super::to_usize)
+            .zip(vals.iter_mut())
     }
 
     pub fn values(&self) -> &[T] {
@@ -68,6 +89,74 @@ impl<T> CsMatrix<T> {
     pub fn values_mut(&mut self) -> &mut [T] {
         &mut self.values
     }
+
+    /// Appends a new major lane after the last one. `minors` must be strictly ascending.
+    pub(crate) fn push_lane(&mut self, minors: &[usize], vals: &[T])
+    where
+        T: Clone,
+    {
+        debug_assert!(minors.is_sorted_by(|a, b| a < b));
+        debug_assert_eq!(minors.len(), vals.len());
+        self.pattern
+            .minor_indices
+            .extend(minors.iter().map(|&m| to_index(m)));
+        self.pattern
+            .major_offsets
+            .push(to_index(self.pattern.minor_indices.len()));
+        self.values.extend_from_slice(vals);
+    }
+
+    /// Swaps the roles of majors and minors, converting between CSC and CSR storage of the same
+    /// matrix. This is a counting sort over the minors, so it takes `O(nnz + major + minor)`.
+    pub(crate) fn transpose(&self) -> CsMatrix<T>
+    where
+        T: Clone,
+    {
+        let pattern = &self.pattern;
+        let mut major_offsets = vec![0 as I; pattern.minor_dim + 1];
+        for &m in &pattern.minor_indices {
+            major_offsets[super::to_usize(m) + 1] += 1;
+        }
+        for i in 0..pattern.minor_dim {
+            major_offsets[i + 1] += major_offsets[i];
+        }
+
+        // next free position in each new lane, as majors are visited in ascending order the
+        // new minors are inserted in ascending order too.
+        let mut next = major_offsets.clone();
+        let mut minor_indices = vec![0 as I; pattern.nnz()];
+        let mut src = vec![0; pattern.nnz()];
+        for maj in 0..pattern.major_dim() {
+            for j in pattern.lane_range(maj) {
+                let dst = &mut next[pattern.minor(j)];
+                minor_indices[super::to_usize(*dst)] = to_index(maj);
+                src[super::to_usize(*dst)] = j;
+                *dst += 1;
+            }
+        }
+        CsMatrix {
+            pattern: SparsityPattern {
+                major_offsets,
+                minor_indices,
+                minor_dim: pattern.major_dim(),
+            },
+            values: src.into_iter().map(|j| self.values[j].clone()).collect(),
+        }
+    }
+
+    /// Constructs a matrix from a pattern and its values, in the same order as its entries.
+    pub(crate) fn from_parts(pattern: SparsityPattern, values: Vec<T>) -> Self {
+        assert_eq!(pattern.nnz(), values.len());
+        CsMatrix { pattern, values }
+    }
+
+    /// Constructs a new matrix with the same sparsity pattern, with `f` applied to each value.
+    pub fn map_values<U>(&self, f: impl FnMut(&T) -> U) -> CsMatrix<U> {
+        CsMatrix {
+            pattern: self.pattern.clone(),
+            values: self.values.iter().map(f).collect(),
+        }
+    }
 }
 
 impl CsMatrix<super::F> {
@@ -97,10 +186,29 @@ impl<T> CsBuilder<T> {
     pub fn from_mat(mat: CsMatrix<T>) -> Self {
         let CsMatrix { pattern, values } = mat;
 
-        CsBuilder {
+        let builder = CsBuilder {
             sparsity_builder: SparsityPatternBuilder::from(pattern),
             values,
-        }
+        };
+        builder.assert_valid();
+        builder
+    }
+    /// Checks the invariants of the underlying sparsity pattern builder, and that there is
+    /// one value per entry. Only checked in debug builds.
+    pub fn assert_valid(&self) {
+        self.sparsity_builder.assert_valid();
+        debug_assert_eq!(self.values.len(), self.sparsity_builder.num_entries());
+    }
+    /// Captures the current state of the builder, to later [`restore`](Self::restore) to.
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        self.sparsity_builder.checkpoint()
+    }
+    /// Discards everything inserted since `checkpoint`, along with its values.
+    pub(crate) fn restore(&mut self, checkpoint: Checkpoint) -> Result<(), RestoreError> {
+        self.sparsity_builder.restore(checkpoint)?;
+        self.values.truncate(self.sparsity_builder.num_entries());
+        self.assert_valid();
+        Ok(())
     }
     /// Backtracks to a given major index
     pub fn revert_to_major(&mut self, maj: usize) -> bool {
@@ -109,6 +217,7 @@ impl<T> CsBuilder<T> {
         }
 
         self.values.truncate(self.sparsity_builder.num_entries());
+        self.assert_valid();
         true
     }
     pub(crate) fn insert(
@@ -117,9 +226,18 @@ impl<T> CsBuilder<T> {
         min: usize,
         val: T,
     ) -> Result<(), BuilderInsertError> {
+        self.insert_at(maj, min, val).map(|_| ())
+    }
+    /// Inserts a value, returning its position in the values of the built matrix.
+    pub(crate) fn insert_at(
+        &mut self,
+        maj: usize,
+        min: usize,
+        val: T,
+    ) -> Result<usize, BuilderInsertError> {
         self.sparsity_builder.insert(maj, min)?;
         self.values.push(val);
-        Ok(())
+        Ok(self.values.len() - 1)
     }
     pub(crate) fn insert_sum(
         &mut self,
@@ -140,6 +258,11 @@ impl<T> CsBuilder<T> {
             Ok(false)
         }
     }
+    /// Builds the matrix, failing if no entries were inserted into the last major.
+    pub fn build_checked(self) -> Result<CsMatrix<T>, BuildError> {
+        self.sparsity_builder.check_complete()?;
+        Ok(self.build())
+    }
     pub fn build(self) -> CsMatrix<T> {
         let CsBuilder {
             sparsity_builder,
@@ -1,22 +1,132 @@
-use super::F;
+use super::{F, I, to_usize};
+use alloc::vec;
+use alloc::vec::Vec;
 
 use super::cs::{CsBuilder, CsMatrix};
-use super::{BuilderInsertError, SparsityPattern};
+use super::{
+    BuildError, BuilderInsertError, Checkpoint, PatternError, RestoreError, SparsityPattern,
+};
+use crate::csr::Csr;
+
+/// An error when a matrix does not have the structure required by a solve.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TriangularError {
+    /// There is an entry at `(row, col)` outside of the expected triangle.
+    NotTriangular { row: usize, col: usize },
+}
+
+impl core::fmt::Display for TriangularError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TriangularError::NotTriangular { row, col } => {
+                write!(
+                    f,
+                    "entry ({row}, {col}) is outside of the expected triangle"
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for TriangularError {}
+
+/// An error when the diagonal of a matrix is not positive.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DiagonalError {
+    /// The diagonal entry at `index` is missing, or is not a positive finite value.
+    NotPositive { index: usize, value: F },
+}
+
+impl core::fmt::Display for DiagonalError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DiagonalError::NotPositive { index, value } => {
+                write!(
+                    f,
+                    "diagonal entry {index} is {value}, which is not positive"
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for DiagonalError {}
+
+/// An error when replacing the entries of a column.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SetColumnError {
+    /// The new rows of `col` differ from its stored rows, and the pattern cannot be changed.
+    PatternMismatch { col: usize },
+    /// The new rows of `col` are not strictly increasing, or are out of bounds.
+    InvalidRows { col: usize },
+}
+
+impl core::fmt::Display for SetColumnError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SetColumnError::PatternMismatch { col } => {
+                write!(f, "new rows of column {col} do not match its pattern")
+            }
+            SetColumnError::InvalidRows { col } => write!(
+                f,
+                "new rows of column {col} are not strictly increasing and in bounds"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for SetColumnError {}
 
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Csc<T>(CsMatrix<T>);
+pub struct Csc<T>(pub(crate) CsMatrix<T>);
+
+/// How entries with the same coordinates are combined when constructing from triplets.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Duplicates are an error.
+    #[default]
+    Error,
+    /// Duplicates are summed.
+    Sum,
+    /// The first of the duplicates in the input order is kept.
+    KeepFirst,
+    /// The last of the duplicates in the input order is kept.
+    KeepLast,
+}
+
+/// Whether zero values are stored when constructing from dense input.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum ZeroPolicy {
+    /// Every value is stored, including zeros.
+    Keep,
+    /// Values which are exactly zero are not stored.
+    #[default]
+    Drop,
+}
 
 impl<T> Csc<T> {
+    /// Number of columns, which is the major dimension of the sparsity pattern.
     pub fn ncols(&self) -> usize {
         self.0.pattern.major_dim()
     }
+    /// Number of rows, which is the minor dimension of the sparsity pattern.
     pub fn nrows(&self) -> usize {
         self.0.pattern.minor_dim
     }
-    pub fn col_iter(
-        &self,
-        i: usize,
-    ) -> impl Iterator<Item = (usize, &T)> + DoubleEndedIterator + '_ {
+    /// Alias of [`ncols`](Self::ncols).
+    pub fn num_cols(&self) -> usize {
+        self.ncols()
+    }
+    /// Alias of [`nrows`](Self::nrows).
+    pub fn num_rows(&self) -> usize {
+        self.nrows()
+    }
+    /// Returns `(nrows, ncols)`, in the same order as the dimensions passed to constructors
+    /// such as [`from_triplets`](Self::from_triplets) and [`CscBuilder::new`].
+    pub fn shape(&self) -> (usize, usize) {
+        (self.nrows(), self.ncols())
+    }
+    pub fn col_iter(&self, i: usize) -> impl DoubleEndedIterator<Item = (usize, &T)> + '_ {
         self.0.lane_iter(i)
     }
     pub fn pattern(&self) -> &SparsityPattern {
@@ -27,14 +137,235 @@ impl<T> Csc<T> {
         self.0.swap_minor(a, b);
     }
 
-    pub fn col(&self, i: usize) -> (&[T], &[usize]) {
+    pub fn col(&self, i: usize) -> (&[T], &[I]) {
         self.0.lane(i)
     }
-    /*
-    pub(crate) fn col_mut(&mut self, i: usize) -> (&mut [T], &mut [usize]) {
+    /// Returns the value stored at `(row, col)`, if any.
+    pub fn get(&self, row: usize, col: usize) -> Option<&T> {
+        let (vals, rows) = self.col(col);
+        let i = rows.binary_search_by(|&r| to_usize(r).cmp(&row)).ok()?;
+        Some(&vals[i])
+    }
+    /// Mutable access to the values of column `i`, along with their row indices.
+    pub fn col_mut(&mut self, i: usize) -> (&mut [T], &[I]) {
         self.0.lane_mut(i)
     }
-    */
+    /// Iterates over each `(row, &mut value)` in column `i`.
+    pub fn col_iter_mut(
+        &mut self,
+        i: usize,
+    ) -> impl DoubleEndedIterator<Item = (usize, &mut T)> + '_ {
+        self.0.lane_iter_mut(i)
+    }
+    /// Overwrites the values of column `col`, where `rows` must be exactly its stored rows.
+    /// Returns an error without modifying `self` if they differ. This is cheap, so it is
+    /// suited to repeatedly updating columns with a fixed pattern, such as when assembling a
+    /// Jacobian by finite differences.
+    pub fn set_column(
+        &mut self,
+        col: usize,
+        rows: &[usize],
+        vals: &[T],
+    ) -> Result<(), SetColumnError>
+    where
+        T: Clone,
+    {
+        assert_eq!(rows.len(), vals.len());
+        let (col_vals, col_rows) = self.col_mut(col);
+        if col_rows.len() != rows.len()
+            || col_rows.iter().zip(rows).any(|(&a, &b)| to_usize(a) != b)
+        {
+            return Err(SetColumnError::PatternMismatch { col });
+        }
+        col_vals.clone_from_slice(vals);
+        Ok(())
+    }
+    /// Replaces column `col` with the entries at `rows`, which must be strictly increasing,
+    /// changing the pattern if needed. This rebuilds the matrix unless the pattern is unchanged.
+    pub fn replace_column(
+        &mut self,
+        col: usize,
+        rows: &[usize],
+        vals: &[T],
+    ) -> Result<(), SetColumnError>
+    where
+        T: Clone,
+    {
+        assert_eq!(rows.len(), vals.len());
+        if !rows.is_sorted_by(|a, b| a < b) || rows.last().is_some_and(|&r| r >= self.nrows()) {
+            return Err(SetColumnError::InvalidRows { col });
+        }
+        if self.set_column(col, rows, vals).is_ok() {
+            return Ok(());
+        }
+        let mut builder = CscBuilder::new(self.nrows(), self.ncols());
+        for c in 0..self.ncols() {
+            if c == col {
+                for (&r, v) in rows.iter().zip(vals) {
+                    let ins = builder.insert(r, c, v.clone());
+                    debug_assert_eq!(ins, Ok(()));
+                }
+                continue;
+            }
+            for (r, v) in self.col_iter(c) {
+                let ins = builder.insert(r, c, v.clone());
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        *self = builder.build();
+        Ok(())
+    }
+
+    /// Returns the lower triangle of this matrix, including the diagonal.
+    pub fn lower_triangle(&self) -> Self
+    where
+        T: Clone,
+    {
+        self.filter_entries(|r, c| r >= c)
+    }
+
+    /// Returns the upper triangle of this matrix, including the diagonal.
+    pub fn upper_triangle(&self) -> Self
+    where
+        T: Clone,
+    {
+        self.filter_entries(|r, c| r <= c)
+    }
+
+    /// Returns the block of this matrix in the given ranges of rows and columns.
+    pub fn submatrix(&self, rows: core::ops::Range<usize>, cols: core::ops::Range<usize>) -> Self
+    where
+        T: Clone,
+    {
+        assert!(rows.end <= self.nrows() && cols.end <= self.ncols());
+        let mut builder = CscBuilder::new(rows.len(), cols.len());
+        for c in cols.clone() {
+            for (r, v) in self.col_iter(c).filter(|(r, _)| rows.contains(r)) {
+                let ins = builder.insert(r - rows.start, c - cols.start, v.clone());
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        builder.build()
+    }
+
+    /// Returns this matrix with column `col` removed, shifting later columns left by one.
+    pub fn without_column(&self, col: usize) -> Self
+    where
+        T: Clone,
+    {
+        assert!(col < self.ncols());
+        let mut builder = CscBuilder::new(self.nrows(), self.ncols() - 1);
+        for c in (0..self.ncols()).filter(|&c| c != col) {
+            for (r, v) in self.col_iter(c) {
+                let ins = builder.insert(r, c - usize::from(c > col), v.clone());
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        builder.build()
+    }
+
+    /// Returns this matrix with row `row` removed, shifting later rows up by one.
+    pub fn without_row(&self, row: usize) -> Self
+    where
+        T: Clone,
+    {
+        assert!(row < self.nrows());
+        let mut builder = CscBuilder::new(self.nrows() - 1, self.ncols());
+        for c in 0..self.ncols() {
+            for (r, v) in self.col_iter(c).filter(|&(r, _)| r != row) {
+                let ins = builder.insert(r - usize::from(r > row), c, v.clone());
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        builder.build()
+    }
+
+    /// Returns the matrix `B` with `B[i][j] = A[row_perm[i]][col_perm[j]]`, where both
+    /// `row_perm` and `col_perm` map each new index to the original index.
+    pub fn permute(&self, row_perm: &[usize], col_perm: &[usize]) -> Self
+    where
+        T: Clone,
+    {
+        assert_eq!(row_perm.len(), self.nrows());
+        assert_eq!(col_perm.len(), self.ncols());
+        let mut inv_row = vec![0; row_perm.len()];
+        for (i, &r) in row_perm.iter().enumerate() {
+            inv_row[r] = i;
+        }
+        let mut builder = CscBuilder::new(self.nrows(), self.ncols());
+        let mut col = vec![];
+        for (j, &c) in col_perm.iter().enumerate() {
+            col.clear();
+            col.extend(self.col_iter(c).map(|(r, v)| (inv_row[r], v)));
+            col.sort_unstable_by_key(|&(r, _)| r);
+            for &(r, v) in &col {
+                let ins = builder.insert(r, j, v.clone());
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        builder.build()
+    }
+
+    /// Returns `PAPᵀ`, which applies `perm` symmetrically to both rows and columns like
+    /// [`permute`](Self::permute), so that the diagonal stays on the diagonal.
+    pub fn permute_symmetric(&self, perm: &[usize]) -> Self
+    where
+        T: Clone,
+    {
+        self.permute(perm, perm)
+    }
+
+    /// The lower and upper bandwidth, which are the largest distances of a stored entry below
+    /// and above the diagonal respectively.
+    pub fn bandwidth(&self) -> (usize, usize) {
+        self.pattern().entries().fold((0, 0), |(lo, up), [c, r]| {
+            (lo.max(r.saturating_sub(c)), up.max(c.saturating_sub(r)))
+        })
+    }
+
+    /// The bandwidth that [`permute_symmetric(perm)`](Self::permute_symmetric) would have,
+    /// computed without building the permuted matrix, to cheaply compare orderings.
+    pub fn reordered_bandwidth(&self, perm: &[usize]) -> (usize, usize) {
+        assert_eq!(self.nrows(), self.ncols());
+        assert_eq!(perm.len(), self.ncols());
+        let mut inv = vec![0; perm.len()];
+        for (i, &p) in perm.iter().enumerate() {
+            inv[p] = i;
+        }
+        self.pattern()
+            .entries()
+            .map(|[c, r]| [inv[c], inv[r]])
+            .fold((0, 0), |(lo, up), [c, r]| {
+                (lo.max(r.saturating_sub(c)), up.max(c.saturating_sub(r)))
+            })
+    }
+
+    /// Returns every stored entry as `([col, row], value)`, in the same layout accepted by
+    /// [`from_triplets`](Self::from_triplets), sorted by column and then row.
+    pub fn to_triplets(&self) -> Vec<([usize; 2], T)>
+    where
+        T: Clone,
+    {
+        self.pattern()
+            .entries()
+            .zip(self.values().iter().cloned())
+            .collect()
+    }
+
+    /// Returns a matrix of the same size, only keeping entries where `keep(row, col)` is true.
+    pub(crate) fn filter_entries(&self, keep: impl Fn(usize, usize) -> bool) -> Self
+    where
+        T: Clone,
+    {
+        let mut builder = CscBuilder::new(self.nrows(), self.ncols());
+        for c in 0..self.ncols() {
+            for (r, v) in self.col_iter(c).filter(|&(r, _)| keep(r, c)) {
+                let ins = builder.insert(r, c, v.clone());
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        builder.build()
+    }
 
     /// Number of non-zero entries in this matrix
     pub fn nnz(&self) -> usize {
@@ -48,7 +379,74 @@ impl<T> Csc<T> {
     pub fn values_mut(&mut self) -> &mut [T] {
         self.0.values_mut()
     }
+
+    /// Applies `f` to every stored value in place. The sparsity pattern is unchanged, so
+    /// values mapped to zero are still stored explicitly.
+    pub fn map_values_in_place(&mut self, mut f: impl FnMut(T) -> T)
+    where
+        T: Copy,
+    {
+        for v in self.values_mut() {
+            *v = f(*v);
+        }
+    }
+
+    /// Returns a copy of this matrix with `f` applied to every stored value, preserving the
+    /// sparsity pattern.
+    pub fn map_values(&self, mut f: impl FnMut(T) -> T) -> Self
+    where
+        T: Copy,
+    {
+        Csc(self.0.map_values(|&v| f(v)))
+    }
+    /// Converts this matrix to row major storage, for operations which access rows.
+    /// This transposes the storage with a counting sort in `O(nnz + nrows + ncols)` time, so
+    /// when the rows are needed repeatedly, the result should be kept rather than recomputed.
+    pub fn to_csr(&self) -> Csr<T>
+    where
+        T: Clone,
+    {
+        Csr(self.0.transpose())
+    }
+    /// Returns the transpose of this matrix, with the same cost as [`to_csr`](Self::to_csr).
+    pub fn transpose(&self) -> Self
+    where
+        T: Clone,
+    {
+        Csc(self.0.transpose())
+    }
+    /// Appends new columns after the last column of this matrix, in place.
+    /// The row indices of each column must be in strictly ascending order and below
+    /// [`nrows`](Self::nrows), with one value per row, otherwise no columns are appended.
+    pub fn append_columns(
+        &mut self,
+        cols: &[(Vec<usize>, Vec<T>)],
+    ) -> Result<(), BuilderInsertError>
+    where
+        T: Clone,
+    {
+        for (c, (rows, vals)) in cols.iter().enumerate() {
+            if rows.len() != vals.len() {
+                return Err(BuilderInsertError::LengthMismatch(rows.len(), vals.len()));
+            }
+            if let Some(&r) = rows.iter().find(|&&r| r >= self.nrows()) {
+                return Err(BuilderInsertError::OutOfBounds(self.ncols() + c, r));
+            }
+            if let Some(w) = rows.windows(2).find(|w| w[1] <= w[0]) {
+                return Err(BuilderInsertError::MinorTooLow(w[1], w[0]));
+            }
+        }
+        for (rows, vals) in cols {
+            self.0.push_lane(rows, vals);
+        }
+        Ok(())
+    }
+
     /// Constructs a CSC matrix from a set of triples. Fails if there are duplicate entries.
+    /// Every triple is stored, even if its value is zero. This is
+    /// [`from_triplets_with_policy`](Csc::from_triplets_with_policy) with
+    /// [`DuplicatePolicy::Error`] and [`ZeroPolicy::Keep`], but without requiring `T` to be
+    /// numeric.
     pub fn from_triplets(
         rows: usize,
         cols: usize,
@@ -57,29 +455,100 @@ impl<T> Csc<T> {
     where
         T: Copy,
     {
-        let mut builder = CscBuilder::new(rows, cols);
-        t.sort_unstable_by_key(|a| a.0);
-        for &([x, y], v) in t.iter() {
-            builder.insert(y, x, v)?;
-        }
-        Ok(builder.build())
+        Self::from_triplets_by(rows, cols, t, DuplicatePolicy::Error, |a, _| a, |_| true)
+    }
+
+    /// Constructs a CSC matrix from a set of triples, where `duplicate_policy` decides how
+    /// duplicate entries are combined, and `zero_policy` decides whether entries equal to
+    /// `T::default()`, which is zero for numbers, are stored. Duplicates are combined first,
+    /// so under [`ZeroPolicy::Drop`] summed duplicates which cancel are dropped as well.
+    /// Only fails if there are duplicates and the policy is [`DuplicatePolicy::Error`].
+    pub fn from_triplets_with_policy(
+        rows: usize,
+        cols: usize,
+        t: &mut [([usize; 2], T)],
+        zero_policy: ZeroPolicy,
+        duplicate_policy: DuplicatePolicy,
+    ) -> Result<Self, BuilderInsertError>
+    where
+        T: Copy + core::ops::Add<Output = T> + PartialEq + Default,
+    {
+        let zero = T::default();
+        Self::from_triplets_by(
+            rows,
+            cols,
+            t,
+            duplicate_policy,
+            |a, b| a + b,
+            |v| zero_policy == ZeroPolicy::Keep || *v != zero,
+        )
     }
 
     /// Constructs a CSC matrix from a set of triples. If there are duplicate entries, sums
     /// them.
+    #[deprecated(note = "use `from_triplets_with_policy` with `DuplicatePolicy::Sum`")]
     pub fn from_triplets_summed(
         rows: usize,
         cols: usize,
         t: &mut [([usize; 2], T)],
         add: impl Fn(T, T) -> T + Copy,
     ) -> Result<Self, BuilderInsertError>
+    where
+        T: Copy,
+    {
+        Self::from_triplets_by(rows, cols, t, DuplicatePolicy::Sum, add, |_| true)
+    }
+
+    /// Constructs a CSC matrix from a set of triples, where `policy` decides how duplicate
+    /// entries are combined.
+    #[deprecated(note = "use `from_triplets_with_policy` with `ZeroPolicy::Keep`")]
+    pub fn from_triplets_with_duplicates(
+        rows: usize,
+        cols: usize,
+        t: &mut [([usize; 2], T)],
+        policy: DuplicatePolicy,
+    ) -> Result<Self, BuilderInsertError>
+    where
+        T: Copy + core::ops::Add<Output = T>,
+    {
+        Self::from_triplets_by(rows, cols, t, policy, |a, b| a + b, |_| true)
+    }
+
+    /// Sorts the triples and builds them, combining duplicates with `policy`, where `add` is
+    /// only used to sum them. Combined values for which `keep` is false are not stored.
+    fn from_triplets_by(
+        rows: usize,
+        cols: usize,
+        t: &mut [([usize; 2], T)],
+        policy: DuplicatePolicy,
+        add: impl Fn(T, T) -> T,
+        keep: impl Fn(&T) -> bool,
+    ) -> Result<Self, BuilderInsertError>
     where
         T: Copy,
     {
         let mut builder = CscBuilder::new(rows, cols);
-        t.sort_unstable_by_key(|a| a.0);
-        for &([x, y], v) in t.iter() {
-            builder.insert_sum(y, x, v, add)?;
+        match policy {
+            // keeping the first or last needs the input order of duplicates.
+            DuplicatePolicy::KeepFirst | DuplicatePolicy::KeepLast => t.sort_by_key(|a| a.0),
+            DuplicatePolicy::Error | DuplicatePolicy::Sum => t.sort_unstable_by_key(|a| a.0),
+        }
+        let mut i = 0;
+        while i < t.len() {
+            let [x, y] = t[i].0;
+            let end = i + t[i..].iter().take_while(|e| e.0 == [x, y]).count();
+            let v = match policy {
+                DuplicatePolicy::Error if end > i + 1 => {
+                    return Err(BuilderInsertError::MinorTooLow(y, y));
+                }
+                DuplicatePolicy::Error | DuplicatePolicy::KeepFirst => t[i].1,
+                DuplicatePolicy::Sum => t[i + 1..end].iter().fold(t[i].1, |a, e| add(a, e.1)),
+                DuplicatePolicy::KeepLast => t[end - 1].1,
+            };
+            if keep(&v) {
+                builder.insert(y, x, v)?;
+            }
+            i = end;
         }
         Ok(builder.build())
     }
@@ -87,7 +556,7 @@ impl<T> Csc<T> {
     pub fn from_btreemap(
         rows: usize,
         cols: usize,
-        map: &std::collections::BTreeMap<[usize; 2], T>,
+        map: &alloc::collections::BTreeMap<[usize; 2], T>,
     ) -> Result<Self, BuilderInsertError>
     where
         T: Copy,
@@ -98,55 +567,489 @@ impl<T> Csc<T> {
         }
         Ok(builder.build())
     }
+
+    /// Constructs a CSC matrix from a map with keys in the same order as
+    /// [`from_btreemap`](Self::from_btreemap). The entries are sorted before building, so
+    /// duplicates can be accumulated directly into the map. As keys are unique, this only
+    /// fails if a key is outside of the matrix.
+    #[cfg(feature = "std")]
+    pub fn from_hashmap(
+        rows: usize,
+        cols: usize,
+        map: &std::collections::HashMap<[usize; 2], T>,
+    ) -> Result<Self, BuilderInsertError>
+    where
+        T: Copy,
+    {
+        let mut t = map.iter().map(|(&k, &v)| (k, v)).collect::<Vec<_>>();
+        Self::from_triplets(rows, cols, &mut t)
+    }
 }
 
 impl Csc<F> {
     pub fn identity(n: usize) -> Self {
         Csc(CsMatrix::identity(n))
     }
+    /// Constructs a matrix from compressed column arrays, such as those exported by another
+    /// library, after validating them. `offsets` has `ncols + 1` entries, and column `c` has
+    /// the strictly increasing row `indices` and `values` in `offsets[c]..offsets[c + 1]`.
+    /// With the default `usize` index type the arrays are moved into the matrix rather than
+    /// copied. With the `u32` feature the indices are converted, so they are copied, and
+    /// `nrows` and the number of entries must fit in a `u32`.
+    pub fn try_from_csc_arrays(
+        nrows: usize,
+        ncols: usize,
+        offsets: Vec<usize>,
+        indices: Vec<usize>,
+        values: Vec<F>,
+    ) -> Result<Self, PatternError> {
+        if offsets.len() != ncols + 1 {
+            return Err(PatternError::OffsetsLength {
+                expected: ncols + 1,
+                found: offsets.len(),
+            });
+        }
+        if indices.len() != values.len() {
+            return Err(PatternError::ValuesLength {
+                indices: indices.len(),
+                values: values.len(),
+            });
+        }
+        let pattern = SparsityPattern::try_from_arrays(offsets, indices, nrows)?;
+        Ok(Csc(CsMatrix::from_parts(pattern, values)))
+    }
+
+    /// Constructs a matrix with the given pattern, where each stored entry is `f(row, col)`.
+    /// `pattern` must be column major, with `cols` majors and `rows` minors.
+    pub fn from_fn(
+        rows: usize,
+        cols: usize,
+        pattern: &SparsityPattern,
+        f: impl Fn(usize, usize) -> F,
+    ) -> Self {
+        assert_eq!(pattern.major_dim(), cols);
+        assert_eq!(pattern.minor_dim, rows);
+        let values = pattern.entries().map(|[c, r]| f(r, c)).collect();
+        Csc(CsMatrix::from_parts(pattern.clone(), values))
+    }
+    /// Constructs a CSC matrix from dense row-major input, where each row must have the same
+    /// length.
+    pub fn from_rows(rows: &[Vec<F>], zero_policy: ZeroPolicy) -> Self {
+        let ncols = rows.first().map_or(0, Vec::len);
+        assert!(rows.iter().all(|r| r.len() == ncols));
+        let mut builder = CscBuilder::new(rows.len(), ncols);
+        for c in 0..ncols {
+            for (r, row) in rows.iter().enumerate() {
+                if zero_policy == ZeroPolicy::Drop && row[c] == 0. {
+                    continue;
+                }
+                let ins = builder.insert(r, c, row[c]);
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        builder.build()
+    }
+    /// Constructs the sparse outer product `u vᵀ` of two sparse vectors, where `u` has
+    /// `rows` entries and `v` has `cols` entries. Indices need not be sorted, but must be
+    /// unique.
+    pub fn outer_product(
+        u_idx: &[usize],
+        u_val: &[F],
+        v_idx: &[usize],
+        v_val: &[F],
+        rows: usize,
+        cols: usize,
+    ) -> Self {
+        assert_eq!(u_idx.len(), u_val.len());
+        assert_eq!(v_idx.len(), v_val.len());
+        let mut u = u_idx
+            .iter()
+            .copied()
+            .zip(u_val.iter().copied())
+            .collect::<Vec<_>>();
+        u.sort_unstable_by_key(|&(i, _)| i);
+        let mut v = v_idx
+            .iter()
+            .copied()
+            .zip(v_val.iter().copied())
+            .collect::<Vec<_>>();
+        v.sort_unstable_by_key(|&(i, _)| i);
+
+        let mut builder = CscBuilder::new(rows, cols);
+        for &(col, v) in &v {
+            for &(row, u) in &u {
+                builder
+                    .insert(row, col, u * v)
+                    .expect("Duplicate index in outer product");
+            }
+        }
+        builder.build()
+    }
+    /// Computes `A + B`. The pattern of the result is the union of both patterns, so entries
+    /// which cancel to zero are still stored.
+    pub fn add(&self, b: &Csc<F>) -> Csc<F> {
+        self.add_scaled(b, 1.)
+    }
+    /// Computes `A - B`. Like [`add`](Self::add), the pattern of the result is the union of
+    /// both patterns, so `a.sub(&a)` stores every entry of `a` as an explicit zero.
+    pub fn sub(&self, b: &Csc<F>) -> Csc<F> {
+        self.add_scaled(b, -1.)
+    }
+    /// Computes `A + beta B` by merging each pair of columns in sorted order, or directly on
+    /// the values if both have the same pattern.
+    fn add_scaled(&self, b: &Csc<F>, beta: F) -> Csc<F> {
+        assert_eq!(self.nrows(), b.nrows());
+        assert_eq!(self.ncols(), b.ncols());
+        if self.pattern() == b.pattern() {
+            let mut out = self.clone();
+            for (v, &bv) in out.values_mut().iter_mut().zip(b.values()) {
+                *v += beta * bv;
+            }
+            return out;
+        }
+        let mut builder = CscBuilder::new(self.nrows(), self.ncols());
+        for c in 0..self.ncols() {
+            let mut a_iter = self.col_iter(c).peekable();
+            let mut b_iter = b.col_iter(c).peekable();
+            loop {
+                let (r, v) = match (a_iter.peek(), b_iter.peek()) {
+                    (None, None) => break,
+                    (Some(&(ra, &va)), Some(&(rb, &vb))) if ra == rb => {
+                        a_iter.next();
+                        b_iter.next();
+                        (ra, va + beta * vb)
+                    }
+                    (Some(&(ra, &va)), Some(&(rb, _))) if ra < rb => {
+                        a_iter.next();
+                        (ra, va)
+                    }
+                    (Some(&(ra, &va)), None) => {
+                        a_iter.next();
+                        (ra, va)
+                    }
+                    (_, Some(&(rb, &vb))) => {
+                        b_iter.next();
+                        (rb, beta * vb)
+                    }
+                };
+                let ins = builder.insert(r, c, v);
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        builder.build()
+    }
+    /// Computes the Frobenius inner product `tr(AᵀB) = Σ A_ij B_ij`, without forming the
+    /// product.
+    pub fn frobenius_inner(&self, b: &Csc<F>) -> F {
+        assert_eq!(self.nrows(), b.nrows());
+        assert_eq!(self.ncols(), b.ncols());
+        let mut sum = 0.;
+        for c in 0..self.ncols() {
+            let mut b_iter = b.col_iter(c).peekable();
+            for (r, &av) in self.col_iter(c) {
+                while b_iter.next_if(|&(br, _)| br < r).is_some() {}
+                if let Some(&(br, &bv)) = b_iter.peek()
+                    && br == r
+                {
+                    sum += av * bv;
+                }
+            }
+        }
+        sum
+    }
+    /// Computes `A + λI` for a square matrix, inserting diagonal entries where they are
+    /// missing. This is a single pass over `A`, which is cheaper than [`add`](Self::add).
+    pub fn add_scaled_identity(&self, lambda: F) -> Csc<F> {
+        assert_eq!(self.nrows(), self.ncols());
+        let mut builder = CscBuilder::new(self.nrows(), self.ncols());
+        for c in 0..self.ncols() {
+            let mut diag = Some(lambda);
+            for (r, &v) in self.col_iter(c) {
+                let v = if r == c {
+                    v + diag.take().unwrap()
+                } else if r > c
+                    && let Some(d) = diag.take()
+                {
+                    let ins = builder.insert(c, c, d);
+                    debug_assert_eq!(ins, Ok(()));
+                    v
+                } else {
+                    v
+                };
+                let ins = builder.insert(r, c, v);
+                debug_assert_eq!(ins, Ok(()));
+            }
+            if let Some(d) = diag {
+                let ins = builder.insert(c, c, d);
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        builder.build()
+    }
+    /// Computes `(A + Aᵀ) / 2`, which is exactly symmetric.
+    pub fn symmetrize(&self) -> Csc<F> {
+        let mut sym = self.add(&self.transpose());
+        sym.map_values_in_place(|v| v * 0.5);
+        sym
+    }
+    /// The symmetric part `(A + Aᵀ) / 2`, which is the same as
+    /// [`symmetrize`](Self::symmetrize).
+    pub fn symmetric_part(&self) -> Csc<F> {
+        self.symmetrize()
+    }
+    /// The skew-symmetric part `(A - Aᵀ) / 2`, such that `A` is the sum of its symmetric and
+    /// skew parts. Its size relative to `A` measures how far `A` is from symmetric. Entries
+    /// of symmetric pairs are stored as explicit zeros.
+    pub fn skew_part(&self) -> Csc<F> {
+        let mut skew = self.sub(&self.transpose());
+        skew.map_values_in_place(|v| v * 0.5);
+        skew
+    }
+    /// Returns this matrix with explicit zeros added so that its pattern is the union of the
+    /// patterns of `A` and `Aᵀ`, without changing any values. Orderings which need a symmetric
+    /// pattern can use this on unsymmetric matrices.
+    pub fn symmetric_pattern(&self) -> Csc<F> {
+        self.add(&self.transpose().map_values(|_| 0.))
+    }
+    /// Returns true if every stored entry `(row, col)` also has `(col, row)` stored, regardless
+    /// of their values.
+    pub fn is_structurally_symmetric(&self) -> bool {
+        self.assert_structurally_symmetric().is_ok()
+    }
+    /// Checks that the pattern is symmetric, as expected by symmetric factorizations such as
+    /// Cholesky, returning the first stored `(row, col)` in column order whose transpose
+    /// position is missing.
+    pub fn assert_structurally_symmetric(&self) -> Result<(), (usize, usize)> {
+        assert_eq!(self.nrows(), self.ncols());
+        match self
+            .pattern()
+            .entries()
+            .find(|&[c, r]| self.get(c, r).is_none())
+        {
+            Some([col, row]) => Err((row, col)),
+            None => Ok(()),
+        }
+    }
+    /// Computes the sparse product `AB`, where `A` is `m×k` and `B` is `k×n`, producing an `m×n`
+    /// matrix. Every structurally nonzero product is stored, even if the sum cancels to zero.
+    pub fn matmul(&self, b: &Csc<F>) -> Csc<F> {
+        assert_eq!(self.ncols(), b.nrows());
+        let (m, n) = (self.nrows(), b.ncols());
+        let mut builder = CscBuilder::new(m, n);
+        // dense accumulator for the current column, along with which rows are occupied.
+        let mut acc = vec![0.; m];
+        let mut occupied = vec![false; m];
+        let mut rows = vec![];
+        for j in 0..n {
+            for (k, &bv) in b.col_iter(j) {
+                for (i, &av) in self.col_iter(k) {
+                    if !occupied[i] {
+                        occupied[i] = true;
+                        rows.push(i);
+                    }
+                    acc[i] += av * bv;
+                }
+            }
+            rows.sort_unstable();
+            for &i in &rows {
+                let ins = builder.insert(i, j, acc[i]);
+                debug_assert_eq!(ins, Ok(()));
+                acc[i] = 0.;
+                occupied[i] = false;
+            }
+            rows.clear();
+        }
+        builder.build()
+    }
+    /// Returns the canonical form of this matrix, which has no explicitly stored zeros. Storage
+    /// is always sorted and free of duplicates, so two matrices with the same nonzero values
+    /// have equal canonical forms.
+    pub fn canonicalize(&self) -> Csc<F> {
+        let mut builder = CscBuilder::new(self.nrows(), self.ncols());
+        for ([c, r], &v) in self.pattern().entries().zip(self.values()) {
+            if v != 0. {
+                let ins = builder.insert(r, c, v);
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        builder.build()
+    }
+    /// Computes the Gram matrix `AᵀA`.
+    pub fn gram(&self) -> Csc<F> {
+        self.transpose().matmul(self)
+    }
+    /// Computes column `j` of the Gram matrix `AᵀA` into `out` as `(row, value)` pairs sorted
+    /// by row, without forming the whole Gram matrix. Each entry is the dot product of column
+    /// `j` with another column of `A`, and only columns which share a row with column `j` are
+    /// included, matching the pattern of [`gram`](Self::gram).
+    pub fn gram_column(&self, j: usize, out: &mut Vec<(usize, F)>) {
+        out.clear();
+        let (vals_j, rows_j) = self.col(j);
+        for i in 0..self.ncols() {
+            let (vals_i, rows_i) = self.col(i);
+            let (mut a, mut b) = (0, 0);
+            let mut dot = None;
+            while a < rows_i.len() && b < rows_j.len() {
+                match rows_i[a].cmp(&rows_j[b]) {
+                    core::cmp::Ordering::Less => a += 1,
+                    core::cmp::Ordering::Greater => b += 1,
+                    core::cmp::Ordering::Equal => {
+                        *dot.get_or_insert(0.) += vals_i[a] * vals_j[b];
+                        a += 1;
+                        b += 1;
+                    }
+                }
+            }
+            if let Some(dot) = dot {
+                out.push((i, dot));
+            }
+        }
+    }
+    /// Computes `x[i] = b[i] / A_ii`, which applies the inverse of the diagonal of `self`.
+    /// Returns the first row whose diagonal entry is missing or zero, in which case `x` is
+    /// left partially solved.
+    pub fn diagonal_solve(&self, b: &[F], x: &mut [F]) -> Result<(), usize> {
+        assert_eq!(self.nrows(), self.ncols());
+        assert_eq!(b.len(), self.ncols());
+        assert_eq!(x.len(), self.ncols());
+        for (i, (x, &b)) in x.iter_mut().zip(b).enumerate() {
+            match self.get(i, i) {
+                Some(&d) if d != 0. => *x = b / d,
+                _ => return Err(i),
+            }
+        }
+        Ok(())
+    }
+    /// The sorted indices which can be nonzero in the solution of a triangular solve with
+    /// `self`, when `b` is only nonzero at `b_idxs`, without computing any values. These are
+    /// the indices reachable from `b_idxs` through the lower or upper triangle of `self`, and
+    /// can be used to preallocate the output of a sparse solve.
+    pub fn solve_pattern(&self, b_idxs: &[usize], lower: bool) -> Vec<usize> {
+        assert!(b_idxs.iter().all(|&i| i < self.ncols()));
+        let mut reached = vec![false; self.ncols()];
+        let mut stack = vec![];
+        if lower {
+            self.pattern()
+                .sparse_lower_triangular_solve_bool(b_idxs, &mut reached, &mut stack);
+        } else {
+            self.pattern()
+                .sparse_upper_triangular_solve_bool(b_idxs, &mut reached, &mut stack);
+        }
+        (0..reached.len()).filter(|&i| reached[i]).collect()
+    }
+    /// Checks that a triangular solve with `self` will not produce NaN or infinity, before
+    /// attempting it. Returns the row of the first problem in column order, which is a
+    /// missing, zero or non-finite diagonal entry when `unit_diagonal` is false, or a
+    /// non-finite entry in the lower or upper triangle which the solve reads. The solves do not
+    /// check the magnitude of the diagonal, so a tiny but nonzero diagonal entry is safe,
+    /// while a zero one produces an infinity or NaN in the output rather than panicking.
+    pub fn triangular_solve_is_safe(&self, lower: bool, unit_diagonal: bool) -> Result<(), usize> {
+        assert_eq!(self.nrows(), self.ncols());
+        for c in 0..self.ncols() {
+            if !unit_diagonal {
+                match self.get(c, c) {
+                    Some(&d) if d != 0. && d.is_finite() => {}
+                    _ => return Err(c),
+                }
+            }
+            let in_triangle = |r: usize| if lower { r > c } else { r < c };
+            if let Some((r, _)) = self
+                .col_iter(c)
+                .find(|&(r, v)| in_triangle(r) && !v.is_finite())
+            {
+                return Err(r);
+            }
+        }
+        Ok(())
+    }
     /// Solves a lower triangular system, `self` is a matrix of NxN, and `b` is a column vector of size N
     /// Assuming that b is dense.
     pub fn dense_lower_triangular_solve(&self, b: &[F], out: &mut [F], unit_diagonal: bool) {
         self.dense_lower_triangular_solve_arr(
-            unsafe { std::mem::transmute::<_, &[[F; 1]]>(b) },
-            unsafe { std::mem::transmute(out) },
+            unsafe { core::mem::transmute::<&[F], &[[F; 1]]>(b) },
+            unsafe { core::mem::transmute::<&mut [F], &mut [[F; 1]]>(out) },
             unit_diagonal,
         );
     }
+    /// Solves a lower triangular system like
+    /// [`dense_lower_triangular_solve`](Self::dense_lower_triangular_solve), but returns an
+    /// error instead of ignoring entries in the upper triangle.
+    pub fn dense_lower_triangular_solve_strict(
+        &self,
+        b: &[F],
+        out: &mut [F],
+        unit_diagonal: bool,
+    ) -> Result<(), TriangularError> {
+        self.check_triangle(|r, c| r >= c)?;
+        self.dense_lower_triangular_solve(b, out, unit_diagonal);
+        Ok(())
+    }
+    /// Returns the first entry for which `in_triangle(row, col)` is false.
+    fn check_triangle(
+        &self,
+        in_triangle: impl Fn(usize, usize) -> bool,
+    ) -> Result<(), TriangularError> {
+        match self.pattern().entries().find(|&[c, r]| !in_triangle(r, c)) {
+            Some([col, row]) => Err(TriangularError::NotTriangular { row, col }),
+            None => Ok(()),
+        }
+    }
     /// Solves a lower triangular system, `self` is a matrix of NxN, and `b` is a column vector of size N
     /// Assuming that b is dense.
+    ///
+    /// Each of the `N` right hand sides is processed together as an array, so that the
+    /// arithmetic over lanes can be vectorized.
     pub fn dense_lower_triangular_solve_arr<const N: usize>(
         &self,
         b: &[[F; N]],
         out: &mut [[F; N]],
         unit_diagonal: bool,
     ) {
-        assert_eq!(self.nrows(), self.ncols());
-        assert_eq!(self.ncols(), b.len());
         assert_eq!(out.len(), b.len());
         out.copy_from_slice(b);
-        let n = b.len();
+        self.dense_lower_triangular_solve_inplace_arr(out, unit_diagonal);
+    }
+
+    /// Solves a lower triangular system like
+    /// [`dense_lower_triangular_solve`](Self::dense_lower_triangular_solve), but overwrites
+    /// `b` with the solution instead of using a separate output buffer.
+    pub fn dense_lower_triangular_solve_inplace(&self, b: &mut [F], unit_diagonal: bool) {
+        self.dense_lower_triangular_solve_inplace_arr(
+            unsafe { core::mem::transmute::<&mut [F], &mut [[F; 1]]>(b) },
+            unit_diagonal,
+        );
+    }
+
+    /// Solves a lower triangular system like
+    /// [`dense_lower_triangular_solve_arr`](Self::dense_lower_triangular_solve_arr), but
+    /// overwrites `b` with the solution.
+    pub fn dense_lower_triangular_solve_inplace_arr<const N: usize>(
+        &self,
+        out: &mut [[F; N]],
+        unit_diagonal: bool,
+    ) {
+        assert_eq!(self.nrows(), self.ncols());
+        assert_eq!(self.ncols(), out.len());
+        let n = out.len();
 
         for i in 0..n {
-            for d in 0..N {
-                let mut iter = self.col_iter(i).peekable();
-                while iter.next_if(|n| n.0 < i).is_some() {}
-                if let Some(n) = iter.peek() {
-                    if n.0 == i && !unit_diagonal {
-                        assert!(n.0 <= i);
-                        assert!(n.1.abs() > 1e-10, "{}", n.1);
-                        out[i][d] /= n.1;
-                        assert!(out[i][d].is_finite());
-                        iter.next();
-                    }
+            let mut iter = self.col_iter(i).peekable();
+            while iter.next_if(|n| n.0 < i).is_some() {}
+            if let Some(&(r, &diag)) = iter.peek()
+                && r == i
+                && !unit_diagonal
+            {
+                for o in out[i].iter_mut() {
+                    *o /= diag;
                 }
-                let mul = out[i][d];
-                for (ri, v) in self.col_iter(i) {
-                    use std::cmp::Ordering::*;
-                    // ensure that only using the lower part
-                    match ri.cmp(&i) {
-                        Greater => out[ri][d] -= v * mul,
-                        Equal | Less => {}
+                iter.next();
+            }
+            let mul = out[i];
+            for (ri, &v) in iter {
+                // ensure that only using the lower part
+                if ri > i {
+                    for (o, m) in out[ri].iter_mut().zip(mul) {
+                        *o -= v * m;
                     }
                 }
             }
@@ -157,13 +1060,28 @@ impl Csc<F> {
     /// Assuming that b is dense.
     pub fn dense_upper_triangular_solve(&self, b: &[F], out: &mut [F]) {
         self.dense_upper_triangular_solve_arr(
-            unsafe { std::mem::transmute::<_, &[[F; 1]]>(b) },
-            unsafe { std::mem::transmute(out) },
+            unsafe { core::mem::transmute::<&[F], &[[F; 1]]>(b) },
+            unsafe { core::mem::transmute::<&mut [F], &mut [[F; 1]]>(out) },
         );
     }
 
+    /// Solves an upper triangular system like
+    /// [`dense_upper_triangular_solve`](Self::dense_upper_triangular_solve), but returns an
+    /// error instead of ignoring entries in the lower triangle.
+    pub fn dense_upper_triangular_solve_strict(
+        &self,
+        b: &[F],
+        out: &mut [F],
+    ) -> Result<(), TriangularError> {
+        self.check_triangle(|r, c| r <= c)?;
+        self.dense_upper_triangular_solve(b, out);
+        Ok(())
+    }
     /// Solves an upper triangular system, `self` is a matrix of NxN, and `b` is a column vector of size N
     /// Assuming that b is dense.
+    ///
+    /// Each of the `N` right hand sides is processed together as an array, so that the
+    /// arithmetic over lanes can be vectorized.
     pub fn dense_upper_triangular_solve_arr<const N: usize>(
         &self,
         b: &[[F; N]],
@@ -176,37 +1094,86 @@ impl Csc<F> {
         let n = b.len();
 
         for i in (0..n).rev() {
-            for d in 0..N {
-                let mut iter = self.col_iter(i).rev().peekable();
-                while iter.next_if(|n| n.0 > i).is_some() {}
-                if let Some(n) = iter.peek() {
-                    if n.0 == i {
-                        assert!(n.1.abs() > 1e-8);
-                        out[i][d] /= n.1;
-                        iter.next();
-                    }
+            let mut iter = self.col_iter(i).rev().peekable();
+            while iter.next_if(|n| n.0 > i).is_some() {}
+            if let Some(&(r, &diag)) = iter.peek()
+                && r == i
+            {
+                for o in out[i].iter_mut() {
+                    *o /= diag;
                 }
-                // introduce a NaN, intentionally, if the diagonal doesn't have a value.
-                let mul = out[i][d];
-                for (row, &v) in iter {
-                    use std::cmp::Ordering::*;
-                    match row.cmp(&i) {
-                        Less => out[row][d] -= v * mul,
-                        Equal | Greater => {}
+                iter.next();
+            }
+            // introduce a NaN, intentionally, if the diagonal doesn't have a value.
+            let mul = out[i];
+            for (row, &v) in iter {
+                if row < i {
+                    for (o, m) in out[row].iter_mut().zip(mul) {
+                        *o -= v * m;
                     }
                 }
             }
         }
     }
 
+    /// Solves `Lᵀx = b` where `L` is the lower triangle of `self`, without forming the
+    /// transpose. `b` is assumed to be dense.
+    pub fn dense_lower_triangular_transpose_solve(
+        &self,
+        b: &[F],
+        out: &mut [F],
+        unit_diagonal: bool,
+    ) {
+        assert_eq!(self.nrows(), self.ncols());
+        assert_eq!(self.ncols(), b.len());
+        assert_eq!(out.len(), b.len());
+        // Row `i` of `Lᵀ` is column `i` of `L`, so each entry is solved by a dot product
+        // against the entries below the diagonal, which are already solved.
+        for i in (0..b.len()).rev() {
+            let mut val = b[i];
+            let mut diag = 1.;
+            for (r, &v) in self.col_iter(i) {
+                if r > i {
+                    val -= v * out[r];
+                } else if r == i && !unit_diagonal {
+                    diag = v;
+                }
+            }
+            out[i] = val / diag;
+        }
+    }
+
+    /// Solves `Uᵀx = b` where `U` is the upper triangle of `self`, without forming the
+    /// transpose. `b` is assumed to be dense.
+    pub fn dense_upper_triangular_transpose_solve(&self, b: &[F], out: &mut [F]) {
+        assert_eq!(self.nrows(), self.ncols());
+        assert_eq!(self.ncols(), b.len());
+        assert_eq!(out.len(), b.len());
+        for i in 0..b.len() {
+            let mut val = b[i];
+            let mut diag = 0.;
+            for (r, &v) in self.col_iter(i) {
+                if r < i {
+                    val -= v * out[r];
+                } else if r == i {
+                    diag = v;
+                }
+            }
+            // introduces a NaN if the diagonal is missing
+            out[i] = val / diag;
+        }
+    }
+
     /// Solves a sparse lower triangular system `Ax = b`, with both the matrix and vector
     /// sparse.
     /// sparsity_idxs should be precomputed using the sparse_lower_triangle pattern.
     ///
     /// `out_sparsity_pattern` must also be pre-sorted.
     ///
-    /// Assumes that the diagonal of the sparse matrix is all 1 if `assume_unit` is true.
-    pub(crate) fn sparse_lower_triangular_solve_sorted(
+    /// Assumes that the diagonal of the sparse matrix is all 1 if `unit_diagonal` is true.
+    /// Otherwise, if the diagonal of a row in `out_sparsity_pattern` is not stored, returns
+    /// that row as an error, and `out` is left partially solved.
+    pub fn sparse_lower_triangular_solve_sorted(
         &self,
         // input vector idxs & values
         b_idxs: &[usize],
@@ -216,8 +1183,8 @@ impl Csc<F> {
         // TODO maybe would be better to enforce sorted, but would have to sort internally.
         out_sparsity_pattern: &[usize],
         out: &mut [F],
-        assume_unit: bool,
-    ) {
+        unit_diagonal: bool,
+    ) -> Result<(), usize> {
         debug_assert_eq!(self.nrows(), self.ncols());
         debug_assert_eq!(b.len(), b_idxs.len());
         debug_assert!(b_idxs.iter().all(|&bi| bi < self.ncols()));
@@ -242,7 +1209,7 @@ impl Csc<F> {
         // iterate thru
         for (i, &row) in out_sparsity_pattern.iter().enumerate() {
             let mut iter = self.col_iter(row).peekable();
-            if !assume_unit {
+            if !unit_diagonal {
                 while iter.next_if(|n| n.0 < row).is_some() {}
                 match iter.peek() {
                     Some((r, l_val)) if *r == row => {
@@ -250,9 +1217,8 @@ impl Csc<F> {
                         *dst /= **l_val;
                         assert!(dst.is_finite());
                     }
-                    // here it now becomes implicitly 0,
-                    // likely this should introduce NaN or some other behavior.
-                    _ => {}
+                    // the diagonal is implicitly 0.
+                    _ => return Err(row),
                 }
             }
             let mul = unsafe { *out.get_unchecked(i) };
@@ -266,6 +1232,63 @@ impl Csc<F> {
                 *unsafe { out.get_unchecked_mut(ni) } -= *l_val * mul;
             }
         }
+        Ok(())
+    }
+    /// Solves a sparse upper triangular system `Ax = b`, with both the matrix and vector
+    /// sparse.
+    /// `out_sparsity_pattern` should be precomputed using the sparse_upper_triangle pattern,
+    /// and must be sorted in ascending order.
+    ///
+    /// Assumes that the diagonal of the sparse matrix is all 1 if `unit_diagonal` is true.
+    /// Otherwise, if the diagonal of a row in `out_sparsity_pattern` is not stored, returns
+    /// that row as an error, and `out` is left partially solved.
+    pub fn sparse_upper_triangular_solve_sorted(
+        &self,
+        b_idxs: &[usize],
+        b: &[F],
+        out_sparsity_pattern: &[usize],
+        out: &mut [F],
+        unit_diagonal: bool,
+    ) -> Result<(), usize> {
+        debug_assert_eq!(self.nrows(), self.ncols());
+        debug_assert_eq!(b.len(), b_idxs.len());
+        debug_assert!(b_idxs.iter().all(|&bi| bi < self.ncols()));
+
+        debug_assert_eq!(out_sparsity_pattern.len(), out.len());
+        debug_assert!(out_sparsity_pattern.is_sorted());
+
+        out.fill(0.);
+        for (&bi, &bv) in b_idxs.iter().zip(b) {
+            if let Ok(out_pos) = out_sparsity_pattern.binary_search(&bi) {
+                out[out_pos] = bv;
+            }
+        }
+
+        // iterate backwards, since the last row only depends on itself
+        for (i, &row) in out_sparsity_pattern.iter().enumerate().rev() {
+            let mut iter = self.col_iter(row).rev().peekable();
+            if !unit_diagonal {
+                while iter.next_if(|n| n.0 > row).is_some() {}
+                match iter.peek() {
+                    Some((r, u_val)) if *r == row => {
+                        out[i] /= **u_val;
+                        assert!(out[i].is_finite());
+                    }
+                    _ => return Err(row),
+                }
+            }
+            let mul = out[i];
+            for (ni, &nrow) in out_sparsity_pattern[..i].iter().enumerate().rev() {
+                debug_assert!(nrow < row);
+                while iter.next_if(|n| n.0 > nrow).is_some() {}
+                let u_val = match iter.peek() {
+                    Some((r, u_val)) if *r == nrow => u_val,
+                    _ => continue,
+                };
+                out[ni] -= *u_val * mul;
+            }
+        }
+        Ok(())
     }
     pub fn vecmul(&self, v: &[F]) -> Vec<F> {
         let mut out = vec![0.; self.nrows()];
@@ -277,6 +1300,254 @@ impl Csc<F> {
         }
         out
     }
+    /// Computes `out = Ax` for a sparse `x`, which has the values `x_val` at the indices
+    /// `x_idx`. Only the columns of the nonzeros of `x` are visited, so this is much cheaper
+    /// than [`vecmul`](Self::vecmul) when `x` has few nonzeros. Indices must be unique.
+    pub fn vecmul_sparse(&self, x_idx: &[usize], x_val: &[F], out: &mut [F]) {
+        assert_eq!(x_idx.len(), x_val.len());
+        assert_eq!(out.len(), self.nrows());
+        out.fill(0.);
+        for (&c, &x) in x_idx.iter().zip(x_val) {
+            for (r, &v) in self.col_iter(c) {
+                out[r] += x * v;
+            }
+        }
+    }
+    /// Computes `out = Ax` like [`vecmul`](Self::vecmul), but with Kahan compensated
+    /// summation for each row. This is slower, but much more accurate when the products in a
+    /// row span many orders of magnitude, such as when computing residuals for refinement.
+    pub fn vecmul_kahan(&self, x: &[F], out: &mut [F]) {
+        assert_eq!(x.len(), self.ncols());
+        assert_eq!(out.len(), self.nrows());
+        out.fill(0.);
+        let mut compensation = vec![0.; self.nrows()];
+        for (j, &xj) in x.iter().enumerate() {
+            for (r, &v) in self.col_iter(j) {
+                let y = v * xj - compensation[r];
+                let t = out[r] + y;
+                compensation[r] = (t - out[r]) - y;
+                out[r] = t;
+            }
+        }
+    }
+    /// Computes `out = (PAQ)x`, where the permuted matrix is defined by
+    /// `(PAQ)[i][j] = A[row_perm[i]][col_perm[j]]`, the same convention as the pivot of an LU
+    /// factorization. The permutations are applied during the multiply, without forming `PAQ`.
+    pub fn vecmul_permuted(&self, x: &[F], row_perm: &[usize], col_perm: &[usize], out: &mut [F]) {
+        assert_eq!(x.len(), self.ncols());
+        assert_eq!(col_perm.len(), self.ncols());
+        assert_eq!(row_perm.len(), self.nrows());
+        assert_eq!(out.len(), self.nrows());
+        let mut inv_row_perm = vec![0; self.nrows()];
+        for (i, &r) in row_perm.iter().enumerate() {
+            inv_row_perm[r] = i;
+        }
+        out.fill(0.);
+        for (&c, &val) in col_perm.iter().zip(x) {
+            for (r, &v) in self.col_iter(c) {
+                out[inv_row_perm[r]] += v * val;
+            }
+        }
+    }
+    /// Computes the residual `out = b - Ax`.
+    pub fn residual(&self, x: &[F], b: &[F], out: &mut [F]) {
+        assert_eq!(b.len(), self.nrows());
+        assert_eq!(out.len(), self.nrows());
+        for ((o, &b), ax) in out.iter_mut().zip(b).zip(self.vecmul(x)) {
+            *o = b - ax;
+        }
+    }
+    /// Solves `Ax = b` with Cramer's rule if this matrix is at most 3x3, which avoids the
+    /// overhead of a sparse factorization for tiny systems.
+    /// Returns false without modifying `x` if the matrix is larger or singular, in which case a
+    /// factorization should be used instead.
+    pub fn solve_small(&self, b: &[F], x: &mut [F]) -> bool {
+        let n = self.ncols();
+        assert_eq!(self.nrows(), n);
+        assert_eq!(b.len(), n);
+        assert_eq!(x.len(), n);
+        if n > 3 {
+            return false;
+        }
+        fn det3(m: &[[F; 3]; 3]) -> F {
+            m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+                - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+                + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+        }
+        // pad smaller systems with the identity, which leaves the solution unchanged.
+        let mut m = [[0.; 3]; 3];
+        for (i, row) in m.iter_mut().enumerate().skip(n) {
+            row[i] = 1.;
+        }
+        for ([c, r], &v) in self.pattern().entries().zip(self.values()) {
+            m[r][c] = v;
+        }
+        let det = det3(&m);
+        if det == 0. {
+            return false;
+        }
+        for (c, x) in x.iter_mut().enumerate() {
+            let mut mc = m;
+            for (row, &b) in mc.iter_mut().zip(b) {
+                row[c] = b;
+            }
+            *x = det3(&mc) / det;
+        }
+        true
+    }
+    /// Counts the stored nonzero values by `log10(|value|)` into `bins` bins of equal width,
+    /// spanning from the smallest to the largest magnitude. A wide spread of magnitudes
+    /// suggests that the matrix is poorly scaled and may benefit from equilibration.
+    #[cfg(feature = "std")]
+    pub fn magnitude_histogram(&self, bins: usize) -> Vec<usize> {
+        assert!(bins > 0);
+        let mut hist = vec![0; bins];
+        let logs = self
+            .values()
+            .iter()
+            .filter(|&&v| v != 0.)
+            .map(|v| v.abs().log10());
+        let (min, max) = logs
+            .clone()
+            .fold((F::INFINITY, F::NEG_INFINITY), |(lo, hi), l| {
+                (lo.min(l), hi.max(l))
+            });
+        let width = (max - min) / bins as F;
+        for l in logs {
+            let bin = if width > 0. {
+                ((l - min) / width) as usize
+            } else {
+                0
+            };
+            hist[bin.min(bins - 1)] += 1;
+        }
+        hist
+    }
+    /// Estimates the spectral norm `||A||₂`, which is the largest singular value, with `iters`
+    /// steps of power iteration on `AᵀA`. `AᵀA` is applied as `Aᵀ(Ax)`, so it is never
+    /// formed. The estimate is a lower bound which increases towards the norm.
+    #[cfg(feature = "std")]
+    pub fn norm_2_estimate(&self, iters: usize) -> F {
+        use crate::vecops::{norm2, scal};
+        if self.nnz() == 0 {
+            return 0.;
+        }
+        // A deterministic pseudo random start (a Weyl sequence), since a structured vector
+        // such as all ones is in the null space of common matrices like differences.
+        let mut x: Vec<F> = (1..=self.ncols() as u64)
+            .map(|i| (i.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 11) as F / (1u64 << 53) as F - 0.5)
+            .collect();
+        scal(1. / norm2(&x), &mut x);
+        let mut restarted = false;
+        for _ in 0..iters {
+            let ata_x = self.vecmul_transpose(&self.vecmul(&x));
+            let norm = norm2(&ata_x);
+            if norm == 0. {
+                if restarted {
+                    break;
+                }
+                // x is in the null space, so restart from the column with the largest norm,
+                // for which `Ax` is that nonzero column.
+                restarted = true;
+                let col_norms = self.fold_columns(|| 0., |s: F, _, v| s + v * v);
+                let c = (0..col_norms.len())
+                    .max_by(|&i, &j| col_norms[i].total_cmp(&col_norms[j]))
+                    .unwrap();
+                x.fill(0.);
+                x[c] = 1.;
+                continue;
+            }
+            x = ata_x;
+            scal(1. / norm, &mut x);
+        }
+        norm2(&self.vecmul(&x))
+    }
+    /// Folds the `(row, value)` entries of each column into an accumulator starting from
+    /// `init()`, returning one accumulator per column. This is the general form of per column
+    /// reductions such as sums, norms and maximums.
+    pub fn fold_columns<A>(&self, init: impl Fn() -> A, f: impl Fn(A, usize, &F) -> A) -> Vec<A> {
+        (0..self.ncols())
+            .map(|c| self.col_iter(c).fold(init(), |acc, (r, v)| f(acc, r, v)))
+            .collect()
+    }
+    /// Computes the largest magnitude of each column, which is 0 for empty columns.
+    pub fn column_max_abs(&self) -> Vec<F> {
+        self.fold_columns(|| 0., |m: F, _, v| m.max(v.abs()))
+    }
+    /// Cheaply screens whether this matrix could be symmetric positive definite, by checking
+    /// that it is exactly symmetric, that its diagonal is positive, and that every 2x2
+    /// principal minor `a_ii a_jj - a_ij²` with a stored `a_ij` is positive.
+    ///
+    /// These are necessary but not sufficient conditions, so this is a heuristic: `false`
+    /// means the matrix is definitely not SPD, while `true` means a Cholesky factorization
+    /// may still fail.
+    pub fn is_likely_spd(&self) -> bool {
+        if self.nrows() != self.ncols() {
+            return false;
+        }
+        let mut diag = Vec::with_capacity(self.ncols());
+        for c in 0..self.ncols() {
+            match self.get(c, c) {
+                Some(&d) if d > 0. => diag.push(d),
+                _ => return false,
+            }
+        }
+        (0..self.ncols()).all(|c| {
+            self.col_iter(c)
+                .all(|(r, &v)| r == c || (self.get(c, r) == Some(&v) && v * v < diag[r] * diag[c]))
+        })
+    }
+    /// Computes `1 / sqrt(A_ii)` for each diagonal entry, which is the scaling `D` of the
+    /// symmetric Jacobi preconditioner `DAD`. Missing diagonal entries are treated as zero.
+    #[cfg(feature = "std")]
+    pub fn diagonal_sqrt_inv(&self) -> Result<Vec<F>, DiagonalError> {
+        self.map_positive_diagonal(|d| d.sqrt().recip())
+    }
+    /// Applies `f` to each diagonal entry, after checking that it is positive and finite.
+    /// Missing diagonal entries are treated as zero.
+    pub(crate) fn map_positive_diagonal(
+        &self,
+        f: impl Fn(F) -> F,
+    ) -> Result<Vec<F>, DiagonalError> {
+        assert_eq!(self.nrows(), self.ncols());
+        (0..self.ncols())
+            .map(|i| {
+                let value = self.get(i, i).copied().unwrap_or(0.);
+                if value > 0. && value.is_finite() {
+                    Ok(f(value))
+                } else {
+                    Err(DiagonalError::NotPositive { index: i, value })
+                }
+            })
+            .collect()
+    }
+    /// Computes `DAD`, where `D` is the diagonal matrix with `d` along its diagonal, such as
+    /// the scaling from [`diagonal_sqrt_inv`](Self::diagonal_sqrt_inv).
+    pub fn scale_symmetric(&self, d: &[F]) -> Csc<F> {
+        assert_eq!(self.nrows(), self.ncols());
+        assert_eq!(d.len(), self.ncols());
+        let mut out = self.clone();
+        for c in 0..out.ncols() {
+            for (r, v) in out.col_iter_mut(c) {
+                *v *= d[r] * d[c];
+            }
+        }
+        out
+    }
+    /// Computes `Aᵀv`, without explicitly forming the transpose.
+    pub fn vecmul_transpose(&self, v: &[F]) -> Vec<F> {
+        assert_eq!(v.len(), self.nrows());
+        (0..self.ncols())
+            .map(|i| self.col_iter(i).map(|(r, &val)| val * v[r]).sum())
+            .collect()
+    }
+}
+
+impl From<Vec<Vec<F>>> for Csc<F> {
+    /// Constructs a CSC matrix from dense rows, dropping zeros.
+    fn from(rows: Vec<Vec<F>>) -> Self {
+        Csc::from_rows(&rows, ZeroPolicy::Drop)
+    }
 }
 
 /// An incremental builder for a Csc matrix.
@@ -297,10 +1568,37 @@ impl<T> CscBuilder<T> {
     pub fn revert_to_col(&mut self, col: usize) -> bool {
         self.0.revert_to_major(col)
     }
-    /// Inserts a value into the builder. Must be called in ascending col, row order.
+    /// Captures the current state of this builder, so that entries inserted after it can be
+    /// undone with [`restore`](Self::restore).
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.0.checkpoint()
+    }
+    /// Restores this builder to `checkpoint`, deleting any entries inserted after it was
+    /// taken. Fails without modifying the builder if the checkpoint is ahead of the current
+    /// state, such as after reverting past it.
+    pub fn restore(&mut self, checkpoint: Checkpoint) -> Result<(), RestoreError> {
+        self.0.restore(checkpoint)
+    }
+    /// Checks that columns and rows are in ascending order and that there is one value per
+    /// entry, panicking if not. Only checked in debug builds.
+    pub fn assert_valid(&self) {
+        self.0.assert_valid();
+    }
+    /// Inserts a value into the builder. Must be called in ascending col, row order, and
+    /// fails with [`BuilderInsertError::OutOfBounds`] for a `(col, row)` outside the matrix.
     pub fn insert(&mut self, row: usize, col: usize, val: T) -> Result<(), BuilderInsertError> {
         self.0.insert(col, row, val)
     }
+    /// Inserts a value into the builder like [`insert`](Self::insert), returning the index
+    /// of the value in [`Csc::values`] of the built matrix.
+    pub fn insert_at(
+        &mut self,
+        row: usize,
+        col: usize,
+        val: T,
+    ) -> Result<usize, BuilderInsertError> {
+        self.0.insert_at(col, row, val)
+    }
 
     pub fn insert_sum(
         &mut self,
@@ -315,7 +1613,66 @@ impl<T> CscBuilder<T> {
         self.0.insert_sum(col, row, val, add)
     }
     /// Converts this builder into a valid Csc.
+    /// Any columns after the last inserted entry are left empty.
     pub fn build(self) -> Csc<T> {
         Csc(self.0.build())
     }
+    /// Converts this builder into a valid Csc, failing if no entries were inserted into the
+    /// last column, which usually means the builder was not finished.
+    pub fn build_checked(self) -> Result<Csc<T>, BuildError> {
+        self.0.build_checked().map(Csc)
+    }
+}
+
+/// A builder for a Csc matrix which accepts entries in any order.
+/// Entries are sorted when building, and duplicate entries are summed unless built with
+/// another [`DuplicatePolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnorderedCscBuilder<T> {
+    rows: usize,
+    cols: usize,
+    triplets: Vec<([usize; 2], T)>,
+}
+
+impl<T> UnorderedCscBuilder<T> {
+    /// Constructs a new instance of an unordered Csc builder.
+    pub fn new(rows: usize, cols: usize) -> Self {
+        Self {
+            rows,
+            cols,
+            triplets: vec![],
+        }
+    }
+    /// Inserts a value into the builder, in any order.
+    pub fn insert(&mut self, row: usize, col: usize, val: T) {
+        assert!(row < self.rows);
+        assert!(col < self.cols);
+        self.triplets.push(([col, row], val));
+    }
+    /// Converts this builder into a valid Csc, summing any duplicate entries.
+    pub fn build(self) -> Csc<T>
+    where
+        T: Copy + core::ops::Add<Output = T>,
+    {
+        self.build_with_duplicates(DuplicatePolicy::Sum)
+            .expect("Sorted triplets should always be valid")
+    }
+    /// Converts this builder into a valid Csc, where `policy` decides how duplicate entries
+    /// are combined, in the order they were inserted.
+    pub fn build_with_duplicates(
+        mut self,
+        policy: DuplicatePolicy,
+    ) -> Result<Csc<T>, BuilderInsertError>
+    where
+        T: Copy + core::ops::Add<Output = T>,
+    {
+        Csc::from_triplets_by(
+            self.rows,
+            self.cols,
+            &mut self.triplets,
+            policy,
+            |a, b| a + b,
+            |_| true,
+        )
+    }
 }
@@ -0,0 +1,59 @@
+use super::{F, I};
+use alloc::vec::Vec;
+
+use super::SparsityPattern;
+use super::cs::CsMatrix;
+use crate::csc::Csc;
+
+/// A sparse matrix stored by rows, which is usually constructed with [`Csc::to_csr`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Csr<T>(pub(crate) CsMatrix<T>);
+
+impl<T> Csr<T> {
+    pub fn nrows(&self) -> usize {
+        self.0.pattern.major_dim()
+    }
+    pub fn ncols(&self) -> usize {
+        self.0.pattern.minor_dim
+    }
+    /// Iterates over each `(col, &value)` in row `i`.
+    pub fn row_iter(&self, i: usize) -> impl DoubleEndedIterator<Item = (usize, &T)> + '_ {
+        self.0.lane_iter(i)
+    }
+    /// The values of row `i`, along with their column indices.
+    pub fn row(&self, i: usize) -> (&[T], &[I]) {
+        self.0.lane(i)
+    }
+    /// The sparsity pattern, where majors are rows and minors are columns.
+    pub fn pattern(&self) -> &SparsityPattern {
+        &self.0.pattern
+    }
+    /// Number of non-zero entries in this matrix
+    pub fn nnz(&self) -> usize {
+        self.0.pattern.nnz()
+    }
+    pub fn values(&self) -> &[T] {
+        self.0.values()
+    }
+    pub fn values_mut(&mut self) -> &mut [T] {
+        self.0.values_mut()
+    }
+    /// Converts this matrix back to column major storage, with the same cost as
+    /// [`Csc::to_csr`].
+    pub fn to_csc(&self) -> Csc<T>
+    where
+        T: Clone,
+    {
+        Csc(self.0.transpose())
+    }
+}
+
+impl Csr<F> {
+    /// Computes `Av`.
+    pub fn vecmul(&self, v: &[F]) -> Vec<F> {
+        assert_eq!(v.len(), self.ncols());
+        (0..self.nrows())
+            .map(|i| self.row_iter(i).map(|(c, &val)| val * v[c]).sum())
+            .collect()
+    }
+}
@@ -0,0 +1,157 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::F;
+use crate::csc::Csc;
+use crate::sparse_lu::{LeftLookingLUFactorization, LuError};
+
+/// An LU factorization which factorizes the leading block of a matrix sparsely, and its
+/// trailing `k × k` block densely, for matrices which are sparse except for a few dense rows
+/// and columns at the end. Factorizing those sparsely would fill in the rest of the matrix.
+///
+/// With `A = [[A11, A12], [A21, A22]]`, `A11` is factorized sparsely, and the dense Schur
+/// complement `S = A22 - A21 A11⁻¹ A12` is factorized with partial pivoting.
+///
+/// Pivoting never crosses the block boundary, so `A11` must be nonsingular by itself. A
+/// nonsingular `A` whose leading block is singular fails to factorize, even though
+/// [`LeftLookingLUFactorization`] could factorize it by swapping in rows of `A21`.
+#[derive(Debug, Clone)]
+pub struct HybridLu {
+    sparse: LeftLookingLUFactorization<F>,
+    a12: Csc<F>,
+    a21: Csc<F>,
+    /// Row major LU factors of the Schur complement, with L unit lower triangular.
+    dense: Vec<Vec<F>>,
+    /// `dense_pivot[i]` is the row of the Schur complement at position `i`.
+    dense_pivot: Vec<usize>,
+}
+
+impl HybridLu {
+    /// Factorizes `a`, where the dense block contains the trailing columns which have at
+    /// least `density * nrows` entries. Only columns at the end are detected, and counting
+    /// stops at the first sparse column, so dense columns elsewhere or dense rows are not
+    /// moved into the dense block. Use [`with_block`](Self::with_block) after permuting `a`
+    /// for those.
+    pub fn new(a: &Csc<F>, density: F) -> Result<Self, LuError> {
+        let n = a.ncols();
+        let dense_cols = (0..n)
+            .rev()
+            .take_while(|&c| a.col(c).1.len() as F >= density * n as F)
+            .count();
+        Self::with_block(a, dense_cols)
+    }
+
+    /// Factorizes `a`, where the trailing `k × k` block is factorized densely. Returns
+    /// [`LuError::Singular`] if the leading `(n - k) × (n - k)` block is singular, even if `a`
+    /// is not.
+    pub fn with_block(a: &Csc<F>, k: usize) -> Result<Self, LuError> {
+        let n = a.nrows();
+        assert_eq!(n, a.ncols());
+        assert!(k <= n);
+        let s = n - k;
+        let sparse = LeftLookingLUFactorization::try_new(&a.submatrix(0..s, 0..s))?;
+        let a12 = a.submatrix(0..s, s..n);
+        let a21 = a.submatrix(s..n, 0..s);
+
+        // S = A22 - A21 A11⁻¹ A12, formed a column at a time.
+        let mut dense = vec![vec![0.; k]; k];
+        let a22 = a.submatrix(s..n, s..n);
+        for ([c, r], &v) in a22.pattern().entries().zip(a22.values()) {
+            dense[r][c] = v;
+        }
+        let mut col = vec![0.; s];
+        let mut buf = vec![0.; s];
+        for j in 0..k {
+            if a12.col_iter(j).next().is_none() {
+                continue;
+            }
+            col.fill(0.);
+            for (r, &v) in a12.col_iter(j) {
+                col[r] = v;
+            }
+            sparse.solve(&mut col, &mut buf);
+            for (row, v) in dense.iter_mut().zip(a21.vecmul(&col)) {
+                row[j] -= v;
+            }
+        }
+
+        // dense LU with partial pivoting
+        let mut dense_pivot: Vec<usize> = (0..k).collect();
+        for c in 0..k {
+            let p = (c..k)
+                .max_by(|&x, &y| dense[x][c].abs().total_cmp(&dense[y][c].abs()))
+                .unwrap();
+            if dense[p][c] == 0. || !dense[p][c].is_finite() {
+                return Err(LuError::Singular { column: s + c });
+            }
+            dense.swap(c, p);
+            dense_pivot.swap(c, p);
+            let (top, rest) = dense.split_at_mut(c + 1);
+            let pivot_row = &top[c];
+            for row in rest {
+                let l = row[c] / pivot_row[c];
+                row[c] = l;
+                for (v, &u) in row[c + 1..].iter_mut().zip(&pivot_row[c + 1..]) {
+                    *v -= l * u;
+                }
+            }
+        }
+
+        Ok(Self {
+            sparse,
+            a12,
+            a21,
+            dense,
+            dense_pivot,
+        })
+    }
+
+    /// The size of the densely factorized trailing block.
+    pub fn dense_block_size(&self) -> usize {
+        self.dense.len()
+    }
+
+    /// Computes `x` in `Ax = b`, where `b` is a dense vector.
+    /// The output will be stored in b.
+    pub fn solve(&self, b: &mut [F]) {
+        let k = self.dense.len();
+        let s = self.sparse.pivot().len();
+        assert_eq!(b.len(), s + k);
+        let (b1, b2) = b.split_at_mut(s);
+        let mut buf = vec![0.; s];
+
+        // y1 = A11⁻¹ b1, then S x2 = b2 - A21 y1
+        self.sparse.solve(b1, &mut buf);
+        let mut x2 = self.dense_pivot.iter().map(|&p| b2[p]).collect::<Vec<_>>();
+        let a21_y1 = self.a21.vecmul(b1);
+        for (x, &p) in x2.iter_mut().zip(&self.dense_pivot) {
+            *x -= a21_y1[p];
+        }
+        for i in 0..k {
+            let (done, rest) = x2.split_at_mut(i);
+            rest[0] -= self.dense[i][..i]
+                .iter()
+                .zip(done)
+                .map(|(l, x)| l * *x)
+                .sum::<F>();
+        }
+        for i in (0..k).rev() {
+            let (head, done) = x2.split_at_mut(i + 1);
+            let row = &self.dense[i];
+            head[i] -= row[i + 1..]
+                .iter()
+                .zip(&*done)
+                .map(|(u, x)| u * x)
+                .sum::<F>();
+            head[i] /= row[i];
+        }
+
+        // x1 = y1 - A11⁻¹ A12 x2
+        let mut correction = self.a12.vecmul(&x2);
+        self.sparse.solve(&mut correction, &mut buf);
+        for (x, c) in b1.iter_mut().zip(correction) {
+            *x -= c;
+        }
+        b2.copy_from_slice(&x2);
+    }
+}
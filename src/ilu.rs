@@ -0,0 +1,73 @@
+use super::{F, to_index};
+use crate::csc::Csc;
+use crate::sparse_lu::LuError;
+use alloc::vec;
+
+/// An incomplete LU factorization with no fill, ILU(0), where `L` and `U` are restricted to
+/// the sparsity pattern of `A`. This is an approximation of `A`, intended for use as a
+/// preconditioner rather than as a direct solver.
+#[derive(Debug, Clone)]
+pub struct IluFactorization {
+    /// A single matrix stores both the lower and upper triangular components, with the
+    /// same pattern as `A`. `L` implicitly has 1 along the diagonal.
+    l_u: Csc<F>,
+}
+
+impl IluFactorization {
+    /// Computes the ILU(0) factorization of `a`, without pivoting. Returns
+    /// [`LuError::ZeroDiagonal`] if a diagonal entry is missing or becomes zero during
+    /// elimination.
+    pub fn new(a: &Csc<F>) -> Result<Self, LuError> {
+        let n = a.nrows();
+        assert_eq!(n, a.ncols());
+        let mut csr = a.to_csr();
+        let (pattern, vals) = csr.0.parts_mut();
+        let mut diag = vec![usize::MAX; n];
+        // position of each column in the current row, if it is stored.
+        let mut pos = vec![usize::MAX; n];
+        for i in 0..n {
+            let row = pattern.lane_range(i);
+            for j in row.clone() {
+                pos[pattern.minor(j)] = j;
+            }
+            for ik in row.clone() {
+                let k = pattern.minor(ik);
+                if k >= i {
+                    break;
+                }
+                let l = vals[ik] / vals[diag[k]];
+                vals[ik] = l;
+                for kj in diag[k] + 1..pattern.lane_range(k).end {
+                    let p = pos[pattern.minor(kj)];
+                    if p != usize::MAX {
+                        vals[p] -= l * vals[kj];
+                    }
+                }
+            }
+            for j in row.clone() {
+                pos[pattern.minor(j)] = usize::MAX;
+            }
+            diag[i] = match pattern.lane(i).binary_search(&to_index(i)) {
+                Ok(d) => row.start + d,
+                Err(_) => return Err(LuError::ZeroDiagonal { row: i }),
+            };
+            let d = vals[diag[i]];
+            if d == 0. || !d.is_finite() {
+                return Err(LuError::ZeroDiagonal { row: i });
+            }
+        }
+        Ok(Self { l_u: csr.to_csc() })
+    }
+
+    /// Returns the joint L\U matrix. Here, `L` implicitly has 1 along the diagonal.
+    pub fn lu(&self) -> &Csc<F> {
+        &self.l_u
+    }
+
+    /// Computes `x ≈ A⁻¹b` by solving `LUx = b`, where `b` is a dense vector.
+    /// The output will be stored in b, and buf is used as a temporary buffer.
+    pub fn solve(&self, b: &mut [F], buf: &mut [F]) {
+        self.l_u.dense_lower_triangular_solve(b, buf, true);
+        self.l_u.dense_upper_triangular_solve(buf, b);
+    }
+}
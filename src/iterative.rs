@@ -0,0 +1,373 @@
+use super::F;
+use crate::csc::Csc;
+use crate::operator::{LinearOperator, Preconditioner, TransposeOperator};
+use crate::sparse_lu::LeftLookingLUFactorization;
+use crate::vecops::{axpy, dot, norm2, scal};
+use alloc::vec;
+
+/// Summary of a run of an iterative solver.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct IterativeResult {
+    /// Number of iterations performed.
+    pub iterations: usize,
+    /// Whether the stopping tolerance was reached before the iteration limit.
+    pub converged: bool,
+    /// Final residual norm as estimated by the solver.
+    pub residual_norm: F,
+}
+
+/// Which method was used by [`Csc::solve_or_least_squares`].
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SolveKind {
+    /// The matrix was nonsingular and was solved with an LU factorization.
+    Direct,
+    /// The matrix was singular, so `x` minimizes `||Ax - b||` as computed by LSMR. The
+    /// residual norm of the result is `||Aᵀ(b - Ax)||`, and the second value is `||b - Ax||`,
+    /// which is nonzero when `b` is inconsistent.
+    LeastSquares(IterativeResult, F),
+}
+
+/// Stable construction of a Givens rotation, returning `(c, s, r)` such that
+/// `[c s; -s c] [a; b] = [r; 0]`.
+fn sym_ortho(a: F, b: F) -> (F, F, F) {
+    if b == 0. {
+        (a.signum(), 0., a.abs())
+    } else if a == 0. {
+        (0., b.signum(), b.abs())
+    } else if b.abs() > a.abs() {
+        let tau = a / b;
+        let s = b.signum() / (1. + tau * tau).sqrt();
+        (s * tau, s, b / s)
+    } else {
+        let tau = b / a;
+        let c = a.signum() / (1. + tau * tau).sqrt();
+        (c, c * tau, a / c)
+    }
+}
+
+impl Csc<F> {
+    /// Solves `Ax = b` using the conjugate gradient method. See [`cg`].
+    pub fn cg_solve(&self, b: &[F], x: &mut [F], max_iter: usize, tol: F) -> IterativeResult {
+        cg(self, b, x, max_iter, tol)
+    }
+
+    /// Solves `min ||Ax - b||² + damp² ||x||²` using LSMR. See [`lsmr`].
+    pub fn lsmr_solve(
+        &self,
+        b: &[F],
+        x: &mut [F],
+        max_iter: usize,
+        tol: F,
+        damp: F,
+    ) -> (IterativeResult, F) {
+        lsmr(self, b, x, max_iter, tol, damp)
+    }
+
+    /// Solves `Ax = b` directly with an LU factorization, falling back to a least squares
+    /// solve if `A` is singular or not square. This always produces some `x`, and returns
+    /// which method was used.
+    ///
+    /// `A` is treated as singular when [`LeftLookingLUFactorization::try_new`] fails, which
+    /// happens when a pivot is no larger than
+    /// [`DEFAULT_PIVOT_TOLERANCE`](LeftLookingLUFactorization::DEFAULT_PIVOT_TOLERANCE) times
+    /// the largest entry of its column, so a matrix which is only singular up to rounding also
+    /// falls back. The fallback uses [`lsmr`], not LSQR, as it is better behaved when stopped
+    /// early.
+    ///
+    /// The least squares solve starts from zero, so for a consistent singular system it finds
+    /// the solution with minimum norm.
+    pub fn solve_or_least_squares(&self, b: &[F], x: &mut [F]) -> SolveKind {
+        assert_eq!(b.len(), self.nrows());
+        assert_eq!(x.len(), self.ncols());
+        if self.nrows() == self.ncols()
+            && let Ok(lu) = LeftLookingLUFactorization::try_new(self)
+        {
+            x.copy_from_slice(b);
+            lu.solve(x, &mut vec![0.; b.len()]);
+            if x.iter().all(|v| v.is_finite()) {
+                return SolveKind::Direct;
+            }
+        }
+        x.fill(0.);
+        let max_iter = 10 * self.ncols().max(10);
+        let (result, norm_r) = lsmr(self, b, x, max_iter, F::EPSILON.sqrt(), 0.);
+        SolveKind::LeastSquares(result, norm_r)
+    }
+}
+
+/// Solves `Ax = b` using the conjugate gradient method, where `A` must be symmetric
+/// positive definite.
+///
+/// `x` is used as the initial guess, and will contain the solution on return. Stops once
+/// `||b - Ax|| <= tol ||b||`, which is returned as the residual norm of the
+/// `IterativeResult`. If `A` is singular or indefinite the iteration can break down, in which
+/// case it stops early without converging, and the residual norm is NaN or infinite.
+pub fn cg(
+    a: &(impl LinearOperator + ?Sized),
+    b: &[F],
+    x: &mut [F],
+    max_iter: usize,
+    tol: F,
+) -> IterativeResult {
+    assert_eq!(a.nrows(), a.ncols());
+    assert_eq!(b.len(), a.nrows());
+    assert_eq!(x.len(), a.ncols());
+
+    let mut r = vec![0.; b.len()];
+    a.matvec(x, &mut r);
+    for (r, &b) in r.iter_mut().zip(b) {
+        *r = b - *r;
+    }
+    let norm_b = norm2(b);
+    let mut rr = dot(&r, &r);
+    let mut result = IterativeResult {
+        iterations: 0,
+        converged: false,
+        residual_norm: rr.sqrt(),
+    };
+    let mut p = r.clone();
+    let mut ap = vec![0.; b.len()];
+    while result.residual_norm > tol * norm_b {
+        if result.iterations == max_iter {
+            return result;
+        }
+        result.iterations += 1;
+
+        a.matvec(&p, &mut ap);
+        let alpha = rr / dot(&p, &ap);
+        axpy(alpha, &p, x);
+        axpy(-alpha, &ap, &mut r);
+
+        let rr_new = dot(&r, &r);
+        scal(rr_new / rr, &mut p);
+        axpy(1., &r, &mut p);
+        rr = rr_new;
+        result.residual_norm = rr.sqrt();
+        // a zero curvature `pᵀAp` divides by zero, and NaN would otherwise end the loop.
+        if !result.residual_norm.is_finite() {
+            return result;
+        }
+    }
+    result.converged = true;
+    result
+}
+
+/// Solves `Ax = b` using the preconditioned conjugate gradient method, where both `A` and
+/// the preconditioner `m` must be symmetric positive definite. Otherwise the same as [`cg`],
+/// and converges in fewer iterations the closer `m` is to `A`. A singular `m` also makes the
+/// iteration break down, which stops early without converging.
+pub fn pcg(
+    a: &(impl LinearOperator + ?Sized),
+    m: &dyn Preconditioner,
+    b: &[F],
+    x: &mut [F],
+    max_iter: usize,
+    tol: F,
+) -> IterativeResult {
+    assert_eq!(a.nrows(), a.ncols());
+    assert_eq!(b.len(), a.nrows());
+    assert_eq!(x.len(), a.ncols());
+
+    let mut r = vec![0.; b.len()];
+    a.matvec(x, &mut r);
+    for (r, &b) in r.iter_mut().zip(b) {
+        *r = b - *r;
+    }
+    let norm_b = norm2(b);
+    let mut z = vec![0.; b.len()];
+    let mut buf = vec![0.; b.len()];
+    m.apply(&r, &mut z, &mut buf);
+    let mut rz = dot(&r, &z);
+    let mut result = IterativeResult {
+        iterations: 0,
+        converged: false,
+        residual_norm: norm2(&r),
+    };
+    let mut p = z.clone();
+    let mut ap = vec![0.; b.len()];
+    while result.residual_norm > tol * norm_b {
+        if result.iterations == max_iter {
+            return result;
+        }
+        result.iterations += 1;
+
+        a.matvec(&p, &mut ap);
+        let alpha = rz / dot(&p, &ap);
+        axpy(alpha, &p, x);
+        axpy(-alpha, &ap, &mut r);
+
+        m.apply(&r, &mut z, &mut buf);
+        let rz_new = dot(&r, &z);
+        scal(rz_new / rz, &mut p);
+        axpy(1., &z, &mut p);
+        rz = rz_new;
+        result.residual_norm = norm2(&r);
+        // a zero `rᵀz` or `pᵀAp` divides by zero, and NaN would otherwise end the loop.
+        if !result.residual_norm.is_finite() {
+            return result;
+        }
+    }
+    result.converged = true;
+    result
+}
+
+/// Solves `min ||Ax - b||² + damp² ||x||²` using LSMR (Fong & Saunders).
+/// `A` may be rectangular, in which case `b` has `nrows` entries and `x` has `ncols`.
+///
+/// `x` is used as the initial guess, and will contain the solution on return.
+/// When starting from a non-zero guess, damping is applied to the correction from `x`.
+///
+/// LSMR monotonically decreases `||Aᵀr||`, which is returned as the residual norm of the
+/// `IterativeResult`. The second value is the estimate of the least-squares residual
+/// `||r||`.
+pub fn lsmr(
+    a: &(impl TransposeOperator + ?Sized),
+    b: &[F],
+    x: &mut [F],
+    max_iter: usize,
+    tol: F,
+    damp: F,
+) -> (IterativeResult, F) {
+    assert_eq!(b.len(), a.nrows());
+    assert_eq!(x.len(), a.ncols());
+
+    let mut u = vec![0.; a.nrows()];
+    a.matvec(x, &mut u);
+    for (u, &b) in u.iter_mut().zip(b.iter()) {
+        *u = b - *u;
+    }
+    let mut beta = norm2(&u);
+    if beta > 0. {
+        scal(1. / beta, &mut u);
+    }
+    let mut v = vec![0.; a.ncols()];
+    a.matvec_transpose(&u, &mut v);
+    let mut alpha = norm2(&v);
+    if alpha > 0. {
+        scal(1. / alpha, &mut v);
+    }
+
+    let mut result = IterativeResult {
+        iterations: 0,
+        converged: true,
+        residual_norm: alpha * beta,
+    };
+    if alpha * beta == 0. {
+        return (result, beta);
+    }
+    result.converged = false;
+
+    let mut zetabar = alpha * beta;
+    let mut alphabar = alpha;
+    let mut rho = 1.;
+    let mut rhobar = 1.;
+    let mut cbar = 1.;
+    let mut sbar = 0.;
+
+    let mut h = v.clone();
+    let mut hbar = vec![0.; a.ncols()];
+    let mut dx = vec![0.; a.ncols()];
+    let mut av = vec![0.; a.nrows()];
+    let mut atu = vec![0.; a.ncols()];
+
+    // used for estimating ||r||
+    let mut betadd = beta;
+    let mut betad = 0.;
+    let mut rhodold = 1.;
+    let mut tautildeold = 0.;
+    let mut thetatilde = 0.;
+    let mut zeta = 0.;
+    let mut d = 0.;
+
+    let mut norm_a2 = alpha * alpha;
+    let norm_b = beta;
+    let mut norm_r = beta;
+
+    while result.iterations < max_iter {
+        result.iterations += 1;
+
+        // Golub-Kahan bidiagonalization step
+        a.matvec(&v, &mut av);
+        for (u, &av) in u.iter_mut().zip(&av) {
+            *u = av - alpha * *u;
+        }
+        beta = norm2(&u);
+        if beta > 0. {
+            scal(1. / beta, &mut u);
+            a.matvec_transpose(&u, &mut atu);
+            for (v, &atu) in v.iter_mut().zip(&atu) {
+                *v = atu - beta * *v;
+            }
+            alpha = norm2(&v);
+            if alpha > 0. {
+                scal(1. / alpha, &mut v);
+            }
+        }
+
+        // rotation to eliminate the damping
+        let (chat, shat, alphahat) = sym_ortho(alphabar, damp);
+
+        let rhoold = rho;
+        let (c, s, rho_new) = sym_ortho(alphahat, beta);
+        rho = rho_new;
+        let thetanew = s * alpha;
+        alphabar = c * alpha;
+
+        let rhobarold = rhobar;
+        let zetaold = zeta;
+        let thetabar = sbar * rho;
+        let (cbar_new, sbar_new, rhobar_new) = sym_ortho(cbar * rho, thetanew);
+        cbar = cbar_new;
+        sbar = sbar_new;
+        rhobar = rhobar_new;
+        zeta = cbar * zetabar;
+        zetabar *= -sbar;
+
+        let hbar_scale = thetabar * rho / (rhoold * rhobarold);
+        let x_scale = zeta / (rho * rhobar);
+        let h_scale = thetanew / rho;
+        for i in 0..dx.len() {
+            hbar[i] = h[i] - hbar_scale * hbar[i];
+            dx[i] += x_scale * hbar[i];
+            h[i] = v[i] - h_scale * h[i];
+        }
+
+        // estimate ||r||
+        let betaacute = chat * betadd;
+        let betacheck = -shat * betadd;
+        let betahat = c * betaacute;
+        betadd = -s * betaacute;
+
+        let thetatildeold = thetatilde;
+        let (ctildeold, stildeold, rhotildeold) = sym_ortho(rhodold, thetabar);
+        thetatilde = stildeold * rhobar;
+        rhodold = ctildeold * rhobar;
+        betad = -stildeold * betad + ctildeold * betahat;
+
+        tautildeold = (zetaold - thetatildeold * tautildeold) / rhotildeold;
+        let taud = (zeta - thetatilde * tautildeold) / rhodold;
+        d += betacheck * betacheck;
+        norm_r = (d + (betad - taud) * (betad - taud) + betadd * betadd).sqrt();
+
+        // estimate ||A||
+        norm_a2 += beta * beta;
+        let norm_a = norm_a2.sqrt();
+        norm_a2 += alpha * alpha;
+
+        result.residual_norm = zetabar.abs();
+        let norm_x = norm2(&dx);
+
+        let test1 = norm_r / norm_b;
+        let test2 = result.residual_norm / (norm_a * norm_r);
+        if test2 <= tol || test1 <= tol + tol * norm_a * norm_x / norm_b {
+            result.converged = true;
+            break;
+        }
+    }
+
+    for (x, dx) in x.iter_mut().zip(dx) {
+        *x += dx;
+    }
+
+    (result, norm_r)
+}
@@ -1,22 +1,146 @@
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 #[cfg(not(feature = "f64"))]
 pub type F = f32;
 
 #[cfg(feature = "f64")]
 pub type F = f64;
 
+/// Index type used to store sparsity patterns.
+#[cfg(not(feature = "u32"))]
+pub type I = usize;
+
+/// Index type used to store sparsity patterns.
+/// Dimensions and the number of entries of each matrix must fit in a `u32`.
+#[cfg(feature = "u32")]
+pub type I = u32;
+
+/// Converts a stored index into a `usize`.
+#[inline]
+#[allow(clippy::unnecessary_cast)]
+pub(crate) fn to_usize(i: I) -> usize {
+    i as usize
+}
+
+/// Converts a `usize` into a stored index.
+#[cfg(not(feature = "u32"))]
+#[inline]
+pub(crate) fn to_index(i: usize) -> I {
+    i
+}
+
+/// Converts a `usize` into a stored index, if it fits.
+#[cfg(not(feature = "u32"))]
+#[inline]
+pub(crate) fn try_to_index(i: usize) -> Option<I> {
+    Some(i)
+}
+
+/// Converts a `usize` into a stored index, if it fits.
+#[cfg(feature = "u32")]
+#[inline]
+pub(crate) fn try_to_index(i: usize) -> Option<I> {
+    I::try_from(i).ok()
+}
+
+/// Converts a `usize` into a stored index, panicking if it does not fit in a `u32` rather
+/// than silently wrapping.
+#[cfg(feature = "u32")]
+#[inline]
+pub(crate) fn to_index(i: usize) -> I {
+    I::try_from(i).expect("index exceeds the range of u32")
+}
+
 /// Constructor for a given sparsity pattern
 mod builder;
-pub use builder::{BuilderInsertError, SparsityPattern, SparsityPatternBuilder};
+pub use builder::{
+    BuildError, BuilderInsertError, Checkpoint, PatternError, RestoreError, SparsityPattern,
+    SparsityPatternBuilder,
+};
 
 /// Compressed Sparse Matrix.
 mod cs;
 
 /// Compressed Sparse Column Matrix
 pub mod csc;
-pub use csc::Csc;
+pub use csc::{Csc, DiagonalError, DuplicatePolicy, SetColumnError, TriangularError, ZeroPolicy};
+
+/// Compressed Sparse Row Matrix
+pub mod csr;
+pub use csr::Csr;
+
+/// Symmetric matrices storing only the lower triangle
+mod symmetric;
+pub use symmetric::SymmetricCsc;
 
 /// Sparse LU algorithm
 mod sparse_lu;
-pub use sparse_lu::LeftLookingLUFactorization;
+#[cfg(feature = "profiling")]
+pub use sparse_lu::ColumnProfile;
+pub use sparse_lu::{
+    CompactLu, LeftLookingLUFactorization, LuError, LuScratch, LuSymbolic, PivotStrategy,
+};
+
+/// Fill reducing and pivot orderings
+mod ordering;
+
+/// Sparse vectors
+mod sparse_vec;
+pub use sparse_vec::SparseVec;
+
+/// Dense vector primitives
+pub mod vecops;
+
+/// Parallel triangular solves
+#[cfg(feature = "parallel")]
+mod parallel;
+
+/// LU factorization with a dense trailing block
+mod hybrid;
+pub use hybrid::HybridLu;
+
+/// Sparse QR factorization
+#[cfg(feature = "std")]
+mod qr;
+#[cfg(feature = "std")]
+pub use qr::SparseQr;
+
+/// Sparse Cholesky factorization
+#[cfg(feature = "std")]
+mod cholesky;
+#[cfg(feature = "std")]
+pub use cholesky::{CholeskyError, CholeskyFactorization};
+
+/// Binary serialization of matrices
+#[cfg(feature = "std")]
+mod binary;
+#[cfg(feature = "std")]
+pub use binary::ReadBinaryError;
+
+/// Iterative solvers
+#[cfg(feature = "std")]
+mod iterative;
+#[cfg(feature = "std")]
+pub use iterative::{IterativeResult, SolveKind, cg, lsmr, pcg};
+
+/// Reproducible random matrices
+#[cfg(feature = "random")]
+mod random;
+
+/// Assertions for tests
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
+
+/// Incomplete LU factorization
+mod ilu;
+pub use ilu::IluFactorization;
+
+/// Matrix-free linear operators and preconditioners
+mod operator;
+pub use operator::{Jacobi, LinearOperator, Preconditioner, TransposeOperator};
 
 // TODO implement gauss seidel?
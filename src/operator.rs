@@ -0,0 +1,141 @@
+use super::F;
+#[cfg(feature = "std")]
+use crate::cholesky::CholeskyFactorization;
+use crate::csc::{Csc, DiagonalError};
+use crate::ilu::IluFactorization;
+use crate::sparse_lu::{CompactLu, LeftLookingLUFactorization};
+use crate::symmetric::SymmetricCsc;
+use alloc::vec::Vec;
+
+/// A linear map `y = Ax`, which only needs to be applied to vectors rather than stored as a
+/// matrix. This allows the iterative solvers to be used matrix-free.
+pub trait LinearOperator {
+    /// Length of `Ax`.
+    fn nrows(&self) -> usize;
+    /// Length of `x`.
+    fn ncols(&self) -> usize;
+    /// Computes `out = Ax`, overwriting `out`.
+    fn matvec(&self, x: &[F], out: &mut [F]);
+}
+
+/// A linear operator which can also apply its transpose, as needed by least squares solvers.
+pub trait TransposeOperator: LinearOperator {
+    /// Computes `out = Aᵀx`, overwriting `out`.
+    fn matvec_transpose(&self, x: &[F], out: &mut [F]);
+}
+
+impl LinearOperator for Csc<F> {
+    fn nrows(&self) -> usize {
+        Csc::nrows(self)
+    }
+    fn ncols(&self) -> usize {
+        Csc::ncols(self)
+    }
+    fn matvec(&self, x: &[F], out: &mut [F]) {
+        assert_eq!(x.len(), Csc::ncols(self));
+        assert_eq!(out.len(), Csc::nrows(self));
+        out.fill(0.);
+        for (c, &x) in x.iter().enumerate() {
+            for (r, &v) in self.col_iter(c) {
+                out[r] += x * v;
+            }
+        }
+    }
+}
+
+impl TransposeOperator for Csc<F> {
+    fn matvec_transpose(&self, x: &[F], out: &mut [F]) {
+        assert_eq!(x.len(), Csc::nrows(self));
+        assert_eq!(out.len(), Csc::ncols(self));
+        for (c, o) in out.iter_mut().enumerate() {
+            *o = self.col_iter(c).map(|(r, &v)| v * x[r]).sum();
+        }
+    }
+}
+
+impl LinearOperator for SymmetricCsc<F> {
+    fn nrows(&self) -> usize {
+        self.n()
+    }
+    fn ncols(&self) -> usize {
+        self.n()
+    }
+    fn matvec(&self, x: &[F], out: &mut [F]) {
+        assert_eq!(x.len(), self.n());
+        assert_eq!(out.len(), self.n());
+        out.fill(0.);
+        for c in 0..self.n() {
+            for (r, &a) in self.lower().col_iter(c) {
+                out[r] += a * x[c];
+                if r != c {
+                    out[c] += a * x[r];
+                }
+            }
+        }
+    }
+}
+
+impl TransposeOperator for SymmetricCsc<F> {
+    fn matvec_transpose(&self, x: &[F], out: &mut [F]) {
+        self.matvec(x, out);
+    }
+}
+
+/// An approximation `M ≈ A` which is cheap to invert, used to accelerate iterative solvers.
+pub trait Preconditioner {
+    /// Computes `z = M⁻¹r`, overwriting `z`. `buf` is a temporary buffer of the same length,
+    /// so that it can be reused across applications.
+    fn apply(&self, r: &[F], z: &mut [F], buf: &mut [F]);
+}
+
+/// The Jacobi preconditioner, `M = diag(A)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Jacobi {
+    inv_diag: Vec<F>,
+}
+
+impl Jacobi {
+    /// Constructs the Jacobi preconditioner of `a`, returning an error if a diagonal entry is
+    /// missing or is not a positive finite value.
+    pub fn new(a: &Csc<F>) -> Result<Self, DiagonalError> {
+        let inv_diag = a.map_positive_diagonal(F::recip)?;
+        Ok(Self { inv_diag })
+    }
+}
+
+impl Preconditioner for Jacobi {
+    fn apply(&self, r: &[F], z: &mut [F], _: &mut [F]) {
+        for ((z, r), d) in z.iter_mut().zip(r).zip(&self.inv_diag) {
+            *z = r * d;
+        }
+    }
+}
+
+impl Preconditioner for LeftLookingLUFactorization<F> {
+    fn apply(&self, r: &[F], z: &mut [F], buf: &mut [F]) {
+        z.copy_from_slice(r);
+        self.solve(z, buf);
+    }
+}
+
+impl Preconditioner for CompactLu {
+    fn apply(&self, r: &[F], z: &mut [F], buf: &mut [F]) {
+        z.copy_from_slice(r);
+        self.solve(z, buf);
+    }
+}
+
+impl Preconditioner for IluFactorization {
+    fn apply(&self, r: &[F], z: &mut [F], buf: &mut [F]) {
+        z.copy_from_slice(r);
+        self.solve(z, buf);
+    }
+}
+
+#[cfg(feature = "std")]
+impl Preconditioner for CholeskyFactorization {
+    fn apply(&self, r: &[F], z: &mut [F], buf: &mut [F]) {
+        z.copy_from_slice(r);
+        self.solve(z, buf);
+    }
+}
@@ -0,0 +1,257 @@
+use alloc::collections::BTreeSet;
+#[cfg(feature = "std")]
+use alloc::collections::BinaryHeap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(feature = "std")]
+use crate::{Csc, F, LuError};
+use crate::{SparsityPattern, to_index, to_usize};
+
+/// Computes a minimum degree ordering of the undirected graph with the adjacency lists `adj`,
+/// where `adj` must be symmetric and contain no self loops.
+///
+/// Each step eliminates the remaining node with the fewest neighbours, with ties broken by
+/// the lowest index, and connects all of its neighbours to each other.
+fn minimum_degree(mut adj: Vec<BTreeSet<usize>>) -> Vec<usize> {
+    let n = adj.len();
+    let mut by_degree: BTreeSet<(usize, usize)> = (0..n).map(|v| (adj[v].len(), v)).collect();
+    let mut order = Vec::with_capacity(n);
+    while let Some((_, v)) = by_degree.pop_first() {
+        order.push(v);
+        let nbrs = core::mem::take(&mut adj[v]);
+        for &u in &nbrs {
+            by_degree.remove(&(adj[u].len(), u));
+            adj[u].remove(&v);
+            adj[u].extend(nbrs.iter().copied().filter(|&w| w != u));
+            by_degree.insert((adj[u].len(), u));
+        }
+    }
+    order
+}
+
+impl SparsityPattern {
+    /// Computes a fill reducing ordering for a square pattern using minimum degree on the
+    /// pattern of `A + Aᵀ`. `order[i]` is the original index placed at position `i`, to be
+    /// applied symmetrically to both rows and columns.
+    pub fn minimum_degree_ordering(&self) -> Vec<usize> {
+        let n = self.major_dim();
+        assert_eq!(n, self.minor_dim);
+        let mut adj = vec![BTreeSet::new(); n];
+        for [c, r] in self.entries().filter(|&[c, r]| c != r) {
+            adj[c].insert(r);
+            adj[r].insert(c);
+        }
+        minimum_degree(adj)
+    }
+
+    /// Computes a fill reducing column ordering for LU, similar to COLAMD, where `order[j]`
+    /// is the original column placed at position `j`.
+    ///
+    /// This approximates minimum degree on the pattern of `AᵀA` without forming it. Each
+    /// column is scored by the sum of the sizes of its rows, which bounds its degree in
+    /// `AᵀA`. Eliminating a column merges all of its rows into a single new row, which
+    /// represents the structure of the pivot row of `U`.
+    pub fn colamd_ordering(&self) -> Vec<usize> {
+        let n = self.major_dim();
+        let mut col_rows: Vec<BTreeSet<usize>> = (0..n)
+            .map(|c| self.lane(c).iter().map(|&r| to_usize(r)).collect())
+            .collect();
+        let mut row_cols = vec![BTreeSet::new(); self.minor_dim];
+        for [c, r] in self.entries() {
+            row_cols[r].insert(c);
+        }
+
+        let score = |c: usize, col_rows: &[BTreeSet<usize>], row_cols: &[BTreeSet<usize>]| {
+            col_rows[c]
+                .iter()
+                .map(|&r| row_cols[r].len() - 1)
+                .sum::<usize>()
+                .min(n)
+        };
+        let mut scores: Vec<usize> = (0..n).map(|c| score(c, &col_rows, &row_cols)).collect();
+        let mut by_score: BTreeSet<(usize, usize)> =
+            scores.iter().enumerate().map(|(c, &s)| (s, c)).collect();
+
+        let mut order = Vec::with_capacity(n);
+        while let Some((_, c)) = by_score.pop_first() {
+            order.push(c);
+            let mut pivot_row = BTreeSet::new();
+            for r in core::mem::take(&mut col_rows[c]) {
+                for o in core::mem::take(&mut row_cols[r]) {
+                    col_rows[o].remove(&r);
+                    if o != c {
+                        pivot_row.insert(o);
+                    }
+                }
+            }
+            let e = row_cols.len();
+            for &o in &pivot_row {
+                col_rows[o].insert(e);
+            }
+            row_cols.push(pivot_row);
+            for &o in &row_cols[e] {
+                by_score.remove(&(scores[o], o));
+                scores[o] = score(o, &col_rows, &row_cols);
+                by_score.insert((scores[o], o));
+            }
+        }
+        order
+    }
+
+    /// The fraction of off-diagonal entries `(i, j)` whose transpose `(j, i)` is also present.
+    /// A pattern without any off-diagonal entries is considered symmetric.
+    pub fn symmetry(&self) -> f64 {
+        let mut off_diag = 0;
+        let mut matched = 0;
+        for [c, r] in self.entries().filter(|&[c, r]| c != r) {
+            off_diag += 1;
+            if r < self.major_dim() && self.lane(r).binary_search(&to_index(c)).is_ok() {
+                matched += 1;
+            }
+        }
+        if off_diag == 0 {
+            return 1.;
+        }
+        matched as f64 / off_diag as f64
+    }
+}
+
+/// A row reached by the shortest path search in
+/// [`max_weight_matching`](Csc::max_weight_matching), ordered so that a `BinaryHeap` pops the
+/// smallest distance first.
+#[cfg(feature = "std")]
+struct Reached {
+    dist: F,
+    row: usize,
+}
+
+#[cfg(feature = "std")]
+impl PartialEq for Reached {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Eq for Reached {}
+
+#[cfg(feature = "std")]
+impl PartialOrd for Reached {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Ord for Reached {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        other
+            .dist
+            .total_cmp(&self.dist)
+            .then(other.row.cmp(&self.row))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Csc<F> {
+    /// Computes a row permutation which maximizes the product of the magnitudes of the
+    /// diagonal, similar to MC64. `perm[i]` is the original row placed at position `i`, so
+    /// `a.permute(&perm, &identity)` has large entries on its diagonal, which reduces the
+    /// number of row swaps needed while pivoting. It can then be combined with a symmetric
+    /// fill reducing ordering in
+    /// [`new_with_row_perm`](crate::LeftLookingLUFactorization::new_with_row_perm).
+    ///
+    /// This is an assignment problem with the cost `log(max_i |a_ic|) - log |a_rc|`, which is
+    /// solved with successive shortest augmenting paths. Each search only visits the rows it
+    /// reaches, using a binary heap. Returns an error with the column which could not be
+    /// matched if the matrix is structurally singular.
+    pub fn max_weight_matching(&self) -> Result<Vec<usize>, LuError> {
+        let n = self.ncols();
+        assert_eq!(n, self.nrows());
+        let costs: Vec<Vec<(usize, F)>> = (0..n)
+            .map(|c| {
+                let col_max = self.col_iter(c).map(|(_, v)| v.abs()).fold(0., F::max).ln();
+                self.col_iter(c)
+                    .filter(|&(_, v)| *v != 0. && v.is_finite())
+                    .map(|(r, v)| (r, col_max - v.abs().ln()))
+                    .collect()
+            })
+            .collect();
+
+        // dual variables, which keep the reduced costs `cost - u[c] - v[r]` non-negative.
+        let mut u = vec![0.; n];
+        let mut v = vec![0.; n];
+        let mut row_of_col: Vec<Option<usize>> = vec![None; n];
+        let mut col_of_row: Vec<Option<usize>> = vec![None; n];
+
+        let mut dist = vec![F::INFINITY; n];
+        let mut prev_col = vec![0; n];
+        let mut done = vec![false; n];
+        let mut visited = vec![];
+        // rows whose distance was set, which are reset before the next search.
+        let mut touched = vec![];
+        let mut heap = BinaryHeap::new();
+        for s in 0..n {
+            for r in touched.drain(..) {
+                dist[r] = F::INFINITY;
+                done[r] = false;
+            }
+            visited.clear();
+            heap.clear();
+            let mut relax = |r: usize, d: F, c: usize, dist: &mut [F], heap: &mut BinaryHeap<_>| {
+                if d < dist[r] {
+                    if dist[r] == F::INFINITY {
+                        touched.push(r);
+                    }
+                    dist[r] = d;
+                    prev_col[r] = c;
+                    heap.push(Reached { dist: d, row: r });
+                }
+            };
+            for &(r, cost) in &costs[s] {
+                relax(r, cost - u[s] - v[r], s, &mut dist, &mut heap);
+            }
+            // Dijkstra over rows, where reaching a matched row continues from its column.
+            let (end, d_end) = loop {
+                let Some(Reached { dist: d, row: r }) = heap.pop() else {
+                    return Err(LuError::Singular { column: s });
+                };
+                // a stale entry, from before the distance of `r` decreased.
+                if done[r] || d > dist[r] {
+                    continue;
+                }
+                done[r] = true;
+                let Some(c) = col_of_row[r] else {
+                    break (r, d);
+                };
+                visited.push(r);
+                for &(r2, cost) in &costs[c] {
+                    if !done[r2] {
+                        relax(r2, d + cost - u[c] - v[r2], c, &mut dist, &mut heap);
+                    }
+                }
+            };
+
+            u[s] += d_end;
+            for &r in &visited {
+                let delta = d_end - dist[r];
+                v[r] -= delta;
+                u[col_of_row[r].unwrap()] += delta;
+            }
+
+            let mut r = end;
+            loop {
+                let c = prev_col[r];
+                let old = row_of_col[c];
+                row_of_col[c] = Some(r);
+                col_of_row[r] = Some(c);
+                if c == s {
+                    break;
+                }
+                r = old.unwrap();
+            }
+        }
+        Ok(row_of_col.into_iter().map(Option::unwrap).collect())
+    }
+}
@@ -0,0 +1,93 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use std::thread;
+
+use super::F;
+use crate::csc::Csc;
+
+/// Levels with fewer rows than this are solved on the calling thread, since spawning threads
+/// would cost more than the work.
+const MIN_ROWS_PER_THREAD: usize = 128;
+
+impl Csc<F> {
+    /// Solves a lower triangular system like
+    /// [`dense_lower_triangular_solve`](Self::dense_lower_triangular_solve), producing identical
+    /// results, but solves independent rows concurrently.
+    ///
+    /// Rows are grouped into levels from the
+    /// [`column_dependencies`](crate::SparsityPattern::column_dependencies) of the matrix, where
+    /// each level only depends on earlier levels. This is only faster when the levels are wide,
+    /// and also needs to transpose the matrix, so it is best suited for large matrices with
+    /// little fill.
+    pub fn par_dense_lower_triangular_solve(&self, b: &[F], out: &mut [F], unit_diagonal: bool) {
+        assert_eq!(self.nrows(), self.ncols());
+        assert_eq!(self.ncols(), b.len());
+        assert_eq!(out.len(), b.len());
+        let n = b.len();
+
+        // Row oriented copy of the strictly lower triangle, with entries in ascending column
+        // order so that the sums match the serial solve exactly.
+        let mut rows = vec![vec![]; n];
+        let mut diag = vec![None; n];
+        for ([c, r], &v) in self.pattern().entries().zip(self.values()) {
+            if r > c {
+                rows[r].push((c, v));
+            } else if r == c {
+                diag[c] = Some(v);
+            }
+        }
+
+        let deps = self.pattern().column_dependencies();
+        let mut level_of = vec![0; n];
+        let mut levels: Vec<Vec<usize>> = vec![];
+        for (i, deps) in deps.iter().enumerate() {
+            let level = deps.iter().map(|&k| level_of[k] + 1).max().unwrap_or(0);
+            level_of[i] = level;
+            if levels.len() <= level {
+                levels.resize(level + 1, vec![]);
+            }
+            levels[level].push(i);
+        }
+
+        let solve_row = |i: usize, out: &[F]| -> F {
+            let mut o = b[i];
+            for &(c, v) in &rows[i] {
+                o -= v * out[c];
+            }
+            match diag[i] {
+                Some(d) if !unit_diagonal => {
+                    o /= d;
+                }
+                _ => {}
+            }
+            o
+        };
+
+        let threads = thread::available_parallelism().map_or(1, |t| t.get());
+        for level in &levels {
+            let chunk = level.len().div_ceil(threads).max(MIN_ROWS_PER_THREAD);
+            if chunk >= level.len() {
+                for &i in level {
+                    out[i] = solve_row(i, out);
+                }
+                continue;
+            }
+            let solved = thread::scope(|s| {
+                let shared = &*out;
+                let handles = level
+                    .chunks(chunk)
+                    .map(|rows| {
+                        s.spawn(move || rows.iter().map(|&i| solve_row(i, shared)).collect())
+                    })
+                    .collect::<Vec<_>>();
+                handles
+                    .into_iter()
+                    .map(|h| h.join().unwrap())
+                    .collect::<Vec<Vec<F>>>()
+            });
+            for (&i, v) in level.iter().zip(solved.into_iter().flatten()) {
+                out[i] = v;
+            }
+        }
+    }
+}
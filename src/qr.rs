@@ -0,0 +1,284 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::F;
+use crate::csc::{Csc, CscBuilder};
+
+/// A sparse column, as `(row, value)` pairs sorted by row.
+type SparseCol = Vec<(usize, F)>;
+
+/// Computes `ca * a + cb * b` for sparse `a` and `b`.
+fn combine(ca: F, a: &[(usize, F)], cb: F, b: &[(usize, F)]) -> SparseCol {
+    let mut out = Vec::with_capacity(a.len() + b.len());
+    let (mut ai, mut bi) = (a.iter().peekable(), b.iter().peekable());
+    loop {
+        let next = match (ai.peek(), bi.peek()) {
+            (None, None) => break,
+            (Some(&&(ar, av)), Some(&&(br, bv))) if ar == br => {
+                ai.next();
+                bi.next();
+                (ar, ca * av + cb * bv)
+            }
+            (Some(&&(ar, av)), Some(&&(br, _))) if ar < br => {
+                ai.next();
+                (ar, ca * av)
+            }
+            (Some(&&(ar, av)), None) => {
+                ai.next();
+                (ar, ca * av)
+            }
+            (_, Some(&&(br, bv))) => {
+                bi.next();
+                (br, cb * bv)
+            }
+        };
+        out.push(next);
+    }
+    out
+}
+
+/// Computes `vᵀx` for sparse `v` and `x`.
+fn sparse_dot(v: &[(usize, F)], x: &[(usize, F)]) -> F {
+    let mut xi = x.iter().peekable();
+    let mut sum = 0.;
+    for &(r, vv) in v {
+        while xi.next_if(|&&(xr, _)| xr < r).is_some() {}
+        if let Some(&&(xr, xv)) = xi.peek()
+            && xr == r
+        {
+            sum += vv * xv;
+        }
+    }
+    sum
+}
+
+/// A QR factorization `AP = QR` computed with Householder reflections, where `P` is a column
+/// permutation. Columns are kept sparse throughout.
+#[derive(Debug, Clone)]
+pub struct SparseQr {
+    /// The Householder vectors, one per column of `Q` that was formed.
+    householder: Vec<SparseCol>,
+    betas: Vec<F>,
+    /// Upper trapezoidal `rank × ncols` factor, in permuted column order.
+    r: Csc<F>,
+    col_perm: Vec<usize>,
+    rank: usize,
+    nrows: usize,
+    /// The leading `rank` entries of `Qᵀb` for the right hand side given to `set_rhs`.
+    qtb: Option<Vec<F>>,
+    /// Number of rows added by `update_row`, after which the Householder vectors no longer
+    /// represent `Q`.
+    rows_added: usize,
+}
+
+impl SparseQr {
+    /// Factorizes `a` with column pivoting, where at each step the remaining column with the
+    /// largest norm below the already factorized rows is selected. This concentrates the rank
+    /// of `a` in the leading columns, and factorization stops once the largest remaining norm
+    /// is at most `tol`, which determines the numerical rank.
+    pub fn new_pivoted(a: &Csc<F>, tol: F) -> Self {
+        let (m, n) = (a.nrows(), a.ncols());
+        let mut cols: Vec<SparseCol> = (0..n)
+            .map(|c| a.col_iter(c).map(|(r, &v)| (r, v)).collect())
+            .collect();
+        let mut col_perm: Vec<usize> = (0..n).collect();
+        let mut householder = vec![];
+        let mut betas = vec![];
+
+        let remaining_norm = |col: &SparseCol, k: usize| {
+            col.iter()
+                .filter(|&&(r, _)| r >= k)
+                .map(|&(_, v)| v * v)
+                .sum::<F>()
+                .sqrt()
+        };
+
+        let mut rank = 0;
+        while rank < m.min(n) {
+            let k = rank;
+            let (best, norm) = (k..n)
+                .map(|j| (j, remaining_norm(&cols[j], k)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            if norm <= tol || norm.is_nan() {
+                break;
+            }
+            cols.swap(k, best);
+            col_perm.swap(k, best);
+
+            // reflect the column onto `alpha e_k`, choosing the sign that avoids cancellation.
+            let xk = cols[k]
+                .iter()
+                .find(|&&(r, _)| r == k)
+                .map_or(0., |&(_, v)| v);
+            let alpha = if xk > 0. { -norm } else { norm };
+            let mut v: SparseCol = cols[k].iter().copied().filter(|&(r, _)| r >= k).collect();
+            match v.first_mut() {
+                Some((r, vk)) if *r == k => *vk -= alpha,
+                _ => v.insert(0, (k, -alpha)),
+            }
+            let beta = 2. / v.iter().map(|&(_, v)| v * v).sum::<F>();
+
+            cols[k].retain(|&(r, _)| r < k);
+            cols[k].push((k, alpha));
+            for col in &mut cols[k + 1..] {
+                let s = beta * sparse_dot(&v, col);
+                if s != 0. {
+                    *col = combine(1., col, -s, &v);
+                }
+            }
+            householder.push(v);
+            betas.push(beta);
+            rank += 1;
+        }
+
+        let mut builder = CscBuilder::new(rank, n);
+        for (c, col) in cols.iter().enumerate() {
+            for &(r, v) in col.iter().filter(|&&(r, v)| r < rank && v != 0.) {
+                let ins = builder.insert(r, c, v);
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+
+        Self {
+            householder,
+            betas,
+            r: builder.build(),
+            col_perm,
+            rank,
+            nrows: m,
+            qtb: None,
+            rows_added: 0,
+        }
+    }
+
+    /// The numerical rank, which is the number of columns that were factorized.
+    pub fn rank(&self) -> usize {
+        self.rank
+    }
+
+    /// The column permutation, where `col_perm()[j]` is the original column at position `j`.
+    pub fn col_perm(&self) -> &[usize] {
+        &self.col_perm
+    }
+
+    /// The upper trapezoidal factor `R`, of size `rank × ncols`, in permuted column order.
+    pub fn r(&self) -> &Csc<F> {
+        &self.r
+    }
+
+    /// Computes a basic solution of `min ||Ax - b||`, which minimizes the residual and has
+    /// at most `rank` nonzeros, where columns beyond the rank are set to zero.
+    /// Panics if rows were added with [`update_row`](Self::update_row), since `Q` is not
+    /// kept up to date. Use [`solution`](Self::solution) instead.
+    pub fn solve(&self, b: &[F]) -> Vec<F> {
+        self.solve_r(&self.apply_qt(b))
+    }
+
+    /// Computes the leading `rank` entries of `Qᵀb`.
+    fn apply_qt(&self, b: &[F]) -> Vec<F> {
+        assert_eq!(self.rows_added, 0, "Q is not kept after adding rows");
+        assert_eq!(b.len(), self.nrows);
+        let mut qtb = b.to_vec();
+        for (v, &beta) in self.householder.iter().zip(&self.betas) {
+            let s = beta * v.iter().map(|&(r, v)| v * qtb[r]).sum::<F>();
+            for &(r, v) in v {
+                qtb[r] -= s * v;
+            }
+        }
+        qtb.truncate(self.rank);
+        qtb
+    }
+
+    /// Solves `R₁₁z = qtb`, and un-permutes `z` into a basic solution.
+    fn solve_r(&self, qtb: &[F]) -> Vec<F> {
+        let r11 = self.r.submatrix(0..self.rank, 0..self.rank);
+        let mut z = vec![0.; self.rank];
+        r11.dense_upper_triangular_solve(qtb, &mut z);
+
+        let mut x = vec![0.; self.col_perm.len()];
+        for (&c, z) in self.col_perm.iter().zip(z) {
+            x[c] = z;
+        }
+        x
+    }
+
+    /// Sets the right hand side which is updated along with the factorization by
+    /// [`update_row`](Self::update_row). Must be called before adding any rows.
+    pub fn set_rhs(&mut self, b: &[F]) {
+        self.qtb = Some(self.apply_qt(b));
+    }
+
+    /// The basic least squares solution for the right hand side given to
+    /// [`set_rhs`](Self::set_rhs), including any rows added since.
+    pub fn solution(&self) -> Vec<F> {
+        self.solve_r(self.qtb.as_ref().expect("no right hand side was set"))
+    }
+
+    /// Adds the row `w` with right hand side `b_new` to the factorized system, where `w` has
+    /// the values `row_val` at the columns `row_idx`. This is a recursive least squares update,
+    /// which applies a sweep of Givens rotations to `R` and the right hand side instead of
+    /// refactorizing.
+    ///
+    /// The rank and column permutation are kept fixed, so the update is exact when the matrix
+    /// has full column rank. Otherwise, the parts of the new row in columns beyond the rank
+    /// are ignored.
+    pub fn update_row(&mut self, row_idx: &[usize], row_val: &[F], b_new: F) {
+        assert_eq!(row_idx.len(), row_val.len());
+        let qtb = self
+            .qtb
+            .as_mut()
+            .expect("set_rhs must be called before adding rows");
+        let n = self.col_perm.len();
+        let mut inv_perm = vec![0; n];
+        for (j, &c) in self.col_perm.iter().enumerate() {
+            inv_perm[c] = j;
+        }
+        let mut w: SparseCol = row_idx
+            .iter()
+            .zip(row_val)
+            .map(|(&c, &v)| (inv_perm[c], v))
+            .collect();
+        w.sort_unstable_by_key(|&(c, _)| c);
+
+        let csr = self.r.to_csr();
+        let mut rows: Vec<SparseCol> = (0..self.rank)
+            .map(|k| csr.row_iter(k).map(|(c, &v)| (c, v)).collect())
+            .collect();
+        let mut b_new = b_new;
+        for (k, row) in rows.iter_mut().enumerate() {
+            let Some(wk) = w.iter().find(|&&(c, _)| c == k).map(|&(_, v)| v) else {
+                continue;
+            };
+            let rkk = row.iter().find(|&&(c, _)| c == k).map_or(0., |&(_, v)| v);
+            let r = rkk.hypot(wk);
+            if r == 0. {
+                continue;
+            }
+            let (c, s) = (rkk / r, wk / r);
+            let new_row = combine(c, row, s, &w);
+            w = combine(-s, row, c, &w);
+            // the rotation zeroes the entry in column k exactly.
+            w.retain(|&(col, _)| col != k);
+            *row = new_row;
+            let qk = qtb[k];
+            qtb[k] = c * qk + s * b_new;
+            b_new = -s * qk + c * b_new;
+        }
+
+        let mut builder = CscBuilder::new(self.rank, n);
+        let mut entries = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(r, row)| row.iter().map(move |&(c, v)| (c, r, v)))
+            .filter(|&(_, _, v)| v != 0.)
+            .collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|&(c, r, _)| (c, r));
+        for (c, r, v) in entries {
+            let ins = builder.insert(r, c, v);
+            debug_assert_eq!(ins, Ok(()));
+        }
+        self.r = builder.build();
+        self.rows_added += 1;
+    }
+}
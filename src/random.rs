@@ -0,0 +1,82 @@
+use alloc::collections::BTreeSet;
+
+use super::F;
+use crate::csc::{Csc, CscBuilder};
+
+/// A small deterministic pseudo random number generator (SplitMix64), so that generated
+/// matrices are reproducible across platforms without any dependencies.
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+    /// Uniform in `[0, 1)`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+    /// Uniform in `0..=max`.
+    fn below_or_eq(&mut self, max: usize) -> usize {
+        (self.next_u64() % (max as u64 + 1)) as usize
+    }
+}
+
+impl Csc<F> {
+    /// Generates a reproducible sparse matrix, where each column has `density * rows` entries
+    /// on average at uniformly random rows, with values uniform in `[-1, 1]`. The same seed
+    /// always produces the same matrix.
+    pub fn random(rows: usize, cols: usize, density: f64, seed: u64) -> Self {
+        assert!((0. ..=1.).contains(&density));
+        let mut rng = SplitMix64(seed);
+        let mut builder = CscBuilder::new(rows, cols);
+        let mut col = BTreeSet::new();
+        for c in 0..cols {
+            // round the expected count up or down at random, so the density is unbiased.
+            let expected = density * rows as f64;
+            let mut k = expected as usize;
+            if rng.next_f64() < expected - k as f64 {
+                k += 1;
+            }
+            // Floyd's algorithm for a sample of `k` distinct rows.
+            col.clear();
+            for j in rows - k..rows {
+                let t = rng.below_or_eq(j);
+                if !col.insert(t) {
+                    col.insert(j);
+                }
+            }
+            for &r in &col {
+                let v = (2. * rng.next_f64() - 1.) as F;
+                let ins = builder.insert(r, c, v);
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        builder.build()
+    }
+
+    /// Generates a reproducible symmetric positive definite matrix, which is the symmetric
+    /// part of [`random`](Self::random) with a strictly diagonally dominant positive diagonal.
+    pub fn random_spd(n: usize, density: f64, seed: u64) -> Self {
+        let sym = Self::random(n, n, density, seed).symmetrize();
+        // shift the diagonal so it is 1 more than the sum of the off diagonal magnitudes.
+        let mut shift = CscBuilder::new(n, n);
+        for c in 0..n {
+            let (mut diag, mut off_diag) = (0., 0.);
+            for (r, &v) in sym.col_iter(c) {
+                if r == c {
+                    diag = v;
+                } else {
+                    off_diag += v.abs();
+                }
+            }
+            let ins = shift.insert(c, c, 1. + off_diag - diag);
+            debug_assert_eq!(ins, Ok(()));
+        }
+        sym.add(&shift.build())
+    }
+}
@@ -1,16 +1,180 @@
-use super::F;
+use super::{F, to_usize};
 use crate::csc::{Csc, CscBuilder};
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// How the pivot row is selected for each column during factorization.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum PivotStrategy {
+    /// Selects the candidate with the largest magnitude in the column.
+    #[default]
+    Partial,
+    /// Keeps the diagonal if its magnitude is at least the given fraction of the largest
+    /// candidate's magnitude, otherwise falls back to partial pivoting.
+    /// This trades some stability for fewer row swaps.
+    Threshold(F),
+    /// Like `Threshold`, but compares each candidate relative to the largest magnitude in its
+    /// row of the original matrix `A`, rather than by its absolute magnitude.
+    /// A candidate which is large within its column but small compared to the rest of its row
+    /// would produce large entries in U, so this avoids it at the cost of a row scan before
+    /// factorizing. This is a cheap approximation of rook pivoting.
+    ///
+    /// The row maximums are computed once from `A` and follow the rows as they are swapped,
+    /// but are not updated by elimination. The later columns of a row have not been formed
+    /// yet when a left-looking factorization picks a pivot, so the original magnitudes are
+    /// used as an estimate, which may be stale once elimination has changed the row.
+    OriginalRowThreshold(F),
+    /// Does not search for a pivot. The diagonal is kept unless it is zero or not finite, in
+    /// which case the first finite nonzero candidate below it is used.
+    None,
+    /// Always uses the diagonal, panicking if it is zero.
+    Diagonal,
+}
+
+impl PivotStrategy {
+    /// Selects the position of the pivot in `vals`, where `rows` are the sorted row indices
+    /// of `vals`, and `ci` is the column being factorized.
+    /// `row_max` is the largest magnitude of each row, which is only used by
+    /// `OriginalRowThreshold`.
+    fn select(self, ci: usize, rows: &[usize], vals: &[F], row_max: &[F]) -> Option<usize> {
+        let start = rows.partition_point(|&r| r < ci);
+        let diag = rows.get(start).filter(|&&r| r == ci).map(|_| start);
+        let largest = || safe_abs_max(&vals[start..]).map(|i| i + start);
+        let relative = |i: usize| vals[i].abs() / row_max[rows[i]];
+        match self {
+            PivotStrategy::Partial => largest(),
+            PivotStrategy::Threshold(t) => {
+                let largest = largest()?;
+                match diag {
+                    Some(d) if vals[d].abs() >= t * vals[largest].abs() => Some(d),
+                    _ => Some(largest),
+                }
+            }
+            PivotStrategy::OriginalRowThreshold(t) => {
+                let largest = (start..vals.len())
+                    .filter(|&i| vals[i] != 0. && !relative(i).is_nan())
+                    .max_by(|&a, &b| relative(a).total_cmp(&relative(b)))?;
+                match diag {
+                    Some(d) if relative(d) >= t * relative(largest) => Some(d),
+                    _ => Some(largest),
+                }
+            }
+            PivotStrategy::None => {
+                let usable = |i: usize| vals[i] != 0. && vals[i].is_finite();
+                diag.filter(|&d| usable(d))
+                    .or_else(|| (start..vals.len()).find(|&i| usable(i)))
+            }
+            PivotStrategy::Diagonal => diag,
+        }
+    }
+}
+
+/// Returns the index of the value with the largest finite magnitude, skipping NaNs and
+/// infinities. Returns `None` if there is no finite nonzero value.
+fn safe_abs_max(vals: &[F]) -> Option<usize> {
+    vals.iter()
+        .enumerate()
+        .filter(|&(_, v)| v.is_finite() && *v != 0.)
+        .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+        .map(|(i, _)| i)
+}
+
+/// The inverse of the permutation `perm`, which maps each value of `perm` to its position.
+fn invert_permutation(perm: &[usize]) -> Vec<usize> {
+    let mut inv = vec![0; perm.len()];
+    for (i, &p) in perm.iter().enumerate() {
+        inv[p] = i;
+    }
+    inv
+}
+
+/// An error when constructing an LU factorization.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LuError {
+    /// No nonzero pivot could be found for `column`, so the matrix is singular.
+    Singular { column: usize },
+    /// Factorizing `column` produced a NaN or infinity, either from a non-finite entry of the
+    /// matrix or from overflow.
+    NonFinite { column: usize },
+    /// The diagonal entry of `row` is missing, or became zero or non-finite during an
+    /// elimination without pivoting, such as [`IluFactorization`](crate::IluFactorization).
+    ZeroDiagonal { row: usize },
+}
+
+impl core::fmt::Display for LuError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LuError::Singular { column } => {
+                write!(f, "matrix is singular, no nonzero pivot in column {column}")
+            }
+            LuError::NonFinite { column } => {
+                write!(f, "non-finite value while factorizing column {column}")
+            }
+            LuError::ZeroDiagonal { row } => {
+                write!(f, "diagonal entry of row {row} is missing or zero")
+            }
+        }
+    }
+}
+
+impl core::error::Error for LuError {}
+
+/// The work done to factorize a single column.
+#[cfg(feature = "profiling")]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ColumnProfile {
+    /// Number of rows reachable from the column, which is the number of non-zeros in the
+    /// column of L\U.
+    pub reach: usize,
+    /// Number of multiply-adds and divisions performed.
+    pub flops: usize,
+}
+
+/// Buffers used while factorizing, which can be shared between factorizations to avoid
+/// reallocating them for each matrix.
+#[derive(Debug, Clone, Default)]
+pub struct LuScratch {
+    val_buf: Vec<F>,
+    pat_contains: Vec<bool>,
+    pat_buf: Vec<usize>,
+    stack: Vec<u32>,
+    ris_buf: Vec<usize>,
+}
+
+impl LuScratch {
+    /// Creates empty buffers, which grow to fit the largest matrix factorized with them.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
 
 /// Constructs an LU Factorization using a left-looking approach.
 /// This means it will construct each column, starting from the leftmost one.
+#[derive(Debug, Clone)]
 pub struct LeftLookingLUFactorization<T> {
     /// A single matrix stores both the lower and upper triangular components
     l_u: Csc<T>,
 
     pivot: Vec<usize>,
+    /// Position of each row in the pivoted order, such that `inv_pivot[pivot[i]] = i`.
+    inv_pivot: Vec<usize>,
+
+    /// Column permutation `Q` applied before factorizing, such that `PAQ = LU`, where
+    /// `col_perm[j]` is the original column at position `j`. `None` is the natural order.
+    col_perm: Option<Vec<usize>>,
+
+    /// Pivots with a magnitude at most this times the largest magnitude in their column are
+    /// treated as zero.
+    pivot_tol: T,
 }
 
 impl LeftLookingLUFactorization<F> {
+    /// The default relative threshold for zero pivots. Pivots this small relative to the rest
+    /// of their column are dominated by rounding error, so dividing by them would make the
+    /// factors unstable.
+    pub const DEFAULT_PIVOT_TOLERANCE: F = F::EPSILON;
+
     /// Returns the joint L\U matrix. Here, `L` implicitly has 1 along the diagonal.
     /// Do not forget that there is a pivot if reading from this matrix
     #[inline]
@@ -24,36 +188,197 @@ impl LeftLookingLUFactorization<F> {
         &self.pivot
     }
 
-    /*
-    /// Returns the upper triangular part of this matrix.
-    pub fn u(&self) -> Csc<T> {
+    /// Column permutation of this factorization, if it was computed with a reordering.
+    /// `col_perm()[j]` is the column of the original matrix at position `j`, such that
+    /// `PAQ = LU`.
+    #[inline]
+    pub fn col_perm(&self) -> Option<&[usize]> {
+        self.col_perm.as_deref()
+    }
+
+    /// The relative threshold below which a pivot was considered zero, so that the matrix was
+    /// treated as singular. A pivot is zero if its magnitude is at most this times the largest
+    /// magnitude in its column of `L\U` before dividing by the pivot. Being relative to each
+    /// column, this does not depend on how the columns are scaled, and the triangular solves
+    /// have no absolute threshold either, so a uniformly scaled matrix factorizes and solves
+    /// the same way as the original. Defaults to
+    /// [`DEFAULT_PIVOT_TOLERANCE`](Self::DEFAULT_PIVOT_TOLERANCE).
+    #[inline]
+    pub fn pivot_tolerance(&self) -> F {
+        self.pivot_tol
+    }
+
+    /// Ratio of the number of non-zeros in the joint L\U matrix to those in `a`, the matrix
+    /// which was factorized. Lower values indicate less fill-in. An `a` without any stored
+    /// entries, such as an empty matrix, has a ratio of 1.
+    pub fn fill_ratio(&self, a: &Csc<F>) -> f64 {
+        if a.nnz() == 0 {
+            return 1.;
+        }
+        self.l_u.nnz() as f64 / a.nnz() as f64
+    }
+
+    /// The number of multiply-adds performed by a single [`solve`](Self::solve), which is one
+    /// per off-diagonal entry of `L` and `U`. The `n` divisions by the diagonal of `U` and
+    /// the permutations are not counted.
+    pub fn solve_flops(&self) -> usize {
+        self.l_u.nnz() - self.pivot.len()
+    }
+
+    /// Positions `(row, col)` of the joint L\U matrix which are not present in `PAQ`, where `a`
+    /// is the matrix which was factorized. These are the entries introduced by fill-in, with
+    /// rows in the pivoted order, sorted by column and then row.
+    pub fn fill_entries(&self, a: &Csc<F>) -> Vec<(usize, usize)> {
+        let n = self.l_u.nrows();
+        let inv_pivot = &self.inv_pivot;
+        let mut in_a = vec![false; n];
+        let mut fill = vec![];
+        for c in 0..self.l_u.ncols() {
+            let a_c = self.col_perm.as_ref().map_or(c, |q| q[c]);
+            for &r in a.col(a_c).1 {
+                in_a[inv_pivot[to_usize(r)]] = true;
+            }
+            fill.extend(
+                self.l_u
+                    .col(c)
+                    .1
+                    .iter()
+                    .map(|&r| to_usize(r))
+                    .filter(|&r| !in_a[r])
+                    .map(|r| (r, c)),
+            );
+            for &r in a.col(a_c).1 {
+                in_a[inv_pivot[to_usize(r)]] = false;
+            }
+        }
+        fill
+    }
+
+    /// Returns the upper triangular part of this factorization.
+    pub fn u(&self) -> Csc<F> {
         self.l_u.upper_triangle()
     }
 
-    /// Returns the lower triangular part of this matrix.
-    pub fn l(&self) -> Csc<T> {
-        let mut l = self.l_u.lower_triangle();
+    /// Returns the lower triangular part of this factorization, with an explicit unit
+    /// diagonal.
+    pub fn l(&self) -> Csc<F> {
         let n = self.l_u.nrows();
-        for i in 0..n {
-            if let SparseEntryMut::NonZero(v) = l.index_entry_mut(i, i) {
-                *v = T::one();
-            } else {
-                unreachable!();
+        let mut l = CscBuilder::new(n, n);
+        for c in 0..n {
+            let ins = l.insert(c, c, 1.);
+            debug_assert_eq!(ins, Ok(()));
+            for (r, &v) in self.l_u.col_iter(c).filter(|&(r, _)| r > c) {
+                let ins = l.insert(r, c, v);
+                debug_assert_eq!(ins, Ok(()));
             }
         }
-        l
+        l.build()
+    }
+
+    /// The row permutation `P` as a matrix, such that `PAQ = LU`, where row `i` of `P` has
+    /// its entry in column `pivot()[i]`.
+    pub fn row_permutation_matrix(&self) -> Csc<F> {
+        let n = self.pivot.len();
+        let mut t: Vec<_> = self
+            .pivot
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| ([p, i], 1.))
+            .collect();
+        Csc::from_triplets(n, n, &mut t).expect("pivot is a permutation")
+    }
+
+    /// The column permutation `Q` as a matrix, such that `PAQ = LU`, where column `j` of `Q`
+    /// has its entry in row `col_perm()[j]`. This is the identity without a reordering.
+    pub fn column_permutation_matrix(&self) -> Csc<F> {
+        let n = self.pivot.len();
+        let Some(q) = &self.col_perm else {
+            return Csc::identity(n);
+        };
+        let mut t: Vec<_> = q.iter().enumerate().map(|(j, &c)| ([j, c], 1.)).collect();
+        Csc::from_triplets(n, n, &mut t).expect("column ordering is a permutation")
+    }
+
+    /// Splits this factorization into the row permutation `P`, and the factors `L` and `U`
+    /// such that `PA = LU`. `P` is represented the same way as `pivot`, where row `i` of `PA`
+    /// is row `P[i]` of `A`. If this factorization was reordered, the factors are of `PAQ`
+    /// instead, with `Q` given by [`col_perm`](Self::col_perm).
+    pub fn into_plu(self) -> (Vec<usize>, Csc<F>, Csc<F>) {
+        let (l, u) = (self.l(), self.u());
+        (self.pivot, l, u)
     }
-    */
 
     /// Computes `x` in `LUx = b`, where `b` is a dense vector.
     /// The output will be stored in b, and buf is used as a temporary buffer.
     pub fn solve(&self, b: &mut [F], buf: &mut [F]) {
         self.solve_arr(
-            unsafe { std::mem::transmute::<_, &mut [[F; 1]]>(b) },
-            unsafe { std::mem::transmute::<_, &mut [[F; 1]]>(buf) },
+            unsafe { core::mem::transmute::<&mut [F], &mut [[F; 1]]>(b) },
+            unsafe { core::mem::transmute::<&mut [F], &mut [[F; 1]]>(buf) },
         );
     }
 
+    /// Computes `x` in `LUx = b`, where `b` is a dense vector, returning `x` in a new vector
+    /// and leaving `b` unchanged.
+    pub fn solve_owned(&self, b: &[F]) -> Vec<F> {
+        let mut x = b.to_vec();
+        self.solve(&mut x, &mut vec![0.; b.len()]);
+        x
+    }
+
+    /// Computes `x` in `Ax = b`, where `b` is a sparse vector with the values `b_val` at the
+    /// unique indices `b_idx`. Returns the indices of `x` which can be nonzero in ascending
+    /// order, and their values.
+    ///
+    /// Only the columns of the factors reachable from the nonzeros of `b` are eliminated, but
+    /// workspace proportional to the size of the matrix is still allocated and scanned for
+    /// each call.
+    pub fn solve_sparse(&self, b_idx: &[usize], b_val: &[F]) -> (Vec<usize>, Vec<F>) {
+        assert_eq!(b_idx.len(), b_val.len());
+        let n = self.pivot.len();
+        // Pb, which has b[pivot[i]] at position i.
+        let mut work = vec![0.; n];
+        let pb_idx: Vec<usize> = b_idx.iter().map(|&i| self.inv_pivot[i]).collect();
+        for (&i, &v) in pb_idx.iter().zip(b_val) {
+            work[i] = v;
+        }
+
+        // Lz = Pb, visiting the reached columns in ascending order.
+        let z_idx = self.l_u.solve_pattern(&pb_idx, true);
+        for &j in &z_idx {
+            let zj = work[j];
+            for (r, &l) in self.l_u.col_iter(j).filter(|&(r, _)| r > j) {
+                work[r] -= l * zj;
+            }
+        }
+        // Uy = z, visiting the reached columns in descending order.
+        let y_idx = self.l_u.solve_pattern(&z_idx, false);
+        for &j in y_idx.iter().rev() {
+            let ujj = *self.l_u.get(j, j).expect("U has a nonzero diagonal");
+            work[j] /= ujj;
+            let yj = work[j];
+            for (r, &u) in self.l_u.col_iter(j).take_while(|&(r, _)| r < j) {
+                work[r] -= u * yj;
+            }
+        }
+
+        // x = Qy
+        let Some(q) = &self.col_perm else {
+            let y = y_idx.iter().map(|&j| work[j]).collect();
+            return (y_idx, y);
+        };
+        let mut x: Vec<(usize, F)> = y_idx.iter().map(|&j| (q[j], work[j])).collect();
+        x.sort_unstable_by_key(|&(i, _)| i);
+        x.into_iter().unzip()
+    }
+
+    /// Computes `x ≈ A⁻¹b` from this factorization of `A`, which may be of a nearby or
+    /// outdated matrix. This is intended as an initial guess for an iterative solver on the
+    /// current matrix rather than as a final answer, and otherwise is the same as
+    /// [`solve_owned`](Self::solve_owned).
+    pub fn approximate_solve(&self, b: &[F]) -> Vec<F> {
+        self.solve_owned(b)
+    }
+
     /// Computes `x` in `LUx = b`, where `b` is a dense vector.
     /// The output will be stored in b, and buf is used as a temporary buffer.
     pub fn solve_arr<const N: usize>(&self, b: &mut [[F; N]], buf: &mut [[F; N]]) {
@@ -64,41 +389,445 @@ impl LeftLookingLUFactorization<F> {
         for i in 0..n {
             b[i] = buf[self.pivot[i]];
         }
+        self.solve_pivoted_arr(b, buf);
+    }
+
+    /// Computes `x` in `Ax = c`, where `c[i] = b[extra_perm[i]]`, which is the same as
+    /// permuting `b` by `extra_perm` and then calling [`solve`](Self::solve). The permutation
+    /// is composed with the pivot, so `b` is only reordered once.
+    /// The output will be stored in b, and buf is used as a temporary buffer.
+    pub fn solve_with_perm(&self, b: &mut [F], extra_perm: &[usize], buf: &mut [F]) {
+        assert_eq!(b.len(), buf.len());
+        assert_eq!(extra_perm.len(), b.len());
+        buf.copy_from_slice(b);
+        for (b, &p) in b.iter_mut().zip(&self.pivot) {
+            *b = buf[extra_perm[p]];
+        }
+        self.solve_pivoted_arr(
+            unsafe { core::mem::transmute::<&mut [F], &mut [[F; 1]]>(b) },
+            unsafe { core::mem::transmute::<&mut [F], &mut [[F; 1]]>(buf) },
+        );
+    }
+
+    /// Solves `LUy = b` for `b` which has already been pivoted, and applies the column
+    /// permutation to `y`.
+    fn solve_pivoted_arr<const N: usize>(&self, b: &mut [[F; N]], buf: &mut [[F; N]]) {
         // Implementation: Solve two systems: Ly = b, then Ux = y.
         self.l_u.dense_lower_triangular_solve_arr(b, buf, true);
         self.l_u.dense_upper_triangular_solve_arr(buf, b);
+        // x = Qy
+        if let Some(q) = &self.col_perm {
+            buf.copy_from_slice(b);
+            for (&c, &y) in q.iter().zip(buf.iter()) {
+                b[c] = y;
+            }
+        }
+    }
+
+    /// Computes `x` in `LUx = b` for `n_rhs` right hand sides stored row major, where `b` is
+    /// an `n_rhs × nrows` dense matrix with each row being a right hand side.
+    /// The output will be stored in b, and buf is a temporary buffer of length `nrows`.
+    pub fn solve_rows(&self, b: &mut [F], n_rhs: usize, buf: &mut [F]) {
+        let n = self.pivot.len();
+        assert_eq!(b.len(), n_rhs * n);
+        if n == 0 {
+            return;
+        }
+        for row in b.chunks_exact_mut(n) {
+            self.solve(row, buf);
+        }
+    }
+
+    /// Computes `x` in `Aᵀx = b`, where `A` is the factorized matrix and `b` is a dense vector.
+    /// The output will be stored in b, and buf is used as a temporary buffer.
+    pub fn solve_transpose(&self, b: &mut [F], buf: &mut [F]) {
+        assert_eq!(b.len(), buf.len());
+        // PAQ = LU, so QᵀAᵀ = UᵀLᵀP. Solve Uᵀy = Qᵀb, then Lᵀz = y, then x = Pᵀz.
+        if let Some(q) = &self.col_perm {
+            buf.copy_from_slice(b);
+            for (b, &c) in b.iter_mut().zip(q) {
+                *b = buf[c];
+            }
+        }
+        self.l_u.dense_upper_triangular_transpose_solve(b, buf);
+        self.l_u
+            .dense_lower_triangular_transpose_solve(buf, b, true);
+        buf.copy_from_slice(b);
+        for (i, &p) in self.pivot.iter().enumerate() {
+            b[p] = buf[i];
+        }
+    }
+
+    /// Computes `x` in `xᵀA = bᵀ`, where `A` is the factorized matrix, solving with the system
+    /// matrix on the right. This is equivalent to [`solve_transpose`](Self::solve_transpose).
+    /// The output will be stored in b, and buf is used as a temporary buffer.
+    pub fn solve_left(&self, b: &mut [F], buf: &mut [F]) {
+        self.solve_transpose(b, buf);
+    }
+
+    /// Solves `LUx = b` for each right hand side in `rhs_iter`, passing each solution to
+    /// `sink` as soon as it is computed. Each solution reuses the allocation of its right
+    /// hand side, so only one right hand side is held at a time.
+    pub fn solve_streaming(
+        &self,
+        rhs_iter: impl Iterator<Item = Vec<F>>,
+        mut sink: impl FnMut(Vec<F>),
+    ) {
+        let mut buf = vec![0.; self.pivot.len()];
+        for mut b in rhs_iter {
+            self.solve(&mut b, &mut buf);
+            sink(b);
+        }
+    }
+
+    /// Computes `Σ A⁻¹b` over the right hand sides in `rhs_iter`, adding the sum to `out`.
+    /// Each solution is accumulated as soon as it is computed in a single scratch buffer, so
+    /// the individual solutions are never stored.
+    pub fn solve_accumulate<'a>(&self, rhs_iter: impl Iterator<Item = &'a [F]>, out: &mut [F]) {
+        let n = self.pivot.len();
+        assert_eq!(out.len(), n);
+        let mut x = vec![0.; n];
+        let mut buf = vec![0.; n];
+        for b in rhs_iter {
+            x.copy_from_slice(b);
+            self.solve(&mut x, &mut buf);
+            for (o, x) in out.iter_mut().zip(&x) {
+                *o += x;
+            }
+        }
+    }
+
+    /// Computes column `j` of `A⁻¹` by solving `Ax = e_j`, without forming the rest of the
+    /// inverse.
+    pub fn inverse_column(&self, j: usize) -> Vec<F> {
+        let n = self.pivot.len();
+        assert!(j < n, "column {j} out of bounds for {n} columns");
+        let mut e = vec![0.; n];
+        e[j] = 1.;
+        self.solve(&mut e, &mut vec![0.; n]);
+        e
+    }
+
+    /// Computes the diagonal of `A⁻¹` without forming the inverse, using the Takahashi
+    /// recurrence over the factors.
+    ///
+    /// With `LU = LDŨ` and `Z = (LU)⁻¹`, `Z = D⁻¹L⁻¹ + (I - Ũ)Z = Ũ⁻¹D⁻¹ + Z(I - L)`, which
+    /// gives each entry of `Z` in the pattern of `(L + U)ᵀ` from entries further down and to
+    /// the right. Because of fill in, that pattern is closed under the recurrence, so only
+    /// those entries are computed. A diagonal entry of `A⁻¹` is an entry of `Z` in that
+    /// pattern if the matching diagonal entry of `A` is stored, otherwise it is found with a
+    /// solve.
+    pub fn inverse_diagonal(&self) -> Vec<F> {
+        let n = self.pivot.len();
+        let u_rows = self.l_u.upper_triangle().to_csr();
+        // `lower[m]` holds `Z[k, m]` for `k > m` with `U[m, k]` stored, and `upper[m]` holds
+        // `Z[m, p]` for `p > m` with `L[p, m]` stored, both sorted by `k` and `p`.
+        let mut lower: Vec<Vec<(usize, F)>> = vec![vec![]; n];
+        let mut upper: Vec<Vec<(usize, F)>> = vec![vec![]; n];
+        let mut diag = vec![0.; n];
+        let z = |lower: &[Vec<(usize, F)>],
+                 upper: &[Vec<(usize, F)>],
+                 diag: &[F],
+                 i: usize,
+                 j: usize| {
+            use core::cmp::Ordering;
+            let (lane, key) = match i.cmp(&j) {
+                Ordering::Equal => return Some(diag[i]),
+                Ordering::Greater => (&lower[j], i),
+                Ordering::Less => (&upper[i], j),
+            };
+            let k = lane.binary_search_by_key(&key, |&(k, _)| k).ok()?;
+            Some(lane[k].1)
+        };
+        for m in (0..n).rev() {
+            let d = *self.l_u.get(m, m).unwrap();
+            let u_m: Vec<(usize, F)> = u_rows
+                .row_iter(m)
+                .filter(|&(k, _)| k > m)
+                .map(|(k, &u)| (k, u / d))
+                .collect();
+            let l_m: Vec<(usize, F)> = self
+                .l_u
+                .col_iter(m)
+                .filter(|&(p, _)| p > m)
+                .map(|(p, &l)| (p, l))
+                .collect();
+            let closed = "the pattern of the factors is closed under the recurrence";
+            lower[m] = u_m
+                .iter()
+                .map(|&(k, _)| {
+                    let sum = l_m
+                        .iter()
+                        .map(|&(p, l)| z(&lower, &upper, &diag, k, p).expect(closed) * l)
+                        .sum::<F>();
+                    (k, -sum)
+                })
+                .collect();
+            upper[m] = l_m
+                .iter()
+                .map(|&(p, _)| {
+                    let sum = u_m
+                        .iter()
+                        .map(|&(k, u)| u * z(&lower, &upper, &diag, k, p).expect(closed))
+                        .sum::<F>();
+                    (p, -sum)
+                })
+                .collect();
+            diag[m] = 1. / d
+                - u_m
+                    .iter()
+                    .zip(&lower[m])
+                    .map(|(&(_, u), &(_, z))| u * z)
+                    .sum::<F>();
+        }
+
+        // PAQ = LU, so A⁻¹[a, a] = Z[j, i] where Q[j] = a and P[i] = a.
+        let inv_pivot = &self.inv_pivot;
+        let mut inv_col: Vec<usize> = (0..n).collect();
+        if let Some(q) = &self.col_perm {
+            for (j, &c) in q.iter().enumerate() {
+                inv_col[c] = j;
+            }
+        }
+        let mut buf = vec![0.; n];
+        (0..n)
+            .map(|a| {
+                z(&lower, &upper, &diag, inv_col[a], inv_pivot[a]).unwrap_or_else(|| {
+                    let mut e = vec![0.; n];
+                    e[a] = 1.;
+                    self.solve(&mut e, &mut buf);
+                    e[a]
+                })
+            })
+            .collect()
+    }
+
+    /// Captures the symbolic structure of this factorization of `a`, so that matrices with
+    /// the same pattern can be refactorized with
+    /// [`LuSymbolic::refactor_values`] without repeating the symbolic analysis or pivoting.
+    pub fn symbolic(&self, a: &Csc<F>) -> LuSymbolic {
+        assert_eq!(a.ncols(), self.l_u.ncols());
+        LuSymbolic {
+            a: a.clone(),
+            l_u: self.l_u.clone(),
+            pivot: self.pivot.clone(),
+            col_perm: self.col_perm.clone(),
+            pivot_tol: self.pivot_tol,
+        }
+    }
+
+    /// Converts this factorization into a read-only form for repeated solves, with `L` and
+    /// `U` stored separately and without any excess capacity.
+    pub fn compact(self) -> CompactLu {
+        let mut l = self.l_u.filter_entries(|r, c| r > c);
+        let mut u = self.l_u.upper_triangle();
+        l.0.shrink_to_fit();
+        u.0.shrink_to_fit();
+        CompactLu {
+            l,
+            u,
+            pivot: self.pivot.into_boxed_slice(),
+            col_perm: self.col_perm.map(Vec::into_boxed_slice),
+        }
     }
 
     /// Construct a new sparse LU factorization
     /// from a given CSC matrix.
+    /// Panics if the matrix is singular.
     pub fn new(a: &Csc<F>) -> Self {
-        let mut a = a.clone(); // TODO tmp remove this later
+        Self::new_with_strategy(a, PivotStrategy::Partial)
+    }
+
+    /// Construct a new sparse LU factorization from a given CSC matrix,
+    /// selecting pivots with the given strategy.
+    /// Panics if the matrix is singular.
+    pub fn new_with_strategy(a: &Csc<F>, strategy: PivotStrategy) -> Self {
+        Self::try_new_with_strategy(a, strategy).expect("rank-deficient matrix")
+    }
+
+    /// Construct a new sparse LU factorization from a given CSC matrix, returning an error
+    /// if the matrix is singular.
+    pub fn try_new(a: &Csc<F>) -> Result<Self, LuError> {
+        Self::try_new_with_strategy(a, PivotStrategy::Partial)
+    }
+
+    /// Construct a new sparse LU factorization from a given CSC matrix, selecting pivots with
+    /// the given strategy, returning an error if the matrix is singular.
+    pub fn try_new_with_strategy(a: &Csc<F>, strategy: PivotStrategy) -> Result<Self, LuError> {
+        Self::try_new_with_tolerance(a, strategy, Self::DEFAULT_PIVOT_TOLERANCE)
+    }
+
+    /// Construct a new sparse LU factorization from a given CSC matrix, selecting pivots with
+    /// the given strategy, and treating any pivot with a magnitude at most `zero_pivot_tol`
+    /// times the largest magnitude in its column as zero, as described in
+    /// [`pivot_tolerance`](Self::pivot_tolerance). Returns an error if the matrix is singular
+    /// under this tolerance.
+    pub fn try_new_with_tolerance(
+        a: &Csc<F>,
+        strategy: PivotStrategy,
+        zero_pivot_tol: F,
+    ) -> Result<Self, LuError> {
+        Self::factorize(
+            a,
+            strategy,
+            zero_pivot_tol,
+            &mut LuScratch::default(),
+            #[cfg(feature = "profiling")]
+            None,
+        )
+    }
+
+    /// Construct a new sparse LU factorization from a given CSC matrix, after reordering it
+    /// with `order` to reduce fill, where `order[i]` is the original index placed at position
+    /// `i`. The ordering is applied symmetrically to the rows and columns before pivoting,
+    /// so that the diagonal stays on the diagonal, and the factorization is of `PAQ = LU`.
+    /// Panics if the matrix is singular.
+    pub fn new_with_ordering(a: &Csc<F>, order: &[usize]) -> Self {
+        let mut lu = Self::new(&a.permute(order, order));
+        for p in lu.pivot.iter_mut() {
+            *p = order[*p];
+        }
+        lu.inv_pivot = invert_permutation(&lu.pivot);
+        lu.col_perm = Some(order.to_vec());
+        lu
+    }
+
+    /// Construct a new sparse LU factorization like
+    /// [`new_with_ordering`](Self::new_with_ordering), after first permuting the rows of `a`
+    /// with `row_perm`, where `row_perm[i]` is the original row placed at position `i`, such
+    /// as from [`max_weight_matching`](Csc::max_weight_matching). `order` is then applied
+    /// symmetrically to the row permuted matrix, so entries moved onto the diagonal stay
+    /// there. The pivot refers to the rows of `a`, so this still solves `Ax = b`.
+    /// Panics if the matrix is singular.
+    pub fn new_with_row_perm(a: &Csc<F>, row_perm: &[usize], order: &[usize]) -> Self {
+        let identity = (0..a.ncols()).collect::<Vec<_>>();
+        let mut lu = Self::new_with_ordering(&a.permute(row_perm, &identity), order);
+        for p in lu.pivot.iter_mut() {
+            *p = row_perm[*p];
+        }
+        lu.inv_pivot = invert_permutation(&lu.pivot);
+        lu
+    }
+
+    /// Construct a new sparse LU factorization from a given CSC matrix, automatically
+    /// choosing a fill reducing ordering. Minimum degree on `A + Aᵀ` is used for nearly
+    /// symmetric patterns, and otherwise [`colamd_ordering`](crate::SparsityPattern::colamd_ordering).
+    /// Panics if the matrix is singular.
+    pub fn new_auto(a: &Csc<F>) -> Self {
+        let pattern = a.pattern();
+        let order = if pattern.symmetry() >= 0.5 {
+            pattern.minimum_degree_ordering()
+        } else {
+            pattern.colamd_ordering()
+        };
+        Self::new_with_ordering(a, &order)
+    }
+
+    /// Construct a new sparse LU factorization from a given CSC matrix, reusing the buffers
+    /// in `scratch` instead of allocating new ones.
+    /// Panics if the matrix is singular.
+    pub fn new_with_scratch(a: &Csc<F>, scratch: &mut LuScratch) -> Self {
+        Self::factorize(
+            a,
+            PivotStrategy::Partial,
+            Self::DEFAULT_PIVOT_TOLERANCE,
+            scratch,
+            #[cfg(feature = "profiling")]
+            None,
+        )
+        .expect("rank-deficient matrix")
+    }
+
+    /// Construct a new sparse LU factorization from a given CSC matrix, also recording how
+    /// much work was done for each column.
+    /// Panics if the matrix is singular.
+    #[cfg(feature = "profiling")]
+    pub fn new_profiled(a: &Csc<F>) -> (Self, Vec<ColumnProfile>) {
+        let mut profile = Vec::new();
+        let lu = Self::factorize(
+            a,
+            PivotStrategy::Partial,
+            Self::DEFAULT_PIVOT_TOLERANCE,
+            &mut LuScratch::default(),
+            Some(&mut profile),
+        )
+        .expect("rank-deficient matrix");
+        (lu, profile)
+    }
+
+    fn factorize(
+        a: &Csc<F>,
+        strategy: PivotStrategy,
+        pivot_tol: F,
+        scratch: &mut LuScratch,
+        #[cfg(feature = "profiling")] mut profile: Option<&mut Vec<ColumnProfile>>,
+    ) -> Result<Self, LuError> {
         assert_eq!(a.nrows(), a.ncols());
         let n = a.nrows();
 
-        let mut pivot = vec![0; n];
-        for i in 0..n {
-            pivot[i] = i;
+        // A column without any nonzeros can never have a pivot.
+        if let Some(column) = (0..n).find(|&c| a.col_iter(c).all(|(_, &v)| v == 0.)) {
+            return Err(LuError::Singular { column });
         }
 
+        let mut pivot: Vec<usize> = (0..n).collect();
+
+        // A diagonal matrix is its own factorization, with L = I and no pivoting.
+        if a.pattern().is_diagonal() {
+            if let Some(column) = a.values().iter().position(|v| !v.is_finite()) {
+                return Err(LuError::NonFinite { column });
+            }
+            // each column only holds its pivot, so only an exact zero is singular.
+            if let Some(column) = a.values().iter().position(|&v| v == 0.) {
+                return Err(LuError::Singular { column });
+            }
+            #[cfg(feature = "profiling")]
+            if let Some(profile) = profile {
+                profile.clear();
+                profile.resize(n, ColumnProfile { reach: 1, flops: 0 });
+            }
+            return Ok(Self {
+                l_u: a.clone(),
+                inv_pivot: pivot.clone(),
+                pivot,
+                col_perm: None,
+                pivot_tol,
+            });
+        }
+
+        let mut row_max: Vec<F> = vec![];
+        if let PivotStrategy::OriginalRowThreshold(_) = strategy {
+            row_max.resize(n, 0.);
+            for ([_, r], v) in a.pattern().entries().zip(a.values()) {
+                row_max[r] = row_max[r].max(v.abs());
+            }
+        }
+
+        let mut a = a.clone(); // TODO tmp remove this later
+
         // this initially starts as an identity  matrix.
         // but the ones are all implicit.
         let mut csc_builder: CscBuilder<F> = CscBuilder::new(n, n);
 
-        let mut val_buf = vec![];
-        let mut pat_contains = vec![false; n];
-        let mut pat_buf = vec![];
-        let mut stack = vec![];
+        let LuScratch {
+            val_buf,
+            pat_contains,
+            pat_buf,
+            stack,
+            ris_buf,
+        } = scratch;
+        pat_contains.resize(n, false);
+        stack.clear();
 
         for ci in 0..n {
             let mut curr_mat = csc_builder.build();
 
             let (col_vals, col_ris) = a.col(ci);
-            curr_mat.pattern().sparse_lower_triangular_solve_bool(
-                col_ris,
-                &mut pat_contains,
-                &mut stack,
-            );
+            ris_buf.clear();
+            ris_buf.extend(col_ris.iter().copied().map(to_usize));
+            curr_mat
+                .pattern()
+                .sparse_lower_triangular_solve_bool(ris_buf, pat_contains, stack);
             pat_buf.clear();
             pat_buf.extend(
                 pat_contains
@@ -112,26 +841,40 @@ impl LeftLookingLUFactorization<F> {
             // sort pat and val buf here
 
             // Solve the current column, assuming that it is lower triangular
-            curr_mat.sparse_lower_triangular_solve_sorted(
-                col_ris,
-                col_vals,
-                &pat_buf,
-                &mut val_buf,
-                true,
-            );
+            let solved = curr_mat
+                .sparse_lower_triangular_solve_sorted(ris_buf, col_vals, pat_buf, val_buf, true);
+            debug_assert_eq!(solved, Ok(()));
+
+            #[cfg(feature = "profiling")]
+            if let Some(profile) = profile.as_deref_mut() {
+                // each solved row applies the entries below the diagonal of its column in L,
+                // and each row below the pivot is then divided by it.
+                let updates = pat_buf
+                    .iter()
+                    .map(|&r| curr_mat.col_iter(r).filter(|&(lr, _)| lr > r).count())
+                    .sum::<usize>();
+                let divisions = pat_buf.len() - pat_buf.partition_point(|&r| r <= ci);
+                profile.push(ColumnProfile {
+                    reach: pat_buf.len(),
+                    flops: updates + divisions,
+                });
+            }
 
             // find optimal pivot
-            // TODO remove these unwraps
-            let best_i = val_buf
-                .iter()
-                .enumerate()
-                .filter(|&(i, _)| pat_buf[i] >= ci)
-                .max_by(|&(_, a), &(_, b)| a.abs().partial_cmp(&b.abs()).unwrap())
-                .map(|v| v.0)
-                .unwrap_or(ci);
+            let Some(best_i) = strategy.select(ci, pat_buf, val_buf, &row_max) else {
+                return Err(LuError::Singular { column: ci });
+            };
 
             let ukk = val_buf[best_i];
-            assert_ne!(ukk, 0., "rank-deficient matrix");
+            if !ukk.is_finite() {
+                return Err(LuError::NonFinite { column: ci });
+            }
+            let col_max = val_buf
+                .iter()
+                .fold(0., |m: F, v| if v.is_finite() { m.max(v.abs()) } else { m });
+            if ukk.abs() <= pivot_tol * col_max {
+                return Err(LuError::Singular { column: ci });
+            }
 
             let best_i = pat_buf[best_i];
             if best_i != ci {
@@ -162,6 +905,9 @@ impl LeftLookingLUFactorization<F> {
                 pivot.swap(ci, best_i);
                 curr_mat.swap_rows(ci, best_i);
                 a.swap_rows(ci, best_i);
+                if !row_max.is_empty() {
+                    row_max.swap(ci, best_i);
+                }
             }
 
             // convert builder back to matrix
@@ -173,19 +919,191 @@ impl LeftLookingLUFactorization<F> {
             for i in 0..pat_buf.len() {
                 let row = unsafe { *pat_buf.get_unchecked(i) };
                 let val = unsafe { *val_buf.get_unchecked(i) };
-                use std::cmp::Ordering;
+                use core::cmp::Ordering;
                 let val = match row.cmp(&ci) {
                     Ordering::Less | Ordering::Equal => val,
                     Ordering::Greater => val / ukk,
                 };
-                assert!(val.is_finite());
+                if !val.is_finite() {
+                    return Err(LuError::NonFinite { column: ci });
+                }
                 let ins = csc_builder.insert(row, ci, val);
                 debug_assert_eq!(ins, Ok(()));
             }
         }
 
         let l_u = csc_builder.build();
-        assert!(l_u.values().iter().copied().all(F::is_finite));
-        Self { l_u, pivot }
+        Ok(Self {
+            l_u,
+            inv_pivot: invert_permutation(&pivot),
+            pivot,
+            col_perm: None,
+            pivot_tol,
+        })
+    }
+}
+
+/// The symbolic structure of an LU factorization, which is its pivot order, column ordering
+/// and the sparsity pattern of its factors. Matrices with the same pattern can be refactorized
+/// reusing all of it, which only performs the numeric elimination.
+#[derive(Debug, Clone)]
+pub struct LuSymbolic {
+    /// The originally factorized matrix, whose pattern new values are given in.
+    a: Csc<F>,
+    /// The factors of `a`, whose pattern is reused.
+    l_u: Csc<F>,
+    pivot: Vec<usize>,
+    col_perm: Option<Vec<usize>>,
+    pivot_tol: F,
+}
+
+impl LuSymbolic {
+    /// Factorizes the matrix with the pattern of the original matrix and the values
+    /// `new_values`, which are in the same order as its [`values`](Csc::values). The pivot
+    /// order is kept fixed, so this is only stable if the values are close to the original.
+    /// Panics if a pivot becomes zero, within the tolerance of the original factorization.
+    pub fn refactor_values(&self, new_values: &[F]) -> LeftLookingLUFactorization<F> {
+        assert_eq!(new_values.len(), self.a.nnz());
+        let n = self.a.ncols();
+        let mut a = self.a.clone();
+        a.values_mut().copy_from_slice(new_values);
+        let identity: Vec<usize>;
+        let q = match &self.col_perm {
+            Some(q) => q.as_slice(),
+            None => {
+                identity = (0..n).collect();
+                &identity
+            }
+        };
+        // PAQ, which has the same pattern as the matrix that was originally eliminated.
+        let b = a.permute(&self.pivot, q);
+
+        let pattern = self.l_u.pattern();
+        let mut vals = vec![0.; self.l_u.nnz()];
+        let mut x = vec![0.; n];
+        for j in 0..n {
+            for (r, &v) in b.col_iter(j) {
+                x[r] = v;
+            }
+            let col = pattern.lane_range(j);
+            let diag_start = col.start + pattern.lane(j).partition_point(|&r| to_usize(r) < j);
+            // rows above the diagonal are in ascending order, so each is final when reached.
+            for idx in col.start..diag_start {
+                let k = pattern.minor(idx);
+                let xk = x[k];
+                vals[idx] = xk;
+                for lidx in pattern
+                    .lane_range(k)
+                    .filter(|&lidx| pattern.minor(lidx) > k)
+                {
+                    x[pattern.minor(lidx)] -= vals[lidx] * xk;
+                }
+            }
+            let d = x[j];
+            let col_max = vals[col.start..diag_start]
+                .iter()
+                .chain(
+                    pattern.lane(j)[diag_start - col.start..]
+                        .iter()
+                        .map(|&r| &x[to_usize(r)]),
+                )
+                .fold(0., |m: F, v| m.max(v.abs()));
+            assert!(
+                d.abs() > self.pivot_tol * col_max && d.is_finite(),
+                "zero pivot in column {j}"
+            );
+            for (idx, v) in vals[diag_start..col.end].iter_mut().enumerate() {
+                let r = pattern.minor(diag_start + idx);
+                *v = if r == j { d } else { x[r] / d };
+            }
+            for idx in col {
+                x[pattern.minor(idx)] = 0.;
+            }
+        }
+
+        let mut l_u = self.l_u.clone();
+        l_u.values_mut().copy_from_slice(&vals);
+        LeftLookingLUFactorization {
+            l_u,
+            pivot: self.pivot.clone(),
+            inv_pivot: invert_permutation(&self.pivot),
+            col_perm: self.col_perm.clone(),
+            pivot_tol: self.pivot_tol,
+        }
+    }
+}
+
+/// A read-only LU factorization produced by [`LeftLookingLUFactorization::compact`], for
+/// when a factorization is only used to solve.
+#[derive(Debug, Clone)]
+pub struct CompactLu {
+    /// Strictly lower triangular part of `L`, which implicitly has 1 along the diagonal.
+    l: Csc<F>,
+    u: Csc<F>,
+    pivot: Box<[usize]>,
+    col_perm: Option<Box<[usize]>>,
+}
+
+impl CompactLu {
+    /// Computes `x` in `Ax = b`, where `b` is a dense vector.
+    /// The output will be stored in b, and buf is used as a temporary buffer.
+    pub fn solve(&self, b: &mut [F], buf: &mut [F]) {
+        assert_eq!(b.len(), buf.len());
+        for (o, &p) in buf.iter_mut().zip(self.pivot.iter()) {
+            *o = b[p];
+        }
+        self.l.dense_lower_triangular_solve(buf, b, true);
+        self.u.dense_upper_triangular_solve(b, buf);
+        match &self.col_perm {
+            Some(q) => {
+                for (&c, &y) in q.iter().zip(buf.iter()) {
+                    b[c] = y;
+                }
+            }
+            None => b.copy_from_slice(buf),
+        }
+    }
+}
+
+impl Csc<F> {
+    /// Computes the Schur complement `A22 - A21 A11⁻¹ A12` of the block partition
+    /// `A = [[A11, A12], [A21, A22]]`, where `A11` is the leading `block_size × block_size`
+    /// block. Returns an error if `A11` is singular.
+    pub fn schur_complement(&self, block_size: usize) -> Result<Csc<F>, LuError> {
+        let n = self.nrows();
+        assert_eq!(n, self.ncols());
+        assert!(block_size <= n);
+        let (k, m) = (block_size, n - block_size);
+        let a11 = self.submatrix(0..k, 0..k);
+        let a12 = self.submatrix(0..k, k..n);
+        let a21 = self.submatrix(k..n, 0..k);
+        let a22 = self.submatrix(k..n, k..n);
+        let lu = LeftLookingLUFactorization::try_new(&a11)?;
+
+        let mut builder = CscBuilder::new(m, m);
+        let mut x = vec![0.; k];
+        let mut buf = vec![0.; k];
+        let mut col = vec![0.; m];
+        for j in 0..m {
+            col.fill(0.);
+            for (r, &v) in a22.col_iter(j) {
+                col[r] = v;
+            }
+            if a12.col_iter(j).next().is_some() {
+                x.fill(0.);
+                for (r, &v) in a12.col_iter(j) {
+                    x[r] = v;
+                }
+                lu.solve(&mut x, &mut buf);
+                for (c, r) in col.iter_mut().zip(a21.vecmul(&x)) {
+                    *c -= r;
+                }
+            }
+            for (r, &v) in col.iter().enumerate().filter(|&(_, &v)| v != 0.) {
+                let ins = builder.insert(r, j, v);
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        Ok(builder.build())
     }
 }
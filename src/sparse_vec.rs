@@ -0,0 +1,125 @@
+use core::borrow::Borrow;
+
+use super::{F, to_usize};
+use crate::csc::Csc;
+use alloc::vec::Vec;
+
+/// A sparse vector of length `dim`, storing the values at `indices` in ascending order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SparseVec<T> {
+    dim: usize,
+    indices: Vec<usize>,
+    values: Vec<T>,
+}
+
+impl<T> SparseVec<T> {
+    /// Constructs a sparse vector from its nonzero entries, where `indices` must be strictly
+    /// ascending and less than `dim`.
+    pub fn new(dim: usize, indices: Vec<usize>, values: Vec<T>) -> Self {
+        assert_eq!(indices.len(), values.len());
+        assert!(indices.is_sorted_by(|a, b| a < b));
+        assert!(indices.last().is_none_or(|&i| i < dim));
+        Self {
+            dim,
+            indices,
+            values,
+        }
+    }
+    /// Length of the equivalent dense vector.
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+    /// Positions of the stored entries, in ascending order.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+    /// Values of the stored entries, in the same order as the indices.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+    /// Number of stored entries.
+    pub fn nnz(&self) -> usize {
+        self.indices.len()
+    }
+    /// Iterates over each `(index, &value)`.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (usize, &T)> + '_ {
+        self.indices.iter().copied().zip(&self.values)
+    }
+}
+
+impl<T: Borrow<F>> SparseVec<T> {
+    /// Copies the values of this vector, such as one borrowed from [`Csc::column`].
+    pub fn to_owned(&self) -> SparseVec<F> {
+        SparseVec {
+            dim: self.dim,
+            indices: self.indices.clone(),
+            values: self.values.iter().map(|v| *v.borrow()).collect(),
+        }
+    }
+    /// Computes `xᵀy`, only multiplying entries stored in both vectors.
+    pub fn dot<U: Borrow<F>>(&self, y: &SparseVec<U>) -> F {
+        assert_eq!(self.dim, y.dim);
+        let mut yi = y.iter().peekable();
+        let mut sum = 0.;
+        for (i, x) in self.iter() {
+            while yi.next_if(|&(j, _)| j < i).is_some() {}
+            if let Some(&(j, y)) = yi.peek()
+                && i == j
+            {
+                sum += x.borrow() * y.borrow();
+            }
+        }
+        sum
+    }
+    /// Computes the euclidean norm.
+    #[cfg(feature = "std")]
+    pub fn norm(&self) -> F {
+        let values = self.values.iter().map(|v| *v.borrow()).collect::<Vec<_>>();
+        crate::vecops::norm2(&values)
+    }
+    /// Converts into a dense vector of length `dim`.
+    pub fn to_dense(&self) -> Vec<F> {
+        let mut out = alloc::vec![0.; self.dim];
+        for (i, v) in self.iter() {
+            out[i] = *v.borrow();
+        }
+        out
+    }
+}
+
+impl SparseVec<F> {
+    /// Computes `self += a * x`, where the pattern of `self` becomes the union of both.
+    pub fn axpy<U: Borrow<F>>(&mut self, a: F, x: &SparseVec<U>) {
+        assert_eq!(self.dim, x.dim);
+        let mut indices = Vec::with_capacity(self.nnz() + x.nnz());
+        let mut values = Vec::with_capacity(self.nnz() + x.nnz());
+        let mut yi = self.iter().peekable();
+        for (i, x) in x.iter() {
+            while let Some((j, &y)) = yi.next_if(|&(j, _)| j < i) {
+                indices.push(j);
+                values.push(y);
+            }
+            let y = yi.next_if(|&(j, _)| j == i).map_or(0., |(_, &y)| y);
+            indices.push(i);
+            values.push(y + a * x.borrow());
+        }
+        for (j, &y) in yi {
+            indices.push(j);
+            values.push(y);
+        }
+        self.indices = indices;
+        self.values = values;
+    }
+}
+
+impl<T> Csc<T> {
+    /// Returns column `i` as a sparse vector borrowing its values.
+    pub fn column(&self, i: usize) -> SparseVec<&T> {
+        let (values, rows) = self.col(i);
+        SparseVec {
+            dim: self.nrows(),
+            indices: rows.iter().map(|&r| to_usize(r)).collect(),
+            values: values.iter().collect(),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use super::F;
+use crate::csc::{Csc, CscBuilder};
+use alloc::vec;
+use alloc::vec::Vec;
+
+/// A symmetric matrix which only stores its lower triangle, including the diagonal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymmetricCsc<T>(Csc<T>);
+
+impl<T> SymmetricCsc<T> {
+    /// Number of rows (and columns) of the full matrix.
+    pub fn n(&self) -> usize {
+        self.0.ncols()
+    }
+
+    /// The stored lower triangle.
+    pub fn lower(&self) -> &Csc<T> {
+        &self.0
+    }
+
+    /// Number of stored entries, which only includes the lower triangle.
+    pub fn nnz(&self) -> usize {
+        self.0.nnz()
+    }
+}
+
+impl SymmetricCsc<F> {
+    /// Constructs a symmetric matrix from the lower triangle of `a`.
+    /// Entries in the upper triangle are ignored, and are not checked for symmetry.
+    pub fn from_full(a: &Csc<F>) -> Self {
+        assert_eq!(a.nrows(), a.ncols());
+        let mut builder = CscBuilder::new(a.nrows(), a.ncols());
+        for c in 0..a.ncols() {
+            for (r, &v) in a.col_iter(c).filter(|&(r, _)| r >= c) {
+                let ins = builder.insert(r, c, v);
+                debug_assert_eq!(ins, Ok(()));
+            }
+        }
+        Self(builder.build())
+    }
+
+    /// Constructs a symmetric matrix from an explicit lower triangle.
+    /// Panics if there are any entries in the upper triangle.
+    pub fn from_lower(lower: Csc<F>) -> Self {
+        assert_eq!(lower.nrows(), lower.ncols());
+        assert!((0..lower.ncols()).all(|c| lower.col_iter(c).all(|(r, _)| r >= c)));
+        Self(lower)
+    }
+
+    /// Expands this into a full matrix with both triangles stored.
+    pub fn to_full(&self) -> Csc<F> {
+        let mut t = vec![];
+        for c in 0..self.n() {
+            for (r, &v) in self.0.col_iter(c) {
+                t.push(([c, r], v));
+                if r != c {
+                    t.push(([r, c], v));
+                }
+            }
+        }
+        Csc::from_triplets(self.n(), self.n(), &mut t).unwrap()
+    }
+
+    /// Computes `Av`, accounting for the implicit upper triangle.
+    pub fn vecmul(&self, v: &[F]) -> Vec<F> {
+        assert_eq!(v.len(), self.n());
+        let mut out = vec![0.; self.n()];
+        for c in 0..self.n() {
+            for (r, &a) in self.0.col_iter(c) {
+                out[r] += a * v[c];
+                if r != c {
+                    out[c] += a * v[r];
+                }
+            }
+        }
+        out
+    }
+}
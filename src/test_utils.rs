@@ -0,0 +1,26 @@
+use super::F;
+use crate::csc::Csc;
+
+/// Asserts that `sparse` matches the row major `dense` matrix within `tol` for every entry,
+/// where entries which are not stored in `sparse` are treated as zero.
+#[track_caller]
+pub fn assert_matches_dense(sparse: &Csc<F>, dense: &[F], tol: F) {
+    let (nrows, ncols) = sparse.shape();
+    assert_eq!(
+        dense.len(),
+        nrows * ncols,
+        "dense matrix has {} entries, but the sparse matrix is {nrows}x{ncols}",
+        dense.len()
+    );
+    for c in 0..ncols {
+        let mut stored = sparse.col_iter(c).peekable();
+        for r in 0..nrows {
+            let v = stored.next_if(|&(sr, _)| sr == r).map_or(0., |(_, &v)| v);
+            let expected = dense[r * ncols + c];
+            assert!(
+                (v - expected).abs() <= tol,
+                "entry ({r}, {c}) is {v}, expected {expected}"
+            );
+        }
+    }
+}
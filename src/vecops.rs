@@ -0,0 +1,44 @@
+use super::F;
+
+/// Computes `xᵀy`.
+pub fn dot(x: &[F], y: &[F]) -> F {
+    assert_eq!(x.len(), y.len());
+    x.iter().zip(y).map(|(x, y)| x * y).sum()
+}
+
+/// Computes the euclidean norm of `x`, scaling to avoid overflow and underflow when
+/// squaring large or small entries. A NaN entry makes the norm NaN.
+#[cfg(feature = "std")]
+pub fn norm2(x: &[F]) -> F {
+    // `F::max` ignores NaN, so it is propagated explicitly.
+    let scale = x.iter().fold(0., |m: F, v| {
+        if m.is_nan() || v.is_nan() {
+            F::NAN
+        } else {
+            m.max(v.abs())
+        }
+    });
+    if scale == 0. || !scale.is_finite() {
+        return scale;
+    }
+    scale
+        * x.iter()
+            .map(|v| (v / scale) * (v / scale))
+            .sum::<F>()
+            .sqrt()
+}
+
+/// Computes `y += a * x`.
+pub fn axpy(a: F, x: &[F], y: &mut [F]) {
+    assert_eq!(x.len(), y.len());
+    for (y, x) in y.iter_mut().zip(x) {
+        *y += a * x;
+    }
+}
+
+/// Computes `x *= a`.
+pub fn scal(a: F, x: &mut [F]) {
+    for x in x.iter_mut() {
+        *x *= a;
+    }
+}
@@ -0,0 +1,59 @@
+#![cfg(feature = "std")]
+use sparse_lu::{Csc, F, ReadBinaryError};
+
+fn matrix() -> Csc<F> {
+    Csc::from_triplets(
+        4,
+        3,
+        &mut [([0, 0], 1.5), ([0, 3], -2.), ([2, 1], 3.25), ([2, 2], 0.)],
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_binary_roundtrip() {
+    let a = matrix();
+    let mut bytes = vec![];
+    a.write_binary(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), 6 + 3 * 8 + 4 * 8 + 4 * 8 + 4 * size_of::<F>());
+    assert_eq!(Csc::read_binary(bytes.as_slice()).unwrap(), a);
+
+    let empty = Csc::<F>::from_triplets(0, 0, &mut []).unwrap();
+    let mut bytes = vec![];
+    empty.write_binary(&mut bytes).unwrap();
+    assert_eq!(Csc::read_binary(bytes.as_slice()).unwrap(), empty);
+}
+
+#[test]
+fn test_binary_corrupt() {
+    let mut bytes = vec![];
+    matrix().write_binary(&mut bytes).unwrap();
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = b'X';
+    assert!(matches!(
+        Csc::read_binary(bad_magic.as_slice()),
+        Err(ReadBinaryError::BadMagic)
+    ));
+
+    let mut bad_version = bytes.clone();
+    bad_version[4] = 9;
+    assert!(matches!(
+        Csc::read_binary(bad_version.as_slice()),
+        Err(ReadBinaryError::UnsupportedVersion(9))
+    ));
+
+    // a row index out of bounds
+    let mut bad_row = bytes.clone();
+    bad_row[6 + 3 * 8 + 4 * 8] = 4;
+    assert!(matches!(
+        Csc::read_binary(bad_row.as_slice()),
+        Err(ReadBinaryError::InvalidStructure)
+    ));
+
+    let truncated = &bytes[..bytes.len() - 1];
+    assert!(matches!(
+        Csc::read_binary(truncated),
+        Err(ReadBinaryError::Io(_))
+    ));
+}
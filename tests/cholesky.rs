@@ -0,0 +1,96 @@
+#![cfg(feature = "std")]
+use sparse_lu::{CholeskyError, CholeskyFactorization, Csc, F};
+
+/// The 1D Laplacian shifted by `shift`, which has no fill.
+fn tridiagonal(n: usize, shift: F) -> Csc<F> {
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 2. + shift));
+        if i > 0 {
+            t.push(([i, i - 1], -1.));
+            t.push(([i - 1, i], -1.));
+        }
+    }
+    Csc::from_triplets(n, n, &mut t).unwrap()
+}
+
+#[test]
+fn test_cholesky_solve() {
+    let n = 8;
+    // an arrowhead with the hub first, which fills in the entire lower triangle.
+    let mut t = vec![([0, 0], n as F)];
+    for i in 1..n {
+        t.push(([i, i], 2.));
+        t.push(([i, 0], 1.));
+        t.push(([0, i], 1.));
+    }
+    let a = Csc::from_triplets(n, n, &mut t).unwrap();
+    let chol = CholeskyFactorization::new(&a).unwrap();
+    assert_eq!(chol.l().nnz(), n * (n + 1) / 2);
+
+    let x_true = (0..n).map(|i| i as F - 2.).collect::<Vec<_>>();
+    let mut x = a.vecmul(&x_true);
+    chol.solve(&mut x, &mut vec![0.; n]);
+    for (x, xt) in x.iter().zip(&x_true) {
+        assert!((x - xt).abs() < 1e-4, "{x} {xt}");
+    }
+}
+
+#[test]
+fn test_cholesky_factor_in_place() {
+    let n = 10;
+    let a = tridiagonal(n, 0.5);
+    let chol = CholeskyFactorization::new(&a).unwrap();
+
+    let mut in_place = a.clone();
+    CholeskyFactorization::factor_in_place(&mut in_place).unwrap();
+    let lower = in_place.lower_triangle();
+    assert_eq!(lower.pattern(), chol.l().pattern());
+    for (x, y) in lower.values().iter().zip(chol.l().values()) {
+        assert!((x - y).abs() < 1e-6, "{x} {y}");
+    }
+    // the upper triangle is untouched.
+    let strict_upper = |m: &Csc<F>| {
+        let mut t = m.to_triplets();
+        t.retain(|&([c, r], _)| r < c);
+        t
+    };
+    assert_eq!(strict_upper(&in_place), strict_upper(&a));
+
+    // the in place factor solves through the lower triangular solves.
+    let x_true = (0..n).map(|i| (i as F).cos()).collect::<Vec<_>>();
+    let b = a.vecmul(&x_true);
+    let mut y = vec![0.; n];
+    let mut x = vec![0.; n];
+    in_place.dense_lower_triangular_solve(&b, &mut y, false);
+    in_place.dense_lower_triangular_transpose_solve(&y, &mut x, false);
+    for (x, xt) in x.iter().zip(&x_true) {
+        assert!((x - xt).abs() < 1e-4, "{x} {xt}");
+    }
+}
+
+#[test]
+fn test_cholesky_errors() {
+    // indefinite, with eigenvalues 3 and -1.
+    let indefinite = Csc::from(vec![vec![1., 0., 2.], vec![0., 1., 0.], vec![2., 0., 1.]]);
+    let expected = CholeskyError::NotPositive {
+        column: 2,
+        value: -3.,
+    };
+    assert_eq!(
+        CholeskyFactorization::new(&indefinite).unwrap_err(),
+        expected
+    );
+    let mut in_place = indefinite.clone();
+    assert_eq!(
+        CholeskyFactorization::factor_in_place(&mut in_place).unwrap_err(),
+        expected
+    );
+
+    // eliminating column 0 fills in (2, 1), which is not stored.
+    let mut fill = Csc::from(vec![vec![4., 1., 1.], vec![1., 4., 0.], vec![1., 0., 4.]]);
+    assert_eq!(
+        CholeskyFactorization::factor_in_place(&mut fill).unwrap_err(),
+        CholeskyError::MissingEntry { row: 2, col: 1 }
+    );
+}
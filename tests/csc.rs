@@ -1,4 +1,8 @@
-use sparse_lu::Csc;
+use sparse_lu::csc::{Csc, CscBuilder, UnorderedCscBuilder};
+use sparse_lu::{
+    BuildError, BuilderInsertError, DuplicatePolicy, F, PatternError, RestoreError, SetColumnError,
+    TriangularError, ZeroPolicy,
+};
 
 #[test]
 fn test_dense_lower_triangular_solve() {
@@ -18,6 +22,27 @@ fn test_dense_lower_triangular_solve() {
     assert_eq!(out, v);
 }
 
+#[test]
+fn test_dense_lower_triangular_solve_inplace() {
+    let a = Csc::from(vec![
+        vec![2., 0., 0., 0.],
+        vec![1., 4., 0., 0.],
+        vec![0., -3., 0.5, 0.],
+        vec![7., 0., 1., 1.],
+    ]);
+    let b = [1., -2., 3., 0.25];
+    for unit_diagonal in [false, true] {
+        let mut out = [0.; 4];
+        a.dense_lower_triangular_solve(&b, &mut out, unit_diagonal);
+        let mut inplace = b;
+        a.dense_lower_triangular_solve_inplace(&mut inplace, unit_diagonal);
+        assert_eq!(inplace, out);
+    }
+    let mut x = b;
+    a.dense_lower_triangular_solve_inplace(&mut x, false);
+    assert_eq!(x, [0.5, -0.625, 2.25, -5.5]);
+}
+
 #[test]
 fn test_permuting() {
     let mut a = Csc::identity(3);
@@ -56,3 +81,1107 @@ fn test_permuting() {
         dense.col(2)
     );
 }
+
+#[test]
+fn test_map_values() {
+    let mut a = Csc::from_triplets(
+        3,
+        3,
+        &mut [([0, 0], 1.), ([0, 2], -2.), ([1, 1], 3.), ([2, 0], -4.)],
+    )
+    .unwrap();
+    let sq = a.map_values(|v| v * v);
+    assert_eq!(sq.pattern(), a.pattern());
+    assert_eq!(sq.values(), &[1., 4., 9., 16.]);
+    // the original is untouched
+    assert_eq!(a.values(), &[1., -2., 3., -4.]);
+
+    a.map_values_in_place(|v| v * v);
+    assert_eq!(a, sq);
+}
+
+#[test]
+fn test_outer_product() {
+    let u = [0., 2., 0., -1.];
+    let v = [3., 0., 0.5];
+    let a = Csc::outer_product(&[3, 1], &[-1., 2.], &[2, 0], &[0.5, 3.], 4, 3);
+    assert_eq!(a.nrows(), 4);
+    assert_eq!(a.ncols(), 3);
+    assert_eq!(a.nnz(), 4);
+    let mut dense = [[0.; 3]; 4];
+    for ([c, r], &val) in a.pattern().entries().zip(a.values()) {
+        dense[r][c] = val;
+    }
+    for (r, row) in dense.iter().enumerate() {
+        for (c, &val) in row.iter().enumerate() {
+            assert_eq!(val, u[r] * v[c]);
+        }
+    }
+}
+
+#[test]
+fn test_append_columns() {
+    let mut a = Csc::from_triplets(3, 2, &mut [([0, 0], 1.), ([1, 1], 2.), ([1, 2], 3.)]).unwrap();
+    let expected = Csc::from_triplets(
+        3,
+        4,
+        &mut [
+            ([0, 0], 1.),
+            ([1, 1], 2.),
+            ([1, 2], 3.),
+            ([2, 0], 4.),
+            ([2, 2], 5.),
+            ([3, 1], 6.),
+        ],
+    )
+    .unwrap();
+
+    // unsorted columns are rejected without modifying the matrix
+    let og = a.clone();
+    assert!(
+        a.append_columns(&[(vec![0, 2], vec![4., 5.]), (vec![1, 0], vec![6., 7.])])
+            .is_err()
+    );
+    assert_eq!(a, og);
+    // as are rows outside of the matrix, and rows without a value.
+    assert_eq!(
+        a.append_columns(&[(vec![0, 2], vec![4., 5.]), (vec![1, 3], vec![6., 7.])]),
+        Err(BuilderInsertError::OutOfBounds(3, 3))
+    );
+    assert_eq!(
+        a.append_columns(&[(vec![0, 2], vec![4.])]),
+        Err(BuilderInsertError::LengthMismatch(2, 1))
+    );
+    assert_eq!(a, og);
+
+    assert!(
+        a.append_columns(&[(vec![0, 2], vec![4., 5.]), (vec![1], vec![6.])])
+            .is_ok()
+    );
+    assert_eq!(a.ncols(), 4);
+    assert_eq!(a, expected);
+}
+
+#[test]
+fn test_build_checked() {
+    let mut builder = CscBuilder::new(3, 3);
+    assert!(builder.insert(0, 0, 1.).is_ok());
+    assert!(builder.insert(2, 1, 1.).is_ok());
+    assert_eq!(
+        builder.clone().build_checked(),
+        Err(BuildError::Incomplete(1, 2))
+    );
+    // the lenient build still succeeds
+    assert_eq!(builder.clone().build().ncols(), 3);
+
+    assert!(builder.insert(1, 2, 1.).is_ok());
+    let built = builder.clone().build_checked().unwrap();
+    assert_eq!(built, builder.build());
+}
+
+fn check_multi_rhs_solve<const N: usize>(a: &Csc<F>) {
+    let n = a.ncols();
+    let b = (0..n)
+        .map(|i| std::array::from_fn(|d| (i * N + d) as F * 0.25 - 1.))
+        .collect::<Vec<[F; N]>>();
+
+    let mut lower = vec![[0.; N]; n];
+    let mut upper = vec![[0.; N]; n];
+    a.dense_lower_triangular_solve_arr(&b, &mut lower, false);
+    a.dense_upper_triangular_solve_arr(&b, &mut upper);
+
+    let mut single_b = vec![0.; n];
+    let mut single_out = vec![0.; n];
+    for d in 0..N {
+        for (s, b) in single_b.iter_mut().zip(&b) {
+            *s = b[d];
+        }
+        a.dense_lower_triangular_solve(&single_b, &mut single_out, false);
+        for (s, l) in single_out.iter().zip(&lower) {
+            assert_eq!(*s, l[d]);
+        }
+        a.dense_upper_triangular_solve(&single_b, &mut single_out);
+        for (s, u) in single_out.iter().zip(&upper) {
+            assert_eq!(*s, u[d]);
+        }
+    }
+}
+
+#[test]
+fn test_multi_rhs_triangular_solve() {
+    let n = 7;
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 2. + i as F));
+        if i + 1 < n {
+            t.push(([i, i + 1], 0.5));
+            t.push(([i + 1, i], -0.25));
+        }
+        if i + 3 < n {
+            t.push(([i, i + 3], 1.5));
+        }
+    }
+    let a = Csc::from_triplets(n, n, &mut t).unwrap();
+    check_multi_rhs_solve::<4>(&a);
+    check_multi_rhs_solve::<8>(&a);
+}
+
+#[test]
+fn test_from_rows() {
+    let rows = vec![vec![1., 0., 2.], vec![0., 3., 0.], vec![4., 0., 5.]];
+    let expected = Csc::from_triplets(
+        3,
+        3,
+        &mut [
+            ([0, 0], 1.),
+            ([0, 2], 4.),
+            ([1, 1], 3.),
+            ([2, 0], 2.),
+            ([2, 2], 5.),
+        ],
+    )
+    .unwrap();
+    assert_eq!(Csc::from_rows(&rows, ZeroPolicy::Drop), expected);
+    assert_eq!(Csc::from(rows.clone()), expected);
+
+    let kept = Csc::from_rows(&rows, ZeroPolicy::Keep);
+    assert_eq!(kept.nnz(), 9);
+    assert_eq!(kept.vecmul(&[1., 2., 3.]), expected.vecmul(&[1., 2., 3.]));
+}
+
+#[test]
+fn test_col_iter_mut() {
+    let mut a = Csc::from(vec![vec![3., 1.], vec![4., 0.], vec![0., 2.]]);
+    let norm = a.col_iter(0).map(|(_, v)| v * v).sum::<F>().sqrt();
+    for (_, v) in a.col_iter_mut(0) {
+        *v /= norm;
+    }
+    assert_eq!(a.col(0), ([0.6, 0.8].as_slice(), [0, 1].as_slice()));
+    // other columns are untouched
+    assert_eq!(a.col(1), ([1., 2.].as_slice(), [0, 2].as_slice()));
+
+    let (vals, rows) = a.col_mut(1);
+    assert_eq!(rows, &[0, 2]);
+    vals[1] = 5.;
+    assert_eq!(a.values(), &[0.6, 0.8, 1., 5.]);
+}
+
+#[test]
+fn test_unordered_builder() {
+    let mut builder = UnorderedCscBuilder::new(3, 3);
+    builder.insert(2, 2, 5.);
+    builder.insert(0, 1, 2.);
+    builder.insert(1, 0, 3.);
+    builder.insert(0, 0, 1.);
+    builder.insert(0, 1, 4.);
+    builder.insert(1, 2, -1.);
+    let a = builder.build();
+
+    let expected = Csc::from(vec![vec![1., 6., 0.], vec![3., 0., -1.], vec![0., 0., 5.]]);
+    assert_eq!(a, expected);
+}
+
+#[test]
+fn test_vecmul_permuted() {
+    let rows = vec![
+        vec![1., 0., 2., 0.],
+        vec![0., 3., 0., 4.],
+        vec![5., 0., 6., 0.],
+    ];
+    let a = Csc::from(rows.clone());
+    let row_perm = [2, 0, 1];
+    let col_perm = [3, 1, 0, 2];
+    let permuted = Csc::from(
+        row_perm
+            .iter()
+            .map(|&r| col_perm.iter().map(|&c| rows[r][c]).collect())
+            .collect::<Vec<Vec<F>>>(),
+    );
+
+    let x = [1., -2., 0.5, 3.];
+    let mut out = [0.; 3];
+    a.vecmul_permuted(&x, &row_perm, &col_perm, &mut out);
+    assert_eq!(out.as_slice(), permuted.vecmul(&x));
+}
+
+#[test]
+fn test_column_max_abs() {
+    let a = Csc::from(vec![
+        vec![1., -3., 0., 0.],
+        vec![-4., -0.5, 0., 2.],
+        vec![2., -7., 0., -2.],
+    ]);
+    assert_eq!(a.column_max_abs(), [4., 7., 0., 2.]);
+}
+
+#[test]
+fn test_builder_revert_and_assert_valid() {
+    let full = Csc::from(vec![vec![1., 2., 3.], vec![4., 5., 6.], vec![7., 8., 9.]]);
+    let mut builder = CscBuilder::from_mat(full);
+    builder.assert_valid();
+
+    // keeps the entries of columns 0 and 1, and none of column 2
+    assert!(builder.revert_to_col(1));
+    builder.assert_valid();
+    assert!(builder.insert(0, 2, -1.).is_ok());
+    builder.assert_valid();
+    // reverting to the current column removes nothing
+    assert!(builder.revert_to_col(2));
+    assert!(!builder.revert_to_col(4));
+
+    let a = builder.build();
+    assert_eq!(a.nnz(), 7);
+    assert_eq!(a.values(), [1., 4., 7., 2., 5., 8., -1.]);
+}
+
+#[test]
+fn test_matmul_rectangular() {
+    let a_dense = vec![vec![1., 0.], vec![0., 2.], vec![3., -1.], vec![0., 0.]];
+    // the middle column is empty, so the middle column of the product is too
+    let b_dense = vec![vec![1., 0., 2.], vec![4., 0., -1.]];
+    let a = Csc::from(a_dense.clone());
+    let b = Csc::from(b_dense.clone());
+    let c = a.matmul(&b);
+    assert_eq!(c.nrows(), 4);
+    assert_eq!(c.ncols(), 3);
+    assert_eq!(c.col(1).0, []);
+
+    let mut c_dense = vec![vec![0.; 3]; 4];
+    for ([col, row], &v) in c.pattern().entries().zip(c.values()) {
+        c_dense[row][col] = v;
+    }
+    for (i, row) in c_dense.iter().enumerate() {
+        for (j, &v) in row.iter().enumerate() {
+            let expected: F = (0..2).map(|k| a_dense[i][k] * b_dense[k][j]).sum();
+            assert_eq!(v, expected, "{i} {j}");
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_magnitude_histogram() {
+    let a = Csc::from(vec![
+        vec![1e-3, 0., 3e-2],
+        vec![-5e-2, 2., 0.],
+        vec![0., -20., 1e3],
+    ]);
+    // log10 spans [-3, 3], so each bin is one order of magnitude.
+    assert_eq!(a.magnitude_histogram(6), [1, 2, 0, 1, 1, 1]);
+    assert_eq!(a.magnitude_histogram(1), [6]);
+    assert_eq!(Csc::identity(3).magnitude_histogram(2), [3, 0]);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_from_hashmap() {
+    use std::collections::{BTreeMap, HashMap};
+    let entries = [
+        ([0, 2], 1.),
+        ([1, 0], 2.),
+        ([2, 1], -1.),
+        ([1, 0], 3.),
+        ([0, 0], 4.),
+    ];
+    let mut hash = HashMap::new();
+    let mut btree = BTreeMap::new();
+    for (k, v) in entries {
+        *hash.entry(k).or_insert(0.) += v;
+        *btree.entry(k).or_insert(0.) += v;
+    }
+    let a = Csc::from_hashmap(3, 3, &hash).unwrap();
+    assert_eq!(a, Csc::from_btreemap(3, 3, &btree).unwrap());
+    assert_eq!(a.nnz(), 4);
+    assert_eq!(a.get(0, 1), Some(&5.));
+
+    // keys are [col, row], so this is row 3 of a matrix with 3 rows.
+    hash.insert([1, 3], 1.);
+    btree.insert([1, 3], 1.);
+    assert_eq!(
+        Csc::from_hashmap(3, 3, &hash),
+        Err(BuilderInsertError::OutOfBounds(1, 3))
+    );
+    assert_eq!(
+        Csc::from_btreemap(3, 3, &btree),
+        Err(BuilderInsertError::OutOfBounds(1, 3))
+    );
+}
+
+#[test]
+fn test_vecmul_kahan() {
+    // a single row of 1 followed by many values too small to change 1 when added one at a time
+    let n = 1000;
+    let tiny = F::EPSILON / 4.;
+    let mut t = vec![([0, 0], 1.)];
+    t.extend((1..=n).map(|c| ([c, 0], tiny)));
+    let a = Csc::from_triplets(1, n + 1, &mut t).unwrap();
+    let x = vec![1.; n + 1];
+    let expected = 1. + n as F * tiny;
+
+    let naive = a.vecmul(&x)[0];
+    assert!((naive - expected).abs() > 100. * F::EPSILON);
+
+    let mut out = [0.];
+    a.vecmul_kahan(&x, &mut out);
+    assert!(
+        (out[0] - expected).abs() <= 2. * F::EPSILON,
+        "{} {expected}",
+        out[0]
+    );
+}
+
+#[test]
+fn test_strict_triangular_solve() {
+    let full = Csc::from(vec![vec![2., 0., 1.], vec![1., 3., 0.], vec![0., 4., 5.]]);
+    let b = [1., 2., 3.];
+    let mut out = [0.; 3];
+    assert_eq!(
+        full.dense_lower_triangular_solve_strict(&b, &mut out, false),
+        Err(TriangularError::NotTriangular { row: 0, col: 2 })
+    );
+    assert_eq!(
+        full.dense_upper_triangular_solve_strict(&b, &mut out),
+        Err(TriangularError::NotTriangular { row: 1, col: 0 })
+    );
+
+    let lower = full.lower_triangle();
+    let mut expected = [0.; 3];
+    lower.dense_lower_triangular_solve(&b, &mut expected, false);
+    assert_eq!(
+        lower.dense_lower_triangular_solve_strict(&b, &mut out, false),
+        Ok(())
+    );
+    assert_eq!(out, expected);
+
+    let upper = full.upper_triangle();
+    upper.dense_upper_triangular_solve(&b, &mut expected);
+    assert_eq!(
+        upper.dense_upper_triangular_solve_strict(&b, &mut out),
+        Ok(())
+    );
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn test_frobenius_inner() {
+    let a_dense = vec![vec![1., 0., 2.], vec![0., 3., 0.], vec![4., 0., -1.]];
+    let b_dense = vec![vec![2., 1., 0.], vec![0., -2., 5.], vec![1., 0., 3.]];
+    let hadamard_sum = a_dense
+        .iter()
+        .flatten()
+        .zip(b_dense.iter().flatten())
+        .map(|(a, b)| a * b)
+        .sum::<F>();
+    let (a, b) = (Csc::from(a_dense), Csc::from(b_dense));
+    assert_eq!(a.frobenius_inner(&b), hadamard_sum);
+    assert_eq!(b.frobenius_inner(&a), hadamard_sum);
+    assert_eq!(a.frobenius_inner(&a), 31.);
+}
+
+#[test]
+fn test_builder_insert_at() {
+    let entries = [(0, 0, 1.), (2, 0, 2.), (1, 2, 3.), (0, 3, 4.), (3, 3, 5.)];
+    let mut builder = CscBuilder::new(4, 4);
+    let idxs = entries
+        .iter()
+        .map(|&(r, c, v)| builder.insert_at(r, c, v).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        builder.insert_at(0, 3, 6.),
+        Err(BuilderInsertError::MinorTooLow(0, 3))
+    );
+    let mut m = builder.build();
+    for (&(r, c, v), &idx) in entries.iter().zip(&idxs) {
+        assert_eq!(m.values()[idx], v);
+        assert_eq!(m.get(r, c), Some(&v));
+    }
+
+    m.values_mut()[idxs[2]] = 7.;
+    assert_eq!(m.get(1, 2), Some(&7.));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_symmetric_jacobi_scaling() {
+    let a = Csc::from(vec![vec![4., 1., 0.], vec![1., 9., 2.], vec![0., 2., 16.]]);
+    let d = a.diagonal_sqrt_inv().unwrap();
+    assert_eq!(d, [0.5, 1. / 3., 0.25]);
+    let scaled = a.scale_symmetric(&d);
+    for i in 0..3 {
+        assert!((scaled.get(i, i).unwrap() - 1.).abs() < 1e-6);
+    }
+    assert!((scaled.get(0, 1).unwrap() - 1. / 6.).abs() < 1e-6);
+    assert_eq!(scaled.nnz(), a.nnz());
+
+    let b = Csc::from(vec![vec![1., 1.], vec![1., -2.]]);
+    assert_eq!(
+        b.diagonal_sqrt_inv(),
+        Err(sparse_lu::DiagonalError::NotPositive {
+            index: 1,
+            value: -2.
+        })
+    );
+    let c = Csc::from(vec![vec![1., 1.], vec![1., 0.]]);
+    assert_eq!(
+        c.diagonal_sqrt_inv(),
+        Err(sparse_lu::DiagonalError::NotPositive {
+            index: 1,
+            value: 0.
+        })
+    );
+}
+
+#[test]
+fn test_gram_column() {
+    let a = Csc::from(vec![
+        vec![1., 0., 2., 0.],
+        vec![0., 3., 0., 0.],
+        vec![4., 0., 0., 5.],
+        vec![0., 6., 7., 0.],
+        vec![0., 0., 0., 8.],
+    ]);
+    let gram = a.gram();
+    assert_eq!(gram.nrows(), 4);
+    assert_eq!(gram.ncols(), 4);
+    let mut col = vec![];
+    for j in 0..4 {
+        a.gram_column(j, &mut col);
+        let expected = gram.col_iter(j).map(|(r, &v)| (r, v)).collect::<Vec<_>>();
+        assert_eq!(col, expected);
+    }
+    a.gram_column(0, &mut col);
+    assert_eq!(col, [(0, 17.), (2, 2.), (3, 20.)]);
+}
+
+#[test]
+fn test_canonicalize_roundtrip() {
+    // a small linear congruential generator, so the cases are reproducible.
+    let mut state = 12345u64;
+    let mut next = |n: usize| {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((state >> 33) % n as u64) as usize
+    };
+    for _ in 0..100 {
+        let (rows, cols) = (1 + next(8), 1 + next(8));
+        let mut t = (0..next(30))
+            .map(|_| ([next(cols), next(rows)], next(5) as F - 2.))
+            .collect::<Vec<_>>();
+        let a = Csc::from_triplets_with_policy(
+            rows,
+            cols,
+            &mut t,
+            ZeroPolicy::Keep,
+            DuplicatePolicy::Sum,
+        )
+        .unwrap();
+        let canon = a.canonicalize();
+        assert!(canon.values().iter().all(|&v| v != 0.));
+        assert_eq!(canon.canonicalize(), canon);
+        for ([c, r], v) in a.to_triplets() {
+            assert_eq!(canon.get(r, c).copied().unwrap_or(0.), v);
+        }
+
+        let mut t = canon.to_triplets();
+        assert!(t.is_sorted_by_key(|&(k, _)| k));
+        assert_eq!(Csc::from_triplets(rows, cols, &mut t).unwrap(), canon);
+    }
+}
+
+#[test]
+fn test_shape() {
+    // a wide matrix, so that rows and columns cannot be confused
+    let a = Csc::from_triplets(2, 5, &mut [([4, 1], 1.), ([0, 0], 2.)]).unwrap();
+    assert_eq!(a.shape(), (2, 5));
+    assert_eq!((a.num_rows(), a.num_cols()), (a.nrows(), a.ncols()));
+    assert_eq!(a.pattern().major_dim(), a.ncols());
+    assert_eq!(a.pattern().minor_dim, a.nrows());
+
+    assert_eq!(CscBuilder::<F>::new(2, 5).build().shape(), (2, 5));
+    assert_eq!(UnorderedCscBuilder::<F>::new(2, 5).build().shape(), (2, 5));
+    assert_eq!(
+        Csc::from_rows(&vec![vec![1.; 5]; 2], ZeroPolicy::Keep).shape(),
+        (2, 5)
+    );
+    assert_eq!(a.transpose().shape(), (5, 2));
+}
+
+#[test]
+fn test_from_triplets_with_policy() {
+    let t = [
+        ([0, 0], 1.),
+        ([1, 0], 0.),
+        ([1, 1], 2.),
+        ([2, 2], 0.),
+        ([0, 2], -0.),
+    ];
+    let kept = Csc::from_triplets(3, 3, &mut t.clone()).unwrap();
+    assert_eq!(kept.nnz(), 5);
+    assert_eq!(
+        Csc::from_triplets_with_policy(
+            3,
+            3,
+            &mut t.clone(),
+            ZeroPolicy::Keep,
+            DuplicatePolicy::Error
+        )
+        .unwrap(),
+        kept
+    );
+
+    let dropped = Csc::from_triplets_with_policy(
+        3,
+        3,
+        &mut t.clone(),
+        ZeroPolicy::Drop,
+        DuplicatePolicy::Error,
+    )
+    .unwrap();
+    assert_eq!(dropped.nnz(), 2);
+    assert_eq!(dropped.get(1, 0), None);
+    assert_eq!(dropped, kept.canonicalize());
+
+    let mut dups = [([0, 0], 1.), ([1, 1], 3.), ([0, 0], 2.), ([1, 1], -3.)];
+    assert_eq!(
+        Csc::from_triplets_with_policy(
+            2,
+            2,
+            &mut dups.clone(),
+            ZeroPolicy::Drop,
+            DuplicatePolicy::Error
+        ),
+        Err(BuilderInsertError::MinorTooLow(0, 0))
+    );
+    // the cancelling duplicates at (1, 1) are summed to zero before zeros are dropped.
+    let summed = Csc::from_triplets_with_policy(
+        2,
+        2,
+        &mut dups.clone(),
+        ZeroPolicy::Drop,
+        DuplicatePolicy::Sum,
+    )
+    .unwrap();
+    assert_eq!(summed.nnz(), 1);
+    assert_eq!(summed.get(0, 0), Some(&3.));
+    let summed_kept =
+        Csc::from_triplets_with_policy(2, 2, &mut dups, ZeroPolicy::Keep, DuplicatePolicy::Sum)
+            .unwrap();
+    assert_eq!(summed_kept.get(1, 1), Some(&0.));
+    let last = Csc::from_triplets_with_policy(
+        2,
+        2,
+        &mut [([0, 0], 1.), ([0, 0], 0.)],
+        ZeroPolicy::Drop,
+        DuplicatePolicy::KeepLast,
+    )
+    .unwrap();
+    assert_eq!(last.nnz(), 0);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_norm_2_estimate() {
+    // the singular values of [[3, 0], [4, 5]] are 3√5 and √5.
+    let a = Csc::from(vec![vec![3., 0.], vec![4., 5.]]);
+    let sigma = (45. as F).sqrt();
+    let estimate = a.norm_2_estimate(20);
+    assert!((estimate - sigma).abs() < 1e-4, "{estimate} {sigma}");
+    assert!(a.norm_2_estimate(1) <= estimate + 1e-5);
+
+    // a rectangular diagonal matrix, whose norm is its largest entry
+    let d = Csc::from_triplets(4, 3, &mut [([0, 0], 1.), ([1, 1], -7.), ([2, 3], 2.)]).unwrap();
+    assert!((d.norm_2_estimate(50) - 7.).abs() < 1e-4);
+    assert_eq!(CscBuilder::<F>::new(3, 3).build().norm_2_estimate(5), 0.);
+
+    // rows which sum to zero, so all ones is in the null space.
+    let diff = Csc::from(vec![vec![1., -1.]]);
+    let estimate = diff.norm_2_estimate(10);
+    assert!((estimate - (2. as F).sqrt()).abs() < 1e-4, "{estimate}");
+    let mut t = vec![];
+    for i in 0..4 {
+        t.push(([i, i], -1.));
+        t.push(([i + 1, i], 1.));
+    }
+    let gradient = Csc::from_triplets(4, 5, &mut t).unwrap();
+    // the singular values of the difference operator are 2 sin(kπ/10).
+    let sigma = 2. * (0.4 * core::f64::consts::PI as F).sin();
+    let estimate = gradient.norm_2_estimate(100);
+    assert!((estimate - sigma).abs() < 1e-4, "{estimate} {sigma}");
+}
+
+#[test]
+fn test_builder_checkpoint() {
+    let mut builder = CscBuilder::new(4, 4);
+    builder.insert(0, 0, 1.).unwrap();
+    builder.insert(2, 1, 2.).unwrap();
+    let checkpoint = builder.checkpoint();
+    let expected = builder.clone().build();
+
+    // speculative entries, both in the current column and later ones
+    builder.insert(3, 1, 3.).unwrap();
+    builder.insert(1, 3, 4.).unwrap();
+    let later = builder.checkpoint();
+    builder.restore(checkpoint).unwrap();
+    assert_eq!(builder.clone().build(), expected);
+    assert_eq!(builder.restore(later), Err(RestoreError::Ahead(4, 2)));
+
+    // the builder continues from the checkpoint, where column 1 may still be extended
+    builder.insert(3, 1, 5.).unwrap();
+    let m = builder.build();
+    assert_eq!(m.nnz(), 3);
+    assert_eq!(m.get(3, 1), Some(&5.));
+    assert_eq!(m.get(1, 3), None);
+}
+
+#[test]
+fn test_add_scaled_identity() {
+    // the diagonal entries of columns 1 and 3 are structurally missing
+    let a = Csc::from_triplets(
+        4,
+        4,
+        &mut [
+            ([0, 0], 1.),
+            ([0, 2], 2.),
+            ([1, 0], 3.),
+            ([1, 3], 4.),
+            ([2, 2], 5.),
+            ([3, 0], 6.),
+        ],
+    )
+    .unwrap();
+    let shifted = a.add_scaled_identity(0.5);
+    assert_eq!(shifted.nnz(), a.nnz() + 2);
+    for i in 0..4 {
+        assert_eq!(
+            shifted.get(i, i).copied(),
+            Some(a.get(i, i).copied().unwrap_or(0.) + 0.5)
+        );
+    }
+    for ([c, r], v) in a.to_triplets() {
+        if r != c {
+            assert_eq!(shifted.get(r, c), Some(&v));
+        }
+    }
+    assert_eq!(shifted, a.add(&Csc::identity(4).map_values(|v| v * 0.5)));
+}
+
+#[test]
+fn test_without_row_column() {
+    let dense: Vec<Vec<F>> = vec![
+        vec![1., 0., 2., 0.],
+        vec![0., 3., 0., 4.],
+        vec![5., 0., 6., 0.],
+    ];
+    let a = Csc::from(dense.clone());
+    for col in 0..4 {
+        let mut expected = dense.clone();
+        for row in &mut expected {
+            row.remove(col);
+        }
+        let b = a.without_column(col);
+        assert_eq!(b.shape(), (3, 3));
+        assert_eq!(b, Csc::from(expected));
+    }
+    for row in 0..3 {
+        let mut expected = dense.clone();
+        expected.remove(row);
+        let b = a.without_row(row);
+        assert_eq!(b.shape(), (2, 4));
+        assert_eq!(b, Csc::from(expected));
+    }
+}
+
+#[test]
+fn test_fold_columns() {
+    let a = Csc::from(vec![
+        vec![1., 0., 2., 0.],
+        vec![0., -3., 0., 0.],
+        vec![5., 0., 6., 0.],
+    ]);
+    let sums = a.fold_columns(|| 0., |acc, _, &v| acc + v);
+    let direct = (0..a.ncols())
+        .map(|c| a.col_iter(c).map(|(_, v)| v).sum::<F>())
+        .collect::<Vec<_>>();
+    assert_eq!(sums, direct);
+    assert_eq!(sums, vec![6., -3., 8., 0.]);
+
+    // the accumulator can also track the rows of each column.
+    let rows = a.fold_columns(Vec::new, |mut rows, r, _| {
+        rows.push(r);
+        rows
+    });
+    assert_eq!(rows, vec![vec![0, 2], vec![1], vec![0, 2], vec![]]);
+}
+
+#[test]
+fn test_set_column() {
+    let mut a = Csc::from(vec![vec![1., 0., 2.], vec![0., 3., 0.], vec![4., 0., 5.]]);
+    let before = a.clone();
+    a.set_column(2, &[0, 2], &[7., 8.]).unwrap();
+    assert_eq!(a.get(0, 2), Some(&7.));
+    assert_eq!(a.get(2, 2), Some(&8.));
+    for c in 0..2 {
+        assert_eq!(a.col(c), before.col(c));
+    }
+    assert_eq!(a.pattern(), before.pattern());
+
+    // a different pattern is only accepted when replacing the column.
+    assert_eq!(
+        a.set_column(1, &[0, 1], &[1., 1.]),
+        Err(SetColumnError::PatternMismatch { col: 1 })
+    );
+    assert_eq!(a.col(1), before.col(1));
+    assert_eq!(
+        a.replace_column(1, &[1, 0], &[1., 1.]),
+        Err(SetColumnError::InvalidRows { col: 1 })
+    );
+    a.replace_column(1, &[0, 2], &[9., 10.]).unwrap();
+    assert_eq!(
+        a,
+        Csc::from(vec![vec![1., 9., 7.], vec![0., 0., 0.], vec![4., 10., 8.]])
+    );
+}
+
+#[test]
+fn test_assert_structurally_symmetric() {
+    let mut t = vec![
+        ([0, 0], 4.),
+        ([0, 1], 1.),
+        ([1, 0], 2.),
+        ([1, 1], 4.),
+        ([1, 2], 1.),
+        ([2, 2], 4.),
+    ];
+    // (2, 1) is stored, but (1, 2) is not.
+    let nearly = Csc::from_triplets(3, 3, &mut t.clone()).unwrap();
+    assert_eq!(nearly.assert_structurally_symmetric(), Err((2, 1)));
+    assert!(!nearly.is_structurally_symmetric());
+
+    // values do not need to match, only positions.
+    t.push(([2, 1], 0.));
+    let sym = Csc::from_triplets(3, 3, &mut t).unwrap();
+    assert_eq!(sym.assert_structurally_symmetric(), Ok(()));
+    assert!(sym.is_structurally_symmetric());
+}
+
+#[test]
+fn test_from_fn() {
+    let n = 6;
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 2. + i as F));
+        if i > 0 {
+            t.push(([i, i - 1], -(i as F)));
+            t.push(([i - 1, i], -(i as F)));
+        }
+    }
+    let expected = Csc::from_triplets(n, n, &mut t).unwrap();
+    let a = Csc::from_fn(n, n, expected.pattern(), |r, c| {
+        if r == c {
+            2. + r as F
+        } else {
+            -(r.max(c) as F)
+        }
+    });
+    assert_eq!(a, expected);
+}
+
+#[test]
+fn test_reordered_bandwidth() {
+    // a path graph numbered out of order, which has a small bandwidth in path order.
+    let order = [3, 0, 5, 1, 4, 2];
+    let mut t = vec![];
+    for w in order.windows(2) {
+        t.push(([w[0], w[1]], 1.));
+        t.push(([w[1], w[0]], 2.));
+    }
+    t.push(([0, 0], 4.));
+    let a = Csc::from_triplets(6, 6, &mut t).unwrap();
+    assert_eq!(a.bandwidth(), (5, 5));
+
+    assert_eq!(a.reordered_bandwidth(&order), (1, 1));
+    for perm in [
+        order,
+        [0, 1, 2, 3, 4, 5],
+        [5, 4, 3, 2, 1, 0],
+        [2, 0, 4, 1, 5, 3],
+    ] {
+        assert_eq!(
+            a.reordered_bandwidth(&perm),
+            a.permute_symmetric(&perm).bandwidth()
+        );
+    }
+}
+
+#[test]
+fn test_triangular_solve_is_safe() {
+    // lower triangular, with the diagonal entry of row 2 missing.
+    let mut t = vec![
+        ([0, 0], 2.),
+        ([0, 2], 1.),
+        ([1, 1], 3.),
+        ([1, 2], 1.),
+        ([2, 3], 1.),
+        ([3, 3], 4.),
+    ];
+    let a = Csc::from_triplets(4, 4, &mut t).unwrap();
+    assert_eq!(a.triangular_solve_is_safe(true, false), Err(2));
+    assert_eq!(a.triangular_solve_is_safe(true, true), Ok(()));
+    assert_eq!(a.triangular_solve_is_safe(false, false), Err(2));
+
+    // a non-finite entry is only a problem in the triangle being solved with.
+    t.push(([2, 2], 1.));
+    t.push(([3, 0], F::NAN));
+    let b = Csc::from_triplets(4, 4, &mut t).unwrap();
+    assert_eq!(b.triangular_solve_is_safe(true, false), Ok(()));
+    assert_eq!(b.triangular_solve_is_safe(false, false), Err(0));
+
+    // a tiny diagonal is accepted, and the solves it approves do not panic.
+    let tiny = Csc::from(vec![vec![1e-9, 1.], vec![0., 1e-9]]);
+    assert_eq!(tiny.triangular_solve_is_safe(false, false), Ok(()));
+    let mut x = [0.; 2];
+    tiny.dense_upper_triangular_solve(&[1e-9, 1e-9], &mut x);
+    assert_eq!(x[1], 1.);
+    assert!((x[0] / (1. - 1e9) - 1.).abs() < 1e-5, "{x:?}");
+    let tiny_lower = tiny.transpose();
+    assert_eq!(tiny_lower.triangular_solve_is_safe(true, false), Ok(()));
+    tiny_lower.dense_lower_triangular_solve(&[1e-9, 1e-9], &mut x, false);
+    assert_eq!(x[0], 1.);
+    assert!((x[1] / (1. - 1e9) - 1.).abs() < 1e-5, "{x:?}");
+}
+
+#[test]
+fn test_solve_pattern() {
+    // a positive diagonal with negative off-diagonal entries, so that nothing cancels.
+    let n = 8;
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 2.));
+    }
+    for [c, r] in [[0, 3], [1, 4], [3, 6], [4, 5], [2, 7]] {
+        t.push(([c, r], -1.));
+        t.push(([r, c], -0.5));
+    }
+    let a = Csc::from_triplets(n, n, &mut t).unwrap();
+
+    for b_idxs in [vec![0], vec![1, 3], vec![6], vec![2, 5]] {
+        let mut b = vec![0.; n];
+        for &i in &b_idxs {
+            b[i] = 1.;
+        }
+        let mut x = vec![0.; n];
+        a.dense_lower_triangular_solve(&b, &mut x, false);
+        let nonzeros = (0..n).filter(|&i| x[i] != 0.).collect::<Vec<_>>();
+        assert_eq!(a.solve_pattern(&b_idxs, true), nonzeros);
+
+        a.dense_upper_triangular_solve(&b, &mut x);
+        let nonzeros = (0..n).filter(|&i| x[i] != 0.).collect::<Vec<_>>();
+        assert_eq!(a.solve_pattern(&b_idxs, false), nonzeros);
+    }
+    assert_eq!(a.solve_pattern(&[0], true), vec![0, 3, 6]);
+
+    // a long chain of dependencies, which reaches every index.
+    let n = 200_000;
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 1.));
+        if i + 1 < n {
+            t.push(([i, i + 1], 1.));
+            t.push(([i + 1, i], 1.));
+        }
+    }
+    let chain = Csc::from_triplets(n, n, &mut t).unwrap();
+    assert_eq!(chain.solve_pattern(&[0], true).len(), n);
+    assert_eq!(chain.solve_pattern(&[n - 1], false).len(), n);
+    let mut reach = vec![];
+    chain
+        .pattern()
+        .sparse_lower_triangular_solve(&[0], &mut reach);
+    assert_eq!(reach.len(), n);
+    chain
+        .pattern()
+        .sparse_upper_triangular_solve(&[n - 1], &mut reach);
+    assert_eq!(reach.len(), n);
+}
+
+#[test]
+fn test_duplicate_policy() {
+    let t = [
+        ([1, 0], 5.),
+        ([0, 0], 1.),
+        ([1, 0], 2.),
+        ([0, 1], 3.),
+        ([1, 0], 4.),
+    ];
+    let build =
+        |policy| Csc::from_triplets_with_policy(2, 2, &mut t.clone(), ZeroPolicy::Keep, policy);
+    assert_eq!(
+        build(DuplicatePolicy::Error),
+        Err(BuilderInsertError::MinorTooLow(0, 0))
+    );
+    let expected = |v| Csc::from(vec![vec![1., v], vec![3., 0.]]);
+    assert_eq!(build(DuplicatePolicy::Sum), Ok(expected(11.)));
+    assert_eq!(build(DuplicatePolicy::KeepFirst), Ok(expected(5.)));
+    assert_eq!(build(DuplicatePolicy::KeepLast), Ok(expected(4.)));
+
+    // the unordered builder keeps the insertion order for each policy.
+    for (policy, v) in [
+        (DuplicatePolicy::Sum, 11.),
+        (DuplicatePolicy::KeepFirst, 5.),
+        (DuplicatePolicy::KeepLast, 4.),
+    ] {
+        let mut builder = UnorderedCscBuilder::new(2, 2);
+        for &([c, r], v) in &t {
+            builder.insert(r, c, v);
+        }
+        assert_eq!(builder.build_with_duplicates(policy), Ok(expected(v)));
+    }
+
+    // the deprecated constructors are wrappers which keep zeros.
+    #[allow(deprecated)]
+    {
+        assert_eq!(
+            Csc::from_triplets_summed(2, 2, &mut t.clone(), |a, b| a + b),
+            Ok(expected(11.))
+        );
+        assert_eq!(
+            Csc::from_triplets_with_duplicates(2, 2, &mut t.clone(), DuplicatePolicy::KeepLast),
+            Ok(expected(4.))
+        );
+    }
+}
+
+#[test]
+fn test_diagonal_solve() {
+    let a = Csc::from(vec![vec![2., 1., 0.], vec![0., -4., 0.], vec![3., 0., 0.5]]);
+    let b = [1., 2., 3.];
+    let mut x = [0.; 3];
+    a.diagonal_solve(&b, &mut x).unwrap();
+    assert_eq!(x, [0.5, -0.5, 6.]);
+
+    let missing = Csc::from(vec![vec![2., 1., 0.], vec![1., 0., 1.], vec![0., 1., 2.]]);
+    assert_eq!(missing.diagonal_solve(&b, &mut x), Err(1));
+    let zero = Csc::from_rows(
+        &[vec![2., 0., 0.], vec![0., 1., 0.], vec![0., 0., 0.]],
+        ZeroPolicy::Keep,
+    );
+    assert_eq!(zero.diagonal_solve(&b, &mut x), Err(2));
+}
+
+#[test]
+fn test_vecmul_sparse() {
+    let a = Csc::from(vec![
+        vec![1., 0., 2., 0., 1.],
+        vec![0., 3., 0., 4., 0.],
+        vec![5., 0., 6., 0., -1.],
+    ]);
+    let x_idx = [4, 0, 3];
+    let x_val = [2., -1., 0.5];
+    let mut dense = vec![0.; 5];
+    for (&i, &v) in x_idx.iter().zip(&x_val) {
+        dense[i] = v;
+    }
+    let mut out = vec![7.; 3];
+    a.vecmul_sparse(&x_idx, &x_val, &mut out);
+    assert_eq!(out, a.vecmul(&dense));
+}
+
+#[test]
+fn test_symmetric_skew_parts() {
+    let a = Csc::from(vec![vec![1., 2., 0.], vec![4., 3., -1.], vec![0., 5., 2.]]);
+    let sym = a.symmetric_part();
+    let skew = a.skew_part();
+    assert_eq!(sym, sym.transpose());
+    assert_eq!(skew, skew.transpose().map_values(|v| -v));
+    let sum = sym.add(&skew);
+    for r in 0..3 {
+        for c in 0..3 {
+            let expected = a.get(r, c).copied().unwrap_or(0.);
+            assert_eq!(sum.get(r, c).copied().unwrap_or(0.), expected);
+        }
+    }
+    assert_eq!(skew.get(1, 0), Some(&1.));
+
+    let symmetric = a.symmetrize();
+    assert!(symmetric.skew_part().values().iter().all(|&v| v == 0.));
+}
+
+#[test]
+fn test_sub() {
+    let a = Csc::from(vec![vec![1., 2., 0.], vec![0., 3., 0.], vec![4., 0., 5.]]);
+    let overlapping = Csc::from(vec![vec![1., 0., 1.], vec![0., 1., 0.], vec![2., 0., 0.]]);
+    let disjoint = Csc::from(vec![vec![0., 0., 7.], vec![6., 0., 0.], vec![0., 8., 0.]]);
+    for b in [&overlapping, &disjoint] {
+        let diff = a.sub(b);
+        assert_eq!(diff.pattern(), a.add(b).pattern());
+        for r in 0..3 {
+            for c in 0..3 {
+                let x = a.get(r, c).copied().unwrap_or(0.);
+                let y = b.get(r, c).copied().unwrap_or(0.);
+                assert_eq!(diff.get(r, c).copied().unwrap_or(0.), x - y);
+            }
+        }
+    }
+    assert_eq!(
+        a.sub(&overlapping).get(0, 0),
+        Some(&0.),
+        "cancelled entries are kept"
+    );
+
+    let zero = a.sub(&a);
+    assert_eq!(zero.pattern(), a.pattern());
+    assert!(zero.values().iter().all(|&v| v == 0.));
+}
+
+#[test]
+fn test_try_from_csc_arrays() {
+    let a = Csc::from(vec![vec![1., 0., 2.], vec![0., 3., 0.]]);
+    let m =
+        Csc::try_from_csc_arrays(2, 3, vec![0, 1, 2, 3], vec![0, 1, 0], vec![1., 3., 2.]).unwrap();
+    assert_eq!(m, a);
+    let empty = Csc::try_from_csc_arrays(4, 2, vec![0, 0, 0], vec![], vec![]).unwrap();
+    assert_eq!(empty.nnz(), 0);
+
+    assert_eq!(
+        Csc::try_from_csc_arrays(2, 3, vec![0, 1, 2], vec![0, 1], vec![1., 3.]).unwrap_err(),
+        PatternError::OffsetsLength {
+            expected: 4,
+            found: 3
+        }
+    );
+    assert_eq!(
+        Csc::try_from_csc_arrays(2, 2, vec![0, 2, 1], vec![0, 1], vec![1., 3.]).unwrap_err(),
+        PatternError::InvalidOffsets { major: 1 }
+    );
+    assert_eq!(
+        Csc::try_from_csc_arrays(2, 2, vec![0, 2, 2], vec![1, 0], vec![1., 3.]).unwrap_err(),
+        PatternError::UnsortedIndices { major: 0 }
+    );
+    assert_eq!(
+        Csc::try_from_csc_arrays(2, 2, vec![0, 1, 2], vec![0, 2], vec![1., 3.]).unwrap_err(),
+        PatternError::IndexOutOfRange { major: 1, minor: 2 }
+    );
+    assert_eq!(
+        Csc::try_from_csc_arrays(2, 2, vec![0, 1, 2], vec![0, 1], vec![1.]).unwrap_err(),
+        PatternError::ValuesLength {
+            indices: 2,
+            values: 1
+        }
+    );
+    assert_eq!(
+        Csc::try_from_csc_arrays(2, 1, vec![1, 2], vec![0, 1], vec![1., 3.]).unwrap_err(),
+        PatternError::InvalidOffsets { major: 0 }
+    );
+    // without any columns, entries could not belong to any of them.
+    assert_eq!(
+        Csc::try_from_csc_arrays(3, 0, vec![3], vec![0, 1, 2], vec![1., 2., 3.]).unwrap_err(),
+        PatternError::InvalidOffsets { major: 0 }
+    );
+    assert_eq!(
+        Csc::<F>::try_from_csc_arrays(3, 0, vec![0], vec![], vec![])
+            .unwrap()
+            .nnz(),
+        0
+    );
+}
@@ -0,0 +1,31 @@
+use sparse_lu::{Csc, F};
+
+#[test]
+fn test_csr_round_trip() {
+    let rows = vec![
+        vec![1., 0., 2., 0.],
+        vec![0., 0., 0., 0.],
+        vec![3., 4., 0., 5.],
+    ];
+    let a = Csc::from(rows.clone());
+    let csr = a.to_csr();
+    assert_eq!(csr.nrows(), 3);
+    assert_eq!(csr.ncols(), 4);
+    assert_eq!(csr.nnz(), a.nnz());
+    for (i, row) in rows.iter().enumerate() {
+        let expected = row
+            .iter()
+            .enumerate()
+            .filter(|&(_, &v)| v != 0.)
+            .map(|(c, &v)| (c, v))
+            .collect::<Vec<_>>();
+        let got = csr.row_iter(i).map(|(c, &v)| (c, v)).collect::<Vec<_>>();
+        assert_eq!(got, expected);
+    }
+
+    let x = [1., -1., 2., 0.5];
+    assert_eq!(csr.vecmul(&x), a.vecmul(&x));
+    assert_eq!(csr.to_csc(), a);
+    let empty = Csc::<F>::from_triplets(2, 3, &mut []).unwrap();
+    assert_eq!(empty.to_csr().to_csc(), empty);
+}
@@ -0,0 +1,50 @@
+use sparse_lu::{Csc, F, I, LeftLookingLUFactorization};
+
+#[test]
+fn test_index_type() {
+    #[cfg(feature = "u32")]
+    assert_eq!(std::mem::size_of::<I>(), 4);
+    #[cfg(not(feature = "u32"))]
+    assert_eq!(std::mem::size_of::<I>(), std::mem::size_of::<usize>());
+
+    let a = Csc::from(vec![vec![4., 1., 0.], vec![2., 5., 1.], vec![0., 3., 6.]]);
+    let rows: &[I] = a.col(1).1;
+    assert_eq!(rows, &[0, 1, 2]);
+    assert_eq!(a.pattern().lane(2), &[1, 2]);
+
+    let lu = LeftLookingLUFactorization::new(&a);
+    let b = [1., 2., 3.];
+    let mut x = b;
+    let mut buf = [0.; 3];
+    lu.solve(&mut x, &mut buf);
+    let mut r = [0.; 3];
+    a.residual(&x, &b, &mut r);
+    assert!(r.iter().all(|r: &F| r.abs() < 1e-5), "{r:?}");
+}
+
+#[test]
+fn test_index_out_of_range() {
+    // a row which wraps to 0 if truncated to 32 bits.
+    let a = Csc::from(vec![vec![4., 1.], vec![2., 5.]]);
+    assert_eq!(a.get(1 << 32, 0), None);
+}
+
+#[cfg(feature = "u32")]
+#[test]
+#[should_panic(expected = "index exceeds the range of u32")]
+fn test_index_overflow() {
+    use sparse_lu::csc::CscBuilder;
+    let mut builder = CscBuilder::new(1 << 33, 1);
+    let _ = builder.insert(1 << 32, 0, 1.);
+}
+
+#[cfg(feature = "u32")]
+#[test]
+fn test_csc_arrays_overflow() {
+    use sparse_lu::PatternError;
+    let rows = 1 << 32;
+    assert_eq!(
+        Csc::try_from_csc_arrays(rows, 1, vec![0, 1], vec![rows - 1], vec![1.]).unwrap_err(),
+        PatternError::IndexTypeOverflow { value: rows }
+    );
+}
@@ -0,0 +1,303 @@
+#![cfg(feature = "std")]
+use sparse_lu::{
+    Csc, DuplicatePolicy, F, IluFactorization, Jacobi, LeftLookingLUFactorization, LinearOperator,
+    Preconditioner, SolveKind, SymmetricCsc, ZeroPolicy, cg, lsmr, pcg,
+};
+
+fn norm(v: &[F]) -> F {
+    v.iter().map(|v| v * v).sum::<F>().sqrt()
+}
+
+/// ||Aᵀ(b - Ax)||
+fn normal_residual(a: &Csc<F>, b: &[F], x: &[F]) -> F {
+    let ax = a.vecmul(x);
+    let r = b.iter().zip(ax).map(|(b, ax)| b - ax).collect::<Vec<_>>();
+    norm(&a.vecmul_transpose(&r))
+}
+
+/// Conjugate gradient on the normal equations `AᵀA x = Aᵀb`.
+fn cgnr(a: &Csc<F>, b: &[F], iters: usize) -> Vec<F> {
+    let mut x = vec![0.; a.ncols()];
+    let mut r = a.vecmul_transpose(b);
+    let mut p = r.clone();
+    let mut rr = r.iter().map(|r| r * r).sum::<F>();
+    for _ in 0..iters {
+        let ap = a.vecmul(&p);
+        let atap = a.vecmul_transpose(&ap);
+        let alpha = rr / ap.iter().map(|v| v * v).sum::<F>();
+        for i in 0..x.len() {
+            x[i] += alpha * p[i];
+            r[i] -= alpha * atap[i];
+        }
+        let rr_new = r.iter().map(|r| r * r).sum::<F>();
+        for i in 0..p.len() {
+            p[i] = r[i] + (rr_new / rr) * p[i];
+        }
+        rr = rr_new;
+    }
+    x
+}
+
+/// A tall matrix whose columns have geometrically decaying scale.
+fn ill_conditioned(rows: usize, cols: usize) -> Csc<F> {
+    let mut t = vec![];
+    for c in 0..cols {
+        let scale = (0.5 as F).powi(c as i32);
+        t.push(([c, c], scale));
+        t.push(([c, c + 1], 0.5 * scale));
+        t.push(([c, rows - 1 - c], 0.25 * scale));
+    }
+    Csc::from_triplets_with_policy(rows, cols, &mut t, ZeroPolicy::Keep, DuplicatePolicy::Sum)
+        .unwrap()
+}
+
+#[test]
+fn test_lsmr_consistent() {
+    let a = ill_conditioned(10, 6);
+    let x_true = [1., -2., 3., 0.5, -1., 2.];
+    let b = a.vecmul(&x_true);
+    let mut x = vec![0.; 6];
+    let (res, norm_r) = a.lsmr_solve(&b, &mut x, 100, 1e-6, 0.);
+    assert!(res.converged);
+    assert!(norm_r < 1e-3, "{norm_r}");
+    for (x, xt) in x.iter().zip(x_true) {
+        assert!((x - xt).abs() < 1e-2, "{x} {xt}");
+    }
+}
+
+#[test]
+fn test_lsmr_smaller_residual_than_normal_equations() {
+    let a = ill_conditioned(12, 8);
+    let b = (0..12).map(|i| (i as F).sin() + 1.).collect::<Vec<_>>();
+
+    for iters in 2..6 {
+        let mut x = vec![0.; 8];
+        let (res, _) = a.lsmr_solve(&b, &mut x, iters, 0., 0.);
+        assert_eq!(res.iterations, iters);
+        let lsmr = normal_residual(&a, &b, &x);
+        let cg = normal_residual(&a, &b, &cgnr(&a, &b, iters));
+        assert!(lsmr <= cg, "iters={iters}: {lsmr} > {cg}");
+        assert!((res.residual_norm - lsmr).abs() < 1e-3 * lsmr.max(1.));
+    }
+}
+
+/// The 2D Laplacian on a `k × k` grid, shifted by `shift` along the diagonal.
+fn laplacian_2d(k: usize, shift: F) -> Csc<F> {
+    let n = k * k;
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 4. + shift));
+        if i % k + 1 < k {
+            t.push(([i, i + 1], -1.));
+            t.push(([i + 1, i], -1.));
+        }
+        if i + k < n {
+            t.push(([i, i + k], -1.));
+            t.push(([i + k, i], -1.));
+        }
+    }
+    Csc::from_triplets(n, n, &mut t).unwrap()
+}
+
+#[test]
+fn test_cg() {
+    let a = laplacian_2d(6, 0.);
+    let x_true = (0..36).map(|i| (i as F * 0.3).cos()).collect::<Vec<_>>();
+    let b = a.vecmul(&x_true);
+    let mut x = vec![0.; 36];
+    let res = a.cg_solve(&b, &mut x, 100, 1e-6);
+    assert!(res.converged);
+    assert!(res.residual_norm <= 1e-6 * norm(&b));
+    for (x, xt) in x.iter().zip(x_true) {
+        assert!((x - xt).abs() < 1e-3, "{x} {xt}");
+    }
+
+    // pᵀAp is zero on the first step, for both an indefinite and a singular matrix.
+    for (a, b) in [
+        (Csc::from(vec![vec![1., 0.], vec![0., -1.]]), [1., 1.]),
+        (Csc::from(vec![vec![1., 0.], vec![0., 0.]]), [0., 1.]),
+    ] {
+        let mut x = vec![0.; 2];
+        let res = a.cg_solve(&b, &mut x, 10, 1e-6);
+        assert!(!res.converged);
+        assert!(!res.residual_norm.is_finite(), "{res:?}");
+    }
+}
+
+#[test]
+fn test_cg_warm_start_from_stale_lu() {
+    let stale = laplacian_2d(8, 0.);
+    let a = laplacian_2d(8, 0.05);
+    let lu = LeftLookingLUFactorization::new(&stale);
+    let b = (0..64).map(|i| (i % 7) as F - 3.).collect::<Vec<_>>();
+
+    let mut cold = vec![0.; 64];
+    let cold_res = a.cg_solve(&b, &mut cold, 200, 1e-5);
+    let mut warm = lu.approximate_solve(&b);
+    let warm_res = a.cg_solve(&b, &mut warm, 200, 1e-5);
+    assert!(cold_res.converged && warm_res.converged);
+    assert!(
+        warm_res.iterations < cold_res.iterations,
+        "{} {}",
+        warm_res.iterations,
+        cold_res.iterations
+    );
+}
+
+/// The 1D Laplacian applied by a closure, without storing a matrix.
+struct Closure<A: Fn(&[F], &mut [F])>(usize, A);
+
+impl<A: Fn(&[F], &mut [F])> LinearOperator for Closure<A> {
+    fn nrows(&self) -> usize {
+        self.0
+    }
+    fn ncols(&self) -> usize {
+        self.0
+    }
+    fn matvec(&self, x: &[F], out: &mut [F]) {
+        (self.1)(x, out)
+    }
+}
+
+#[test]
+fn test_cg_matrix_free() {
+    let n = 20;
+    let op = Closure(n, |x: &[F], out: &mut [F]| {
+        for i in 0..x.len() {
+            out[i] = 2. * x[i];
+            if i > 0 {
+                out[i] -= x[i - 1];
+            }
+            if i + 1 < x.len() {
+                out[i] -= x[i + 1];
+            }
+        }
+    });
+    let x_true = (0..n).map(|i| (i as F * 0.4).sin()).collect::<Vec<_>>();
+    let mut b = vec![0.; n];
+    op.matvec(&x_true, &mut b);
+
+    let mut x = vec![0.; n];
+    let res = cg(&op, &b, &mut x, 100, 1e-6);
+    assert!(res.converged);
+    for (x, xt) in x.iter().zip(&x_true) {
+        assert!((x - xt).abs() < 1e-3, "{x} {xt}");
+    }
+
+    // the same system through the stored matrix and the symmetric storage
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 2.));
+        if i > 0 {
+            t.push(([i, i - 1], -1.));
+            t.push(([i - 1, i], -1.));
+        }
+    }
+    let a = Csc::from_triplets(n, n, &mut t).unwrap();
+    let mut y = vec![0.; n];
+    assert_eq!(cg(&a, &b, &mut y, 100, 1e-6), res);
+    assert_eq!(x, y);
+    let sym = SymmetricCsc::from_full(&a);
+    let mut z = vec![0.; n];
+    let (lsmr_res, _) = lsmr(&sym, &b, &mut z, 100, 1e-6, 0.);
+    assert!(lsmr_res.converged);
+    for (z, xt) in z.iter().zip(&x_true) {
+        assert!((z - xt).abs() < 1e-3, "{z} {xt}");
+    }
+}
+
+#[test]
+fn test_solve_or_least_squares() {
+    let a = laplacian_2d(3, 0.);
+    let b = (0..9).map(|i| i as F).collect::<Vec<_>>();
+    let mut x = vec![0.; 9];
+    assert_eq!(a.solve_or_least_squares(&b, &mut x), SolveKind::Direct);
+    let ax = a.vecmul(&x);
+    for (ax, b) in ax.iter().zip(&b) {
+        assert!((ax - b).abs() < 1e-4);
+    }
+
+    // rank 2, where the last row is the sum of the first two.
+    let singular = Csc::from(vec![vec![1., 2., 0.], vec![0., 1., 1.], vec![1., 3., 1.]]);
+    let consistent = singular.vecmul(&[1., 1., 1.]);
+    let mut x = vec![0.; 3];
+    let SolveKind::LeastSquares(res, norm_r) = singular.solve_or_least_squares(&consistent, &mut x)
+    else {
+        panic!("singular matrix was solved directly");
+    };
+    assert!(res.converged);
+    assert!(norm_r < 1e-3, "{norm_r}");
+    for (ax, b) in singular.vecmul(&x).iter().zip(&consistent) {
+        assert!((ax - b).abs() < 1e-3, "{ax} {b}");
+    }
+
+    let inconsistent = [1., 1., 0.];
+    let mut x = vec![0.; 3];
+    let SolveKind::LeastSquares(res, norm_r) =
+        singular.solve_or_least_squares(&inconsistent, &mut x)
+    else {
+        panic!("singular matrix was solved directly");
+    };
+    assert!(res.converged);
+    assert!(norm_r > 0.1, "{norm_r}");
+    assert!(normal_residual(&singular, &inconsistent, &x) < 1e-3);
+
+    // only singular up to rounding, which the pivot tolerance rejects.
+    let rounded = Csc::from(vec![
+        vec![0.1, 0.2, 0.3],
+        vec![0.4, 0.5, 0.6],
+        vec![0.7, 0.8, 0.9],
+    ]);
+    let b = rounded.vecmul(&[1., 1., 1.]);
+    let mut x = vec![0.; 3];
+    let SolveKind::LeastSquares(res, norm_r) = rounded.solve_or_least_squares(&b, &mut x) else {
+        panic!("numerically singular matrix was solved directly");
+    };
+    assert!(res.converged);
+    assert!(norm_r < 1e-3, "{norm_r}");
+    // [1, 1, 1] is orthogonal to the null space [1, -2, 1], so it is the minimum norm solution.
+    for v in &x {
+        assert!((v - 1.).abs() < 1e-2, "{x:?}");
+    }
+}
+
+#[test]
+fn test_pcg() {
+    // a badly scaled SPD matrix, which Jacobi preconditioning corrects.
+    let n = 64;
+    let lap = laplacian_2d(8, 0.1);
+    let scale = (0..n).map(|i| 1. + (i % 5) as F * 3.).collect::<Vec<_>>();
+    let a = lap.scale_symmetric(&scale);
+    let x_true = (0..n).map(|i| (i as F * 0.2).sin()).collect::<Vec<_>>();
+    let b = a.vecmul(&x_true);
+
+    let mut x = vec![0.; n];
+    let plain = cg(&a, &b, &mut x, 500, 1e-5);
+    assert!(plain.converged);
+
+    for m in [
+        &Jacobi::new(&a).unwrap() as &dyn Preconditioner,
+        &IluFactorization::new(&a).unwrap(),
+        &LeftLookingLUFactorization::new(&a),
+    ] {
+        let mut x = vec![0.; n];
+        let res = pcg(&a, m, &b, &mut x, 500, 1e-5);
+        assert!(res.converged);
+        assert!(res.iterations < plain.iterations, "{res:?} {plain:?}");
+        for (x, xt) in x.iter().zip(&x_true) {
+            assert!((x - xt).abs() < 1e-2, "{x} {xt}");
+        }
+    }
+
+    // a singular preconditioner, which makes `rᵀz` zero.
+    struct Zero;
+    impl Preconditioner for Zero {
+        fn apply(&self, _: &[F], z: &mut [F], _: &mut [F]) {
+            z.fill(0.);
+        }
+    }
+    let mut x = vec![0.; n];
+    let res = pcg(&a, &Zero, &b, &mut x, 500, 1e-5);
+    assert!(!res.converged);
+    assert!(!res.residual_norm.is_finite(), "{res:?}");
+}
@@ -1,15 +1,26 @@
-#![feature(assert_matches)]
-use std::assert_matches::assert_matches;
-
-use sparse_lu::LeftLookingLUFactorization;
 use sparse_lu::csc::{Csc, CscBuilder};
+use sparse_lu::{
+    F, HybridLu, IluFactorization, LeftLookingLUFactorization, LuError, LuScratch, PivotStrategy,
+};
+
+fn assert_solves(a: &Csc<F>, lu_fact: &LeftLookingLUFactorization<F>) {
+    let n = a.ncols();
+    let mut buf = vec![0.; n];
+    let og = (0..n).map(|i| i as F + 1.).collect::<Vec<_>>();
+    let mut out = og.clone();
+    lu_fact.solve(&mut out, &mut buf);
+    let solved = a.vecmul(&out);
+    for i in 0..n {
+        assert!((solved[i] - og[i]).abs() < 1e-4, "{solved:?} {og:?}");
+    }
+}
 
 #[test]
 fn test_basic_lu_factorization() {
     let n = 5;
     let mut a = CscBuilder::new(n, n);
     for i in 0..n {
-        assert_matches!(a.insert(i, i, 1.), Ok(_));
+        assert!(a.insert(i, i, 1.).is_ok());
     }
     // construct an identity matrix as a basic test
     let a = a.build();
@@ -26,7 +37,7 @@ fn test_basic_lu_factorization_with_one_more_entry() {
     for i in 0..n {
         assert!(a.insert(i, i, if i == 0 { 1. } else { 0.5 }).is_ok());
         if i == 0 {
-            assert_matches!(a.insert(1, 0, 2.), Ok(_));
+            assert!(a.insert(1, 0, 2.).is_ok());
         }
     }
     // construct an identity matrix as a basic test
@@ -136,3 +147,1073 @@ pub fn test_lu_fact_dense() {
         assert!((solved[i] - og[i]).abs() < 1e-5, "{:?}", solved);
     }
 }
+
+#[test]
+pub fn test_pivot_strategies() {
+    // [1 2]
+    // [2 1]
+    let a = Csc::from_triplets(
+        2,
+        2,
+        &mut [([0, 0], 1.), ([0, 1], 2.), ([1, 0], 2.), ([1, 1], 1.)],
+    )
+    .unwrap();
+
+    let partial = LeftLookingLUFactorization::new_with_strategy(&a, PivotStrategy::Partial);
+    assert_eq!(partial.pivot(), &[1, 0]);
+    assert_eq!(partial.lu(), LeftLookingLUFactorization::new(&a).lu());
+    assert_solves(&a, &partial);
+
+    // the diagonal is within the threshold, so it is kept
+    let loose = LeftLookingLUFactorization::new_with_strategy(&a, PivotStrategy::Threshold(0.1));
+    assert_eq!(loose.pivot(), &[0, 1]);
+    assert_solves(&a, &loose);
+    let strict = LeftLookingLUFactorization::new_with_strategy(&a, PivotStrategy::Threshold(0.9));
+    assert_eq!(strict.pivot(), &[1, 0]);
+    assert_solves(&a, &strict);
+
+    let none = LeftLookingLUFactorization::new_with_strategy(&a, PivotStrategy::None);
+    assert_eq!(none.pivot(), &[0, 1]);
+    assert_solves(&a, &none);
+
+    let diag = LeftLookingLUFactorization::new_with_strategy(&a, PivotStrategy::Diagonal);
+    assert_eq!(diag.pivot(), &[0, 1]);
+    assert_solves(&a, &diag);
+}
+
+#[test]
+pub fn test_no_pivoting_zero_diagonal() {
+    // [0 1 0]
+    // [1 0 0]
+    // [2 0 1]
+    let a = Csc::from_triplets(
+        3,
+        3,
+        &mut [([0, 1], 1.), ([0, 2], 2.), ([1, 0], 1.), ([2, 2], 1.)],
+    )
+    .unwrap();
+    // uses the first nonzero below the diagonal, not the largest.
+    let none = LeftLookingLUFactorization::new_with_strategy(&a, PivotStrategy::None);
+    assert_eq!(none.pivot(), &[1, 0, 2]);
+    assert_solves(&a, &none);
+}
+
+#[test]
+#[should_panic]
+pub fn test_diagonal_pivoting_zero_diagonal() {
+    let a = Csc::from_triplets(2, 2, &mut [([0, 1], 1.), ([1, 0], 1.)]).unwrap();
+    LeftLookingLUFactorization::new_with_strategy(&a, PivotStrategy::Diagonal);
+}
+
+/// An arrowhead matrix with a dominant diagonal, and a dense row and column at `hub`.
+fn arrowhead(n: usize, hub: usize) -> Csc<F> {
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 10.));
+        if i != hub {
+            t.push(([i, hub], 1.));
+            t.push(([hub, i], 1.));
+        }
+    }
+    Csc::from_triplets(n, n, &mut t).unwrap()
+}
+
+#[test]
+pub fn test_fill_ratio() {
+    let n = 6;
+    // a dense first row and column fills in the entire matrix
+    let first = arrowhead(n, 0);
+    let lu_first = LeftLookingLUFactorization::new(&first);
+    assert_eq!(lu_first.lu().nnz(), n * n);
+    assert_eq!(
+        lu_first.fill_ratio(&first),
+        (n * n) as f64 / first.nnz() as f64
+    );
+
+    // reordering it to be last introduces no fill
+    let last = arrowhead(n, n - 1);
+    let lu_last = LeftLookingLUFactorization::new(&last);
+    assert_eq!(lu_last.lu().nnz(), last.nnz());
+    assert_eq!(lu_last.fill_ratio(&last), 1.);
+    assert!(lu_last.fill_ratio(&last) < lu_first.fill_ratio(&first));
+
+    let empty = Csc::<F>::from_triplets(0, 0, &mut []).unwrap();
+    assert_eq!(
+        LeftLookingLUFactorization::new(&empty).fill_ratio(&empty),
+        1.
+    );
+}
+
+#[test]
+pub fn test_fill_entries() {
+    let n = 5;
+    // eliminating the hub first fills in every position of the trailing block
+    let first = arrowhead(n, 0);
+    let lu_first = LeftLookingLUFactorization::new(&first);
+    assert_eq!(lu_first.pivot(), &[0, 1, 2, 3, 4]);
+    let fill = lu_first.fill_entries(&first);
+    let expected = (1..n)
+        .flat_map(|c| (1..n).filter(move |&r| r != c).map(move |r| (r, c)))
+        .collect::<Vec<_>>();
+    assert_eq!(fill, expected);
+    assert_eq!(fill.len(), lu_first.lu().nnz() - first.nnz());
+
+    let last = arrowhead(n, n - 1);
+    let lu_last = LeftLookingLUFactorization::new(&last);
+    assert!(lu_last.fill_entries(&last).is_empty());
+}
+
+#[test]
+pub fn test_diagonal_fast_path() {
+    let diag = [2., -4., 0.5, 8.];
+    let mut t = diag
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| ([i, i], v))
+        .collect::<Vec<_>>();
+    let a = Csc::from_triplets(4, 4, &mut t).unwrap();
+    let lu_fact = LeftLookingLUFactorization::new(&a);
+    assert_eq!(lu_fact.lu(), &a);
+    assert_eq!(lu_fact.pivot(), &[0, 1, 2, 3]);
+
+    // an explicit zero makes it structurally non-diagonal, so it takes the general path.
+    t.push(([0, 3], 0.));
+    let general = Csc::from_triplets(4, 4, &mut t).unwrap();
+    let general_fact = LeftLookingLUFactorization::new(&general);
+    assert_eq!(general_fact.pivot(), lu_fact.pivot());
+    for i in 0..4 {
+        assert_eq!(general_fact.lu().col(i).0[0], lu_fact.lu().col(i).0[0]);
+    }
+
+    let b = [1., 2., 3., 4.];
+    let mut fast = b;
+    let mut slow = b;
+    let mut buf = [0.; 4];
+    lu_fact.solve(&mut fast, &mut buf);
+    general_fact.solve(&mut slow, &mut buf);
+    assert_eq!(fast, slow);
+    for i in 0..4 {
+        assert_eq!(fast[i], b[i] / diag[i]);
+    }
+}
+
+#[test]
+pub fn test_residual() {
+    let a = arrowhead(5, 2);
+    let b = [1., -2., 3., 0.5, 4.];
+    let mut x = b;
+    let mut buf = [0.; 5];
+    LeftLookingLUFactorization::new(&a).solve(&mut x, &mut buf);
+    let mut r = [1.; 5];
+    a.residual(&x, &b, &mut r);
+    for r in r {
+        assert!(r.abs() < 1e-5, "{r}");
+    }
+
+    // a residual of a non-solution is exactly b - Ax
+    a.residual(&[0.; 5], &b, &mut r);
+    assert_eq!(r, b);
+}
+
+#[test]
+pub fn test_empty_column_is_singular() {
+    let a = Csc::from(vec![vec![1., 0., 2.], vec![3., 0., 4.], vec![5., 0., 6.]]);
+    assert_eq!(a.col(1).0.len(), 0);
+    let err = LeftLookingLUFactorization::try_new(&a).unwrap_err();
+    assert_eq!(err, LuError::Singular { column: 1 });
+    assert_eq!(
+        err.to_string(),
+        "matrix is singular, no nonzero pivot in column 1"
+    );
+
+    // a numerically singular matrix is also reported
+    let a = Csc::from(vec![vec![1., 2.], vec![2., 4.]]);
+    assert_eq!(
+        LeftLookingLUFactorization::try_new(&a).err(),
+        Some(LuError::Singular { column: 1 })
+    );
+}
+
+fn dense(a: &Csc<F>) -> Vec<Vec<F>> {
+    let mut out = vec![vec![0.; a.ncols()]; a.nrows()];
+    for ([c, r], &v) in a.pattern().entries().zip(a.values()) {
+        out[r][c] = v;
+    }
+    out
+}
+
+fn dense_matmul(a: &[Vec<F>], b: &[Vec<F>]) -> Vec<Vec<F>> {
+    a.iter()
+        .map(|row| {
+            (0..b[0].len())
+                .map(|j| row.iter().zip(b).map(|(a, b)| a * b[j]).sum())
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+pub fn test_into_plu() {
+    let a = Csc::from(vec![
+        vec![1., 4., 0., 2.],
+        vec![3., 0., 1., 0.],
+        vec![0., 2., 5., 1.],
+        vec![6., 1., 0., 3.],
+    ]);
+    let (p, l, u) = LeftLookingLUFactorization::new(&a).into_plu();
+    assert_ne!(p, [0, 1, 2, 3], "expected pivoting");
+    assert_eq!(l, l.lower_triangle());
+    assert_eq!(u, u.upper_triangle());
+    for i in 0..4 {
+        assert_eq!(l.col(i).0[0], 1.);
+    }
+
+    let a = dense(&a);
+    let pa = p.iter().map(|&r| a[r].clone()).collect::<Vec<_>>();
+    let lu = dense_matmul(&dense(&l), &dense(&u));
+    for (pa, lu) in pa.iter().flatten().zip(lu.iter().flatten()) {
+        assert!((pa - lu).abs() < 1e-5, "{pa} {lu}");
+    }
+}
+
+#[test]
+pub fn test_solve_left() {
+    let a = Csc::from(vec![
+        vec![1., 4., 0., 2.],
+        vec![3., 0., 1., 0.],
+        vec![0., 2., 5., 1.],
+        vec![6., 1., 0., 3.],
+    ]);
+    let lu_fact = LeftLookingLUFactorization::new(&a);
+    let b = [1., -2., 0.5, 3.];
+    let mut x = b;
+    let mut buf = [0.; 4];
+    lu_fact.solve_left(&mut x, &mut buf);
+    // xᵀA = (Aᵀx)ᵀ
+    let xa = a.vecmul_transpose(&x);
+    for (xa, b) in xa.iter().zip(b) {
+        assert!((xa - b).abs() < 1e-5, "{xa:?} {b:?}");
+    }
+}
+
+#[test]
+pub fn test_shared_scratch() {
+    let mut scratch = LuScratch::new();
+    // Shrinking and growing sizes, including one that needs pivoting.
+    for (n, hub) in [(8, 0), (3, 2), (12, 5), (5, 0)] {
+        let a = arrowhead(n, hub);
+        let lu = LeftLookingLUFactorization::new_with_scratch(&a, &mut scratch);
+        assert_solves(&a, &lu);
+        let fresh = LeftLookingLUFactorization::new(&a);
+        assert_eq!(lu.pivot(), fresh.pivot());
+        assert_eq!(lu.lu().values(), fresh.lu().values());
+    }
+}
+
+#[test]
+pub fn test_sparse_rhs_through_both_triangles() {
+    let n = 7;
+    let a = arrowhead(n, 3);
+    let lu = LeftLookingLUFactorization::new(&a);
+    let l_u = lu.lu();
+    // PA = LU, so the rhs row must be moved to where it was pivoted.
+    let k = 5;
+    let pk = lu.pivot().iter().position(|&p| p == k).unwrap();
+
+    let mut y_pat = vec![];
+    l_u.pattern()
+        .sparse_lower_triangular_solve(&[pk], &mut y_pat);
+    y_pat.sort_unstable();
+    let mut y = vec![0.; y_pat.len()];
+    let solved = l_u.sparse_lower_triangular_solve_sorted(&[pk], &[1.], &y_pat, &mut y, true);
+    assert_eq!(solved, Ok(()));
+
+    let mut x_pat = vec![];
+    l_u.pattern()
+        .sparse_upper_triangular_solve(&y_pat, &mut x_pat);
+    x_pat.sort_unstable();
+    let mut x = vec![0.; x_pat.len()];
+    let solved = l_u.sparse_upper_triangular_solve_sorted(&y_pat, &y, &x_pat, &mut x, false);
+    assert_eq!(solved, Ok(()));
+
+    let mut dense_x = vec![0.; n];
+    for (&i, &v) in x_pat.iter().zip(&x) {
+        dense_x[i] = v;
+    }
+    let ax = a.vecmul(&dense_x);
+    for (i, v) in ax.into_iter().enumerate() {
+        let expected = if i == k { 1. } else { 0. };
+        assert!((v - expected).abs() < 1e-5, "{i} {v}");
+    }
+}
+
+#[test]
+pub fn test_solve_rows() {
+    let n = 6;
+    let a = arrowhead(n, 2);
+    let lu = LeftLookingLUFactorization::new(&a);
+    let b = (0..3 * n).map(|i| (i as F).sin()).collect::<Vec<_>>();
+
+    let mut rows = b.clone();
+    lu.solve_rows(&mut rows, 3, &mut vec![0.; n]);
+
+    // transpose into the column major layout of `solve_arr`
+    let mut cols = (0..n)
+        .map(|r| [b[r], b[n + r], b[2 * n + r]])
+        .collect::<Vec<_>>();
+    lu.solve_arr(&mut cols, &mut vec![[0.; 3]; n]);
+    for (r, col) in cols.iter().enumerate() {
+        for (k, &v) in col.iter().enumerate() {
+            assert_eq!(rows[k * n + r], v);
+        }
+    }
+}
+
+#[test]
+pub fn test_solve_streaming() {
+    let n = 6;
+    let a = arrowhead(n, 2);
+    let lu = LeftLookingLUFactorization::new(&a);
+    let rhs = (0..4)
+        .map(|k| (0..n).map(|i| ((i * k) % 5) as F - 1.).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let mut streamed = vec![];
+    lu.solve_streaming(rhs.iter().cloned(), |x| streamed.push(x));
+
+    assert_eq!(streamed.len(), rhs.len());
+    let mut buf = vec![0.; n];
+    for (b, x) in rhs.iter().zip(&streamed) {
+        let mut expected = b.clone();
+        lu.solve(&mut expected, &mut buf);
+        assert_eq!(x, &expected);
+    }
+}
+
+#[test]
+pub fn test_solve_small() {
+    let mats = [
+        Csc::from(vec![vec![-4.]]),
+        Csc::from(vec![vec![2., 1.], vec![0., 3.]]),
+        // needs pivoting in LU
+        Csc::from(vec![vec![0., 2., 1.], vec![1., 0., 3.], vec![4., 1., 0.]]),
+    ];
+    for a in &mats {
+        let n = a.ncols();
+        let b = (0..n).map(|i| i as F - 0.5).collect::<Vec<_>>();
+        let mut x = vec![0.; n];
+        assert!(a.solve_small(&b, &mut x));
+
+        let mut expected = b.clone();
+        LeftLookingLUFactorization::new(a).solve(&mut expected, &mut vec![0.; n]);
+        for (x, e) in x.iter().zip(expected) {
+            assert!((x - e).abs() < 1e-5, "{x} {e}");
+        }
+    }
+
+    let singular = Csc::from(vec![vec![1., 2.], vec![2., 4.]]);
+    assert!(!singular.solve_small(&[1., 1.], &mut [0.; 2]));
+    let large = Csc::identity(4);
+    assert!(!large.solve_small(&[1.; 4], &mut [0.; 4]));
+}
+
+#[test]
+pub fn test_sparse_solve_missing_diagonal() {
+    // lower triangular, with no diagonal in row 1
+    let l = Csc::from(vec![vec![2., 0., 0.], vec![1., 0., 0.], vec![0., 1., 4.]]);
+    let mut pat = vec![];
+    l.pattern().sparse_lower_triangular_solve(&[0], &mut pat);
+    pat.sort_unstable();
+    assert_eq!(pat, [0, 1, 2]);
+    let mut out = vec![0.; 3];
+    assert_eq!(
+        l.sparse_lower_triangular_solve_sorted(&[0], &[2.], &pat, &mut out, false),
+        Err(1)
+    );
+    // with a unit diagonal, the missing diagonal is not needed.
+    assert_eq!(
+        l.sparse_lower_triangular_solve_sorted(&[0], &[2.], &pat, &mut out, true),
+        Ok(())
+    );
+    assert_eq!(out, [2., -2., 2.]);
+
+    let u = Csc::from(vec![vec![2., 1., 0.], vec![0., 0., 1.], vec![0., 0., 4.]]);
+    let mut pat = vec![];
+    u.pattern().sparse_upper_triangular_solve(&[2], &mut pat);
+    pat.sort_unstable();
+    let mut out = vec![0.; pat.len()];
+    assert_eq!(
+        u.sparse_upper_triangular_solve_sorted(&[2], &[4.], &pat, &mut out, false),
+        Err(1)
+    );
+}
+
+#[test]
+pub fn test_solve_owned() {
+    let n = 5;
+    let a = arrowhead(n, 1);
+    let lu = LeftLookingLUFactorization::new(&a);
+    let b = (0..n).map(|i| 1. - i as F).collect::<Vec<_>>();
+    let x = lu.solve_owned(&b);
+
+    let mut expected = b.clone();
+    lu.solve(&mut expected, &mut vec![0.; n]);
+    assert_eq!(x, expected);
+    assert_eq!(b, (0..n).map(|i| 1. - i as F).collect::<Vec<_>>());
+}
+
+#[test]
+pub fn test_row_threshold_pivoting() {
+    // Row 1 has the largest entry in column 0, but it is tiny compared to the rest of its row.
+    let a = Csc::from(vec![
+        vec![1., 0.1, 0.],
+        vec![2., 1000., 1.],
+        vec![0., 1., 3.],
+    ]);
+
+    let partial = LeftLookingLUFactorization::new(&a);
+    assert_eq!(partial.pivot()[0], 1);
+    assert_solves(&a, &partial);
+
+    let row =
+        LeftLookingLUFactorization::new_with_strategy(&a, PivotStrategy::OriginalRowThreshold(0.5));
+    assert_eq!(row.pivot()[0], 0);
+    assert_solves(&a, &row);
+}
+
+#[test]
+pub fn test_schur_complement() {
+    let a = Csc::from(vec![
+        vec![4., 1., 0., 2.],
+        vec![1., 3., 1., 0.],
+        vec![0., 2., 5., 1.],
+        vec![1., 0., 1., 6.],
+    ]);
+    let s = a.schur_complement(2).unwrap();
+    assert_eq!((s.nrows(), s.ncols()), (2, 2));
+
+    // A11⁻¹ = [[3, -1], [-1, 4]] / 11
+    let a11_inv = [[3. / 11., -1. / 11.], [-1. / 11., 4. / 11.]];
+    let a12 = [[0., 2.], [1., 0.]];
+    let a21 = [[0., 2.], [1., 0.]];
+    let a22 = [[5., 1.], [1., 6.]];
+    let s_dense = dense(&s);
+    for i in 0..2 {
+        for j in 0..2 {
+            let mut expected = a22[i][j];
+            for p in 0..2 {
+                for q in 0..2 {
+                    expected -= a21[i][p] * a11_inv[p][q] * a12[q][j];
+                }
+            }
+            assert!((s_dense[i][j] - expected).abs() < 1e-5, "{i} {j}");
+        }
+    }
+
+    let singular = Csc::from(vec![vec![0., 1.], vec![1., 1.]]);
+    assert_eq!(
+        singular.schur_complement(1),
+        Err(LuError::Singular { column: 0 })
+    );
+}
+
+#[test]
+pub fn test_nan_pivot_candidates() {
+    // every candidate in the first column is NaN, so there is no usable pivot.
+    let a = Csc::from(vec![vec![F::NAN, 1.], vec![F::NAN, 2.]]);
+    for strategy in [
+        PivotStrategy::Partial,
+        PivotStrategy::Threshold(0.1),
+        PivotStrategy::OriginalRowThreshold(0.1),
+    ] {
+        let res = LeftLookingLUFactorization::try_new_with_strategy(&a, strategy);
+        assert_eq!(res.unwrap_err(), LuError::Singular { column: 0 });
+    }
+
+    // a NaN beside a finite pivot is skipped when pivoting, but cannot be eliminated.
+    let mixed = Csc::from(vec![vec![F::NAN, 1.], vec![2., 3.]]);
+    for strategy in [
+        PivotStrategy::Partial,
+        PivotStrategy::Threshold(0.1),
+        PivotStrategy::OriginalRowThreshold(0.1),
+        PivotStrategy::None,
+        PivotStrategy::Diagonal,
+    ] {
+        let res = LeftLookingLUFactorization::try_new_with_strategy(&mixed, strategy);
+        assert_eq!(res.unwrap_err(), LuError::NonFinite { column: 0 });
+    }
+    // a NaN in a different column than the pivot is found when that column is reached.
+    let later = Csc::from(vec![
+        vec![1., 0., 0.],
+        vec![0., 1., 1.],
+        vec![0., F::NAN, 1.],
+    ]);
+    for strategy in [PivotStrategy::Partial, PivotStrategy::None] {
+        let res = LeftLookingLUFactorization::try_new_with_strategy(&later, strategy);
+        assert_eq!(res.unwrap_err(), LuError::NonFinite { column: 1 });
+    }
+
+    // the diagonal fast path.
+    let diagonal = Csc::from_triplets(2, 2, &mut [([0, 0], 1.), ([1, 1], F::INFINITY)]).unwrap();
+    for strategy in [PivotStrategy::Partial, PivotStrategy::Diagonal] {
+        let res = LeftLookingLUFactorization::try_new_with_strategy(&diagonal, strategy);
+        assert_eq!(res.unwrap_err(), LuError::NonFinite { column: 1 });
+    }
+}
+
+#[test]
+pub fn test_hybrid_dense_block() {
+    let n = 10;
+    // dense last row and column, with a zero diagonal so the dense block must pivot.
+    let mut a = arrowhead(n, n - 1);
+    a.col_mut(n - 1).0[n - 1] = 0.;
+    let b = (0..n).map(|i| i as F - 3.).collect::<Vec<_>>();
+    let expected = LeftLookingLUFactorization::new(&a).solve_owned(&b);
+
+    let hybrid = HybridLu::new(&a, 0.5).unwrap();
+    assert_eq!(hybrid.dense_block_size(), 1);
+    let mut x = b.clone();
+    hybrid.solve(&mut x);
+    for (x, e) in x.iter().zip(&expected) {
+        assert!((x - e).abs() < 1e-4, "{x} {e}");
+    }
+
+    for k in [0, 3, n] {
+        let hybrid = HybridLu::with_block(&a, k).unwrap();
+        assert_eq!(hybrid.dense_block_size(), k);
+        let mut x = b.clone();
+        hybrid.solve(&mut x);
+        for (x, e) in x.iter().zip(&expected) {
+            assert!((x - e).abs() < 1e-4, "{k}: {x} {e}");
+        }
+    }
+
+    // only trailing dense columns are found, so a leading hub stays in the sparse block.
+    assert_eq!(
+        HybridLu::new(&arrowhead(n, 0), 0.5)
+            .unwrap()
+            .dense_block_size(),
+        0
+    );
+}
+
+#[test]
+pub fn test_hybrid_singular_leading_block() {
+    // A is a permutation, but A11 = [0] cannot be pivoted with rows from A21.
+    let a = Csc::from(vec![vec![0., 1.], vec![1., 0.]]);
+    assert!(LeftLookingLUFactorization::try_new(&a).is_ok());
+    assert_eq!(
+        HybridLu::with_block(&a, 1).unwrap_err(),
+        LuError::Singular { column: 0 }
+    );
+    let mut x = vec![1., 2.];
+    HybridLu::with_block(&a, 2).unwrap().solve(&mut x);
+    assert_eq!(x, [2., 1.]);
+}
+
+#[test]
+#[should_panic(expected = "assertion `left == right` failed")]
+pub fn test_hybrid_solve_wrong_length() {
+    let hybrid = HybridLu::with_block(&arrowhead(6, 5), 2).unwrap();
+    hybrid.solve(&mut [1.; 5]);
+}
+
+#[test]
+pub fn test_new_auto() {
+    let n = 8;
+    // symmetric pattern, where the hub should be ordered last
+    let a = arrowhead(n, 0);
+    let natural = LeftLookingLUFactorization::new(&a);
+    let auto = LeftLookingLUFactorization::new_auto(&a);
+    assert_solves(&a, &auto);
+    assert!(auto.lu().nnz() < natural.lu().nnz());
+    assert!(auto.fill_entries(&a).is_empty());
+
+    // unsymmetric pattern, a dense first column with a shifted upper diagonal
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 4.));
+        if i > 0 {
+            t.push(([0, i], 1.));
+        }
+        if i + 2 < n {
+            t.push(([i + 2, i], 1.));
+        }
+    }
+    let b = Csc::from_triplets(n, n, &mut t).unwrap();
+    let natural = LeftLookingLUFactorization::new(&b);
+    let auto = LeftLookingLUFactorization::new_auto(&b);
+    assert_solves(&b, &auto);
+    assert!(auto.lu().nnz() <= natural.lu().nnz());
+
+    let mut x = (0..n).map(|i| i as F).collect::<Vec<_>>();
+    let mut expected = x.clone();
+    auto.solve_transpose(&mut x, &mut vec![0.; n]);
+    natural.solve_transpose(&mut expected, &mut vec![0.; n]);
+    for (x, e) in x.iter().zip(&expected) {
+        assert!((x - e).abs() < 1e-4, "{x} {e}");
+    }
+}
+
+#[test]
+pub fn test_colamd_ordering() {
+    let n = 10;
+    // a dense first column and an upper bidiagonal, which fills in every column of U
+    // when the first column is eliminated first.
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 4.));
+        if i > 0 {
+            t.push(([0, i], 1.));
+        }
+        if i + 1 < n {
+            t.push(([i + 1, i], 1.));
+        }
+    }
+    let a = Csc::from_triplets(n, n, &mut t).unwrap();
+    let order = a.pattern().colamd_ordering();
+    let mut sorted = order.clone();
+    sorted.sort_unstable();
+    assert_eq!(sorted, (0..n).collect::<Vec<_>>());
+
+    let natural = LeftLookingLUFactorization::new(&a);
+    let ordered = LeftLookingLUFactorization::new_with_ordering(&a, &order);
+    assert_solves(&a, &ordered);
+    assert!(
+        ordered.lu().nnz() < natural.lu().nnz(),
+        "{} {}",
+        ordered.lu().nnz(),
+        natural.lu().nnz()
+    );
+}
+
+#[test]
+pub fn test_compact() {
+    let n = 8;
+    let a = arrowhead(n, 3);
+    let b = (0..n).map(|i| (i as F).cos()).collect::<Vec<_>>();
+    for lu in [
+        LeftLookingLUFactorization::new(&a),
+        LeftLookingLUFactorization::new_auto(&a),
+    ] {
+        let expected = lu.solve_owned(&b);
+        let compact = lu.compact();
+        let mut x = b.clone();
+        compact.solve(&mut x, &mut vec![0.; n]);
+        assert_eq!(x, expected);
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+pub fn test_max_weight_matching() {
+    use sparse_lu::{DuplicatePolicy, ZeroPolicy};
+    let n = 8;
+    // tiny diagonal, with the large entries on a cyclic shift of the rows
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 1e-3));
+        t.push(([i, (i + 3) % n], 5. + i as F));
+        t.push(([i, (i + 5) % n], 1.));
+    }
+    let a = Csc::from_triplets_with_policy(n, n, &mut t, ZeroPolicy::Keep, DuplicatePolicy::Sum)
+        .unwrap();
+    let perm = a.max_weight_matching().unwrap();
+    assert_eq!(perm, (0..n).map(|i| (i + 3) % n).collect::<Vec<_>>());
+
+    let identity = (0..n).collect::<Vec<_>>();
+    let matched = a.permute(&perm, &identity);
+    let swaps = |lu: &LeftLookingLUFactorization<F>| {
+        lu.pivot()
+            .iter()
+            .enumerate()
+            .filter(|&(i, &p)| i != p)
+            .count()
+    };
+    let natural = LeftLookingLUFactorization::new(&a);
+    let lu = LeftLookingLUFactorization::new(&matched);
+    assert_eq!(swaps(&lu), 0);
+    assert!(swaps(&natural) > 0);
+    assert_solves(&matched, &lu);
+
+    let with_perm = LeftLookingLUFactorization::new_with_row_perm(&a, &perm, &identity);
+    assert_eq!(with_perm.pivot(), perm.as_slice());
+    assert_solves(&a, &with_perm);
+    let reversed = (0..n).rev().collect::<Vec<_>>();
+    let ordered = LeftLookingLUFactorization::new_with_row_perm(&a, &perm, &reversed);
+    assert_solves(&a, &ordered);
+}
+
+#[cfg(feature = "std")]
+#[test]
+pub fn test_max_weight_matching_is_optimal() {
+    let n = 6;
+    let mut t = vec![];
+    for c in 0..n {
+        for r in 0..n {
+            // a deterministic mix of magnitudes, with some entries missing.
+            let v = ((r * 7 + c * 13) % 11) as F - 4.;
+            if v != 0. {
+                t.push(([c, r], v * (1. + r as F * 0.3)));
+            }
+        }
+    }
+    let a = Csc::from_triplets(n, n, &mut t).unwrap();
+    let product = |perm: &[usize]| -> F {
+        (0..n)
+            .map(|c| a.get(perm[c], c).map_or(0., |v| v.abs()))
+            .product()
+    };
+
+    // checks every permutation with Heap's algorithm.
+    let mut perm = (0..n).collect::<Vec<_>>();
+    let mut best = product(&perm);
+    let mut counters = vec![0; n];
+    let mut i = 0;
+    while i < n {
+        if counters[i] < i {
+            perm.swap(if i % 2 == 0 { 0 } else { counters[i] }, i);
+            best = best.max(product(&perm));
+            counters[i] += 1;
+            i = 0;
+        } else {
+            counters[i] = 0;
+            i += 1;
+        }
+    }
+    let matched = product(&a.max_weight_matching().unwrap());
+    assert!(best > 0.);
+    assert!((matched - best).abs() <= 1e-4 * best, "{matched} {best}");
+}
+
+#[cfg(feature = "std")]
+#[test]
+pub fn test_max_weight_matching_large_sparse() {
+    // each search only reaches a few rows, so this is fast despite the size.
+    let n = 200_000;
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 1e-3));
+        t.push(([i, (i + 3) % n], 5.));
+        t.push(([i, (i + 5) % n], 1.));
+    }
+    let a = Csc::from_triplets(n, n, &mut t).unwrap();
+    let perm = a.max_weight_matching().unwrap();
+    assert!(perm.iter().enumerate().all(|(i, &p)| p == (i + 3) % n));
+}
+
+#[test]
+pub fn test_ilu_without_fill_is_exact() {
+    // the arrowhead with its hub last has no fill, so ILU(0) is the complete factorization.
+    let n = 8;
+    let a = arrowhead(n, n - 1);
+    let ilu = IluFactorization::new(&a).unwrap();
+    let lu = LeftLookingLUFactorization::new(&a);
+    assert_eq!(ilu.lu().pattern(), a.pattern());
+    let b = (0..n).map(|i| i as F + 1.).collect::<Vec<_>>();
+    let mut x = b.clone();
+    ilu.solve(&mut x, &mut vec![0.; n]);
+    for (x, e) in x.iter().zip(lu.solve_owned(&b)) {
+        assert!((x - e).abs() < 1e-5, "{x} {e}");
+    }
+
+    let missing_diag = Csc::from_triplets(2, 2, &mut [([0, 1], 1.), ([1, 0], 1.)]).unwrap();
+    assert_eq!(
+        IluFactorization::new(&missing_diag).unwrap_err(),
+        LuError::ZeroDiagonal { row: 0 }
+    );
+    // the diagonal of the second row is eliminated to zero.
+    let cancelled = Csc::from(vec![vec![1., 2.], vec![1., 2.]]);
+    assert_eq!(
+        IluFactorization::new(&cancelled).unwrap_err(),
+        LuError::ZeroDiagonal { row: 1 }
+    );
+}
+
+#[test]
+pub fn test_refactor_values() {
+    let n = 9;
+    // pivoting is needed on the original values, and the pivot order is reused.
+    let mut a = arrowhead(n, 0);
+    a.col_mut(0).0[0] = 0.5;
+    for lu in [
+        LeftLookingLUFactorization::new(&a),
+        LeftLookingLUFactorization::new_auto(&a),
+    ] {
+        let symbolic = lu.symbolic(&a);
+
+        // refactoring the same values reproduces the factorization
+        let same = symbolic.refactor_values(a.values());
+        assert_eq!(same.pivot(), lu.pivot());
+        for (x, y) in same.lu().values().iter().zip(lu.lu().values()) {
+            assert!((x - y).abs() < 1e-5, "{x} {y}");
+        }
+
+        let perturbed = a.map_values(|v| v * 1.1 + 0.05);
+        let relu = symbolic.refactor_values(perturbed.values());
+        assert_eq!(relu.lu().pattern(), lu.lu().pattern());
+        assert_eq!(relu.pivot(), lu.pivot());
+        assert_solves(&perturbed, &relu);
+    }
+}
+
+#[test]
+pub fn test_inverse_diagonal() {
+    // a nonsymmetric matrix which needs pivoting, and one with a zero diagonal entry.
+    let mut a = arrowhead(7, 3);
+    a.col_mut(3).0[3] = 0.5;
+    a.col_mut(5).0[0] = -2.;
+    let zero_diag = Csc::from(vec![
+        vec![0., 2., 1., 0.],
+        vec![3., 1., 0., 0.],
+        vec![0., 1., 4., 1.],
+        vec![1., 0., 0., 2.],
+    ]);
+    for a in [a, zero_diag] {
+        let n = a.ncols();
+        let dense_inverse_diag = (0..n)
+            .map(|i| {
+                let mut e = vec![0.; n];
+                e[i] = 1.;
+                LeftLookingLUFactorization::new(&a).solve_owned(&e)[i]
+            })
+            .collect::<Vec<_>>();
+        for lu in [
+            LeftLookingLUFactorization::new(&a),
+            LeftLookingLUFactorization::new_auto(&a),
+        ] {
+            let diag = lu.inverse_diagonal();
+            for (x, y) in diag.iter().zip(&dense_inverse_diag) {
+                assert!((x - y).abs() < 1e-5, "{x} {y}");
+            }
+        }
+    }
+}
+
+#[test]
+pub fn test_solve_accumulate() {
+    let n = 9;
+    let a = arrowhead(n, 4);
+    let lu = LeftLookingLUFactorization::new(&a);
+    let rhs = (0..5)
+        .map(|k| (0..n).map(|i| ((i * k) % 7) as F - 3.).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let mut expected = vec![1.; n];
+    for b in &rhs {
+        for (e, x) in expected.iter_mut().zip(lu.solve_owned(b)) {
+            *e += x;
+        }
+    }
+    let mut out = vec![1.; n];
+    lu.solve_accumulate(rhs.iter().map(Vec::as_slice), &mut out);
+    for (o, e) in out.iter().zip(&expected) {
+        assert!((o - e).abs() < 1e-5, "{o} {e}");
+    }
+}
+
+#[test]
+pub fn test_solve_with_perm() {
+    let n = 8;
+    let mut a = arrowhead(n, 2);
+    a.col_mut(2).0[2] = 0.25;
+    let b = (0..n).map(|i| (i as F).sin() + 1.).collect::<Vec<_>>();
+    let extra_perm = [3, 7, 0, 5, 1, 6, 2, 4];
+    let mut buf = vec![0.; n];
+    for lu in [
+        LeftLookingLUFactorization::new(&a),
+        LeftLookingLUFactorization::new_auto(&a),
+    ] {
+        let permuted = extra_perm.iter().map(|&p| b[p]).collect::<Vec<_>>();
+        let expected = lu.solve_owned(&permuted);
+        let mut x = b.clone();
+        lu.solve_with_perm(&mut x, &extra_perm, &mut buf);
+        assert_eq!(x, expected);
+    }
+}
+
+#[test]
+pub fn test_pivot_tolerance() {
+    // the last pivot is tiny compared to the rest of its column.
+    let a = Csc::from(vec![
+        vec![2., 1., 0.],
+        vec![1., 3., 1.],
+        vec![0., 0., 1e-18],
+    ]);
+    let lu = LeftLookingLUFactorization::try_new(&arrowhead(5, 0)).unwrap();
+    assert_eq!(lu.pivot_tolerance(), F::EPSILON);
+    assert_eq!(
+        LeftLookingLUFactorization::try_new(&a).unwrap_err(),
+        LuError::Singular { column: 2 }
+    );
+
+    // an exact zero test accepts the tiny pivot.
+    let exact =
+        LeftLookingLUFactorization::try_new_with_tolerance(&a, PivotStrategy::Partial, 0.).unwrap();
+    assert_eq!(exact.pivot_tolerance(), 0.);
+
+    // badly scaled columns are not singular, as the tolerance is relative to each column.
+    let scaled = Csc::from(vec![vec![1e3, 0.], vec![0., 1e-5]]);
+    let lu = LeftLookingLUFactorization::try_new(&scaled).unwrap();
+    assert_eq!(lu.solve_owned(&[2e3, 3e-5]), [2., 3.]);
+    let mut scaled_arrowhead = arrowhead(5, 2);
+    for (c, scale) in [1e-6, 1e4, 1., 1e-8, 1e3].into_iter().enumerate() {
+        for v in scaled_arrowhead.col_mut(c).0 {
+            *v *= scale;
+        }
+    }
+    assert!(LeftLookingLUFactorization::try_new(&scaled_arrowhead).is_ok());
+
+    // a uniformly tiny matrix is both factorized and solved.
+    let mut tiny = arrowhead(6, 0);
+    tiny.values_mut().iter_mut().for_each(|v| *v *= 1e-9);
+    let lu = LeftLookingLUFactorization::try_new(&tiny).unwrap();
+    let b = (0..6).map(|i| (i as F + 1.) * 1e-9).collect::<Vec<_>>();
+    let expected = LeftLookingLUFactorization::new(&arrowhead(6, 0)).solve_owned(&b);
+    for (x, e) in lu.solve_owned(&b).iter().zip(&expected) {
+        assert!((x * 1e-9 - e).abs() <= 1e-4 * e.abs().max(1e-9), "{x} {e}");
+    }
+}
+
+#[test]
+pub fn test_permutation_matrices() {
+    let n = 7;
+    let mut a = arrowhead(n, 0);
+    a.col_mut(0).0[0] = 0.5;
+    a.col_mut(3).0[0] = 20.;
+    for lu in [
+        LeftLookingLUFactorization::new(&a),
+        LeftLookingLUFactorization::new_auto(&a),
+    ] {
+        let paq = lu
+            .row_permutation_matrix()
+            .matmul(&a)
+            .matmul(&lu.column_permutation_matrix());
+        let product = lu.l().matmul(&lu.u());
+        for c in 0..n {
+            for r in 0..n {
+                let x = paq.get(r, c).copied().unwrap_or(0.);
+                let y = product.get(r, c).copied().unwrap_or(0.);
+                assert!((x - y).abs() < 1e-5, "({r}, {c}): {x} {y}");
+            }
+        }
+    }
+    assert_eq!(
+        LeftLookingLUFactorization::new(&a).column_permutation_matrix(),
+        Csc::identity(n)
+    );
+}
+
+#[test]
+pub fn test_solve_flops() {
+    let n = 7;
+    let mut a = arrowhead(n, 0);
+    a.col_mut(0).0[0] = 0.5;
+    let lu = LeftLookingLUFactorization::new(&a);
+    let b = (0..n).map(|i| i as F + 1.).collect::<Vec<_>>();
+
+    // the same solve as `solve`, counting each multiply-add.
+    let l_u = lu.lu();
+    let mut flops = 0;
+    let mut x = lu.pivot().iter().map(|&p| b[p]).collect::<Vec<_>>();
+    for i in 0..n {
+        for (r, &v) in l_u.col_iter(i).filter(|&(r, _)| r > i) {
+            x[r] -= v * x[i];
+            flops += 1;
+        }
+    }
+    for i in (0..n).rev() {
+        x[i] /= l_u.get(i, i).unwrap();
+        for (r, &v) in l_u.col_iter(i).filter(|&(r, _)| r < i) {
+            x[r] -= v * x[i];
+            flops += 1;
+        }
+    }
+    assert_eq!(lu.solve_flops(), flops);
+    for (x, y) in x.iter().zip(lu.solve_owned(&b)) {
+        assert!((x - y).abs() < 1e-5, "{x} {y}");
+    }
+}
+
+#[test]
+fn test_inverse_column() {
+    // a row swap of an upper triangular matrix, which needs pivoting.
+    let a = Csc::from(vec![vec![0., 1., 3.], vec![1., 2., 0.], vec![0., 0., 1.]]);
+    // the columns of the dense inverse.
+    let inverse = [[-2., 1., 0.], [1., 0., 0.], [6., -3., 1.]];
+    for lu in [
+        LeftLookingLUFactorization::new(&a),
+        LeftLookingLUFactorization::new_auto(&a),
+    ] {
+        for (j, expected) in inverse.iter().enumerate() {
+            for (x, y) in lu.inverse_column(j).iter().zip(expected) {
+                assert!((x - y).abs() < 1e-5, "{x} {y}");
+            }
+        }
+    }
+
+    let a = arrowhead(7, 3);
+    let lu = LeftLookingLUFactorization::new_auto(&a);
+    for j in [0, 3, 6] {
+        let ax = a.vecmul(&lu.inverse_column(j));
+        for (i, v) in ax.iter().enumerate() {
+            let e = if i == j { 1. } else { 0. };
+            assert!((v - e).abs() < 1e-5, "{v} {e}");
+        }
+    }
+}
+
+#[test]
+fn test_solve_sparse() {
+    let mut a = arrowhead(9, 4);
+    a.col_mut(2).0[0] = 3.;
+    // a block diagonal matrix, where a right hand side in one block only reaches that block.
+    let block = Csc::from(vec![
+        vec![0., 2., 0., 0.],
+        vec![3., 1., 0., 0.],
+        vec![0., 0., 1., 4.],
+        vec![0., 0., 2., 1.],
+    ]);
+    for a in [a, block.clone()] {
+        let n = a.ncols();
+        for lu in [
+            LeftLookingLUFactorization::new(&a),
+            LeftLookingLUFactorization::new_auto(&a),
+        ] {
+            for (b_idx, b_val) in [(vec![0], vec![1.]), (vec![1, 3], vec![-2., 0.5])] {
+                let mut dense = vec![0.; n];
+                for (&i, &v) in b_idx.iter().zip(&b_val) {
+                    dense[i] = v;
+                }
+                let expected = lu.solve_owned(&dense);
+
+                let (x_idx, x_val) = lu.solve_sparse(&b_idx, &b_val);
+                assert!(x_idx.is_sorted_by(|a, b| a < b));
+                let mut x = vec![0.; n];
+                for (&i, &v) in x_idx.iter().zip(&x_val) {
+                    x[i] = v;
+                }
+                for (x, y) in x.iter().zip(&expected) {
+                    assert!((x - y).abs() < 1e-5, "{x} {y}");
+                }
+            }
+        }
+    }
+
+    let lu = LeftLookingLUFactorization::new(&block);
+    assert_eq!(lu.solve_sparse(&[3], &[1.]).0, vec![2, 3]);
+
+    // a lower bidiagonal matrix, where the first entry reaches every other through a chain.
+    let n = 2_000;
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 1.));
+        if i + 1 < n {
+            t.push(([i, i + 1], -1.));
+        }
+    }
+    let lu = LeftLookingLUFactorization::new(&Csc::from_triplets(n, n, &mut t).unwrap());
+    let (x_idx, x_val) = lu.solve_sparse(&[0], &[1.]);
+    assert_eq!(x_idx.len(), n);
+    assert!(x_val.iter().all(|&x| x == 1.));
+}
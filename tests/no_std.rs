@@ -0,0 +1,60 @@
+//! Uses the factorization and triangular solves from a `no_std` crate, which only has access to
+//! `core` and `alloc`. Run with `--no-default-features` to also build the library without std.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use sparse_lu::{Csc, F, LeftLookingLUFactorization};
+
+fn tridiagonal(n: usize) -> Csc<F> {
+    let mut t = Vec::new();
+    for i in 0..n {
+        t.push(([i, i], 4.));
+        if i + 1 < n {
+            t.push(([i, i + 1], -1.));
+            t.push(([i + 1, i], 2.));
+        }
+    }
+    Csc::from_triplets(n, n, &mut t).unwrap()
+}
+
+#[test]
+fn test_lu_without_std() {
+    let n = 8;
+    let a = tridiagonal(n);
+    let lu = LeftLookingLUFactorization::new(&a);
+    let b = (0..n).map(|i| i as F).collect::<Vec<_>>();
+    let mut x = b.clone();
+    let mut buf = vec![0.; n];
+    lu.solve(&mut x, &mut buf);
+    let mut r = vec![0.; n];
+    a.residual(&x, &b, &mut r);
+    assert!(r.iter().all(|r| r.abs() < 1e-4));
+}
+
+#[test]
+fn test_triangular_solves_without_std() {
+    let a = tridiagonal(5);
+    let b = [1., 2., 3., 4., 5.];
+    let mut x = [0.; 5];
+    let lower = a.lower_triangle();
+    lower.dense_lower_triangular_solve(&b, &mut x, false);
+    assert!(
+        lower
+            .vecmul(&x)
+            .iter()
+            .zip(b)
+            .all(|(l, b)| (l - b).abs() < 1e-5)
+    );
+    let upper = a.upper_triangle();
+    upper.dense_upper_triangular_solve(&b, &mut x);
+    assert!(
+        upper
+            .vecmul(&x)
+            .iter()
+            .zip(b)
+            .all(|(u, b)| (u - b).abs() < 1e-5)
+    );
+}
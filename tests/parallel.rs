@@ -0,0 +1,33 @@
+#![cfg(feature = "parallel")]
+use sparse_lu::{Csc, F};
+
+/// A lower triangular matrix with bands `offsets` below the diagonal.
+fn banded(n: usize, offsets: &[usize]) -> Csc<F> {
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 2. + (i % 3) as F));
+        for &off in offsets {
+            if i + off < n {
+                t.push(([i, i + off], -1. / off as F));
+            }
+        }
+    }
+    Csc::from_triplets(n, n, &mut t).unwrap()
+}
+
+#[test]
+fn test_par_lower_triangular_solve() {
+    let n = 4000;
+    let b = (0..n).map(|i| (i % 17) as F - 8.).collect::<Vec<_>>();
+    // a chain where every level is a single row, and wide bands with levels of 700 rows.
+    for offsets in [&[1, 5][..], &[700, 1500]] {
+        let a = banded(n, offsets);
+        for unit_diagonal in [false, true] {
+            let mut serial = vec![0.; n];
+            a.dense_lower_triangular_solve(&b, &mut serial, unit_diagonal);
+            let mut par = vec![0.; n];
+            a.par_dense_lower_triangular_solve(&b, &mut par, unit_diagonal);
+            assert_eq!(serial, par);
+        }
+    }
+}
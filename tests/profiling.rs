@@ -0,0 +1,45 @@
+#![cfg(feature = "profiling")]
+use sparse_lu::{Csc, F, LeftLookingLUFactorization};
+
+/// An arrowhead matrix with a dominant diagonal, and a dense row and column at `hub`.
+fn arrowhead(n: usize, hub: usize) -> Csc<F> {
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 10.));
+        if i != hub {
+            t.push(([i, hub], 1.));
+            t.push(([hub, i], 1.));
+        }
+    }
+    Csc::from_triplets(n, n, &mut t).unwrap()
+}
+
+#[test]
+fn test_profile_heavy_columns() {
+    let n = 6;
+    // With the dense column first, every column fills in, and later columns must apply all
+    // the earlier ones.
+    let (lu, profile) = LeftLookingLUFactorization::new_profiled(&arrowhead(n, 0));
+    assert_eq!(profile.len(), n);
+    assert_eq!(
+        lu.lu().nnz(),
+        profile.iter().map(|p| p.reach).sum::<usize>()
+    );
+    assert!(profile.iter().all(|p| p.reach == n));
+    assert!(profile.windows(2).all(|w| w[0].flops <= w[1].flops));
+    let heaviest = (0..n).max_by_key(|&i| profile[i].flops).unwrap();
+    assert_eq!(heaviest, n - 1);
+
+    // With the dense column last, there is no fill and only the last column does real work.
+    let (lu, profile) = LeftLookingLUFactorization::new_profiled(&arrowhead(n, n - 1));
+    assert_eq!(
+        lu.lu().nnz(),
+        profile.iter().map(|p| p.reach).sum::<usize>()
+    );
+    for p in &profile[..n - 1] {
+        assert_eq!(p.reach, 2);
+        assert_eq!(p.flops, 1);
+    }
+    assert_eq!(profile[n - 1].reach, n);
+    assert_eq!(profile[n - 1].flops, n - 1);
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "std")]
+use sparse_lu::{Csc, F, SparseQr};
+
+/// A tall matrix whose last column is the sum of the first two, so it has rank 3.
+fn rank_deficient() -> Csc<F> {
+    Csc::from(vec![
+        vec![1., 0., 2., 1.],
+        vec![0., 1., 0., 1.],
+        vec![2., 0., 0., 2.],
+        vec![0., 3., 1., 3.],
+        vec![1., 1., 0., 2.],
+        vec![0., 0., 4., 0.],
+    ])
+}
+
+#[test]
+fn test_pivoted_qr_rank() {
+    let a = rank_deficient();
+    let qr = SparseQr::new_pivoted(&a, 1e-4);
+    assert_eq!(qr.rank(), 3);
+    assert_eq!(qr.r().nrows(), 3);
+    let mut perm = qr.col_perm().to_vec();
+    perm.sort_unstable();
+    assert_eq!(perm, [0, 1, 2, 3]);
+
+    // the first pivot is the column with the largest norm
+    assert_eq!(qr.col_perm()[0], 2);
+    // |R_kk| is non-increasing
+    let diag = (0..3)
+        .map(|k| qr.r().get(k, k).unwrap().abs())
+        .collect::<Vec<_>>();
+    assert!(diag.windows(2).all(|w| w[0] >= w[1]), "{diag:?}");
+
+    let full_rank = Csc::from(vec![vec![2., 1.], vec![1., 3.], vec![0., 1.]]);
+    assert_eq!(SparseQr::new_pivoted(&full_rank, 1e-4).rank(), 2);
+}
+
+#[test]
+fn test_pivoted_qr_least_squares() {
+    let a = rank_deficient();
+    let qr = SparseQr::new_pivoted(&a, 1e-4);
+    let b = [1., -1., 2., 0.5, 3., -2.];
+    let x = qr.solve(&b);
+
+    // a basic solution only uses `rank` columns
+    assert_eq!(x.iter().filter(|&&x| x == 0.).count(), 1);
+    // the residual is orthogonal to the range of A, so it is minimal.
+    let ax = a.vecmul(&x);
+    let r = b.iter().zip(ax).map(|(b, ax)| b - ax).collect::<Vec<_>>();
+    for v in a.vecmul_transpose(&r) {
+        assert!(v.abs() < 1e-4, "{v}");
+    }
+}
+
+#[test]
+fn test_update_row() {
+    let rows = vec![
+        vec![2., 0., 1.],
+        vec![0., 1., 0.],
+        vec![1., 0., 3.],
+        vec![0., 2., 1.],
+        vec![1., 1., 0.],
+        vec![0., 3., -1.],
+    ];
+    let b = [1., 2., -1., 0.5, 3., 1.];
+    let batch = SparseQr::new_pivoted(&Csc::from(rows.clone()), 1e-6);
+    let expected = batch.solve(&b);
+
+    let mut qr = SparseQr::new_pivoted(&Csc::from(rows[..3].to_vec()), 1e-6);
+    assert_eq!(qr.rank(), 3);
+    qr.set_rhs(&b[..3]);
+    for (row, &b) in rows.iter().zip(&b).skip(3) {
+        let (idx, val): (Vec<_>, Vec<_>) = row
+            .iter()
+            .enumerate()
+            .filter(|&(_, &v)| v != 0.)
+            .map(|(i, &v)| (i, v))
+            .unzip();
+        qr.update_row(&idx, &val, b);
+    }
+    for (x, e) in qr.solution().iter().zip(expected) {
+        assert!((x - e).abs() < 1e-4, "{x} {e}");
+    }
+}
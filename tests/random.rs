@@ -0,0 +1,26 @@
+#![cfg(feature = "random")]
+use sparse_lu::{Csc, LeftLookingLUFactorization};
+
+#[test]
+fn test_random_reproducible() {
+    let a = Csc::random(200, 150, 0.05, 7);
+    assert_eq!(a, Csc::random(200, 150, 0.05, 7));
+    assert_ne!(a, Csc::random(200, 150, 0.05, 8));
+    assert_eq!((a.nrows(), a.ncols()), (200, 150));
+
+    let density = a.nnz() as f64 / (200. * 150.);
+    assert!((density - 0.05).abs() < 0.005, "{density}");
+    assert!(a.values().iter().all(|v| (-1. ..=1.).contains(v)));
+
+    assert_eq!(Csc::random(10, 10, 0., 1).nnz(), 0);
+    assert_eq!(Csc::random(10, 10, 1., 1).nnz(), 100);
+}
+
+#[test]
+fn test_random_spd() {
+    let a = Csc::random_spd(50, 0.1, 3);
+    assert_eq!(a, a.transpose());
+    assert!(a.is_likely_spd());
+    let lu = LeftLookingLUFactorization::new(&a);
+    assert!(lu.pivot().iter().enumerate().all(|(i, &p)| i == p));
+}
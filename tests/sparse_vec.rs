@@ -0,0 +1,60 @@
+use sparse_lu::vecops::{axpy, dot};
+use sparse_lu::{Csc, F, SparseVec};
+
+fn matrix() -> Csc<F> {
+    Csc::from(vec![
+        vec![1., 0., 2.],
+        vec![0., 3., 0.],
+        vec![4., 0., 5.],
+        vec![0., 6., 7.],
+    ])
+}
+
+#[test]
+fn test_column() {
+    let a = matrix();
+    let c = a.column(2);
+    assert_eq!(c.dim(), 4);
+    assert_eq!(c.indices(), [0, 2, 3]);
+    assert_eq!(c.values(), [&2., &5., &7.]);
+    assert_eq!(c.to_owned().to_dense(), [2., 0., 5., 7.]);
+}
+
+#[test]
+fn test_sparse_dot() {
+    let a = matrix();
+    for i in 0..3 {
+        for j in 0..3 {
+            let (x, y) = (a.column(i), a.column(j));
+            let expected = dot(&x.to_dense(), &y.to_dense());
+            assert_eq!(x.dot(&y), expected);
+            assert_eq!(x.to_owned().dot(&y), expected);
+        }
+    }
+}
+
+#[test]
+fn test_sparse_axpy() {
+    let a = matrix();
+    for i in 0..3 {
+        for j in 0..3 {
+            let mut y = a.column(j).to_owned();
+            let mut dense = y.to_dense();
+            y.axpy(-2., &a.column(i));
+            axpy(-2., &a.column(i).to_dense(), &mut dense);
+            assert_eq!(y.to_dense(), dense);
+            assert!(y.indices().is_sorted_by(|a, b| a < b));
+        }
+    }
+
+    let mut y = SparseVec::new(5, vec![1, 4], vec![1., 1.]);
+    y.axpy(1., &SparseVec::new(5, vec![0, 1, 3], vec![2., -1., 3.]));
+    assert_eq!(y, SparseVec::new(5, vec![0, 1, 3, 4], vec![2., 0., 3., 1.]));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_sparse_norm() {
+    let v = SparseVec::new(10, vec![2, 7], vec![3., -4.]);
+    assert_eq!(v.norm(), 5.);
+}
@@ -1,4 +1,4 @@
-use sparse_lu::{SparsityPattern, SparsityPatternBuilder};
+use sparse_lu::{BuildError, SparsityPattern, SparsityPatternBuilder};
 
 #[test]
 fn sparsity_identity() {
@@ -129,3 +129,139 @@ fn test_builder_reset() {
     assert!(builder.revert_to_major(1));
     assert_eq!(builder.current_major(), 1);
 }
+
+#[test]
+fn test_error_messages() {
+    let mut builder = SparsityPatternBuilder::new(3, 3);
+    assert!(builder.insert(1, 2).is_ok());
+    let err = builder.insert(1, 0).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "minor index 0 is not greater than the previous minor 2"
+    );
+    let err = builder.insert(0, 0).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "major index is lower than the current major 1"
+    );
+
+    let err: Box<dyn std::error::Error> = Box::new(BuildError::Incomplete(1, 2));
+    assert_eq!(
+        err.to_string(),
+        "builder stopped at major 1, before reaching the last major 2"
+    );
+}
+
+#[test]
+fn test_builder_assert_valid() {
+    let mut builder = SparsityPatternBuilder::new(3, 3);
+    for (maj, min) in [(0, 0), (0, 2), (2, 1)] {
+        builder.insert(maj, min).unwrap();
+    }
+    builder.assert_valid();
+    let mut pattern = builder.build();
+    SparsityPatternBuilder::from(pattern.clone()).assert_valid();
+
+    // shrinking the minor dimension leaves an out of bounds entry
+    pattern.minor_dim = 2;
+    let corrupted = SparsityPatternBuilder::from(pattern);
+    let res = std::panic::catch_unwind(|| corrupted.assert_valid());
+    assert_eq!(res.is_err(), cfg!(debug_assertions));
+}
+
+#[test]
+fn test_column_dependencies() {
+    let mut builder = SparsityPatternBuilder::new(5, 5);
+    // a chain 0 -> 1 -> 2 -> 3, with a shortcut from 0 to 3, and an independent column 4.
+    // the upper triangular entry (2, 0) is ignored.
+    for (maj, min) in [
+        (0, 0),
+        (0, 1),
+        (0, 3),
+        (1, 1),
+        (1, 2),
+        (2, 0),
+        (2, 2),
+        (2, 3),
+        (3, 3),
+        (4, 4),
+    ] {
+        builder.insert(maj, min).unwrap();
+    }
+    let deps = builder.build().column_dependencies();
+    assert_eq!(deps, vec![vec![], vec![0], vec![1], vec![0, 2], vec![]]);
+}
+
+#[test]
+fn test_build_strict() {
+    let mut builder = SparsityPatternBuilder::new(3, 3);
+    builder.insert(0, 0).unwrap();
+    builder.insert(1, 2).unwrap();
+    // leniently, the last major is left empty
+    let lenient = builder.clone().build();
+    assert_eq!(lenient.major_dim(), 3);
+    assert_eq!(lenient.lane(2), []);
+    assert_eq!(
+        builder.clone().build_strict(),
+        Err(BuildError::Incomplete(1, 2))
+    );
+
+    builder.insert(2, 1).unwrap();
+    let strict = builder.clone().build_strict().unwrap();
+    assert_eq!(strict, builder.build());
+}
+
+/// Builds a pattern with a minor dimension of `n` from its sorted lanes.
+fn pattern(n: usize, lanes: &[&[usize]]) -> SparsityPattern {
+    let mut b = SparsityPatternBuilder::new(lanes.len(), n);
+    for (maj, lane) in lanes.iter().enumerate() {
+        for &min in lane.iter() {
+            b.insert(maj, min).unwrap();
+        }
+    }
+    b.build()
+}
+
+#[test]
+fn test_structural_singularity() {
+    // full rank, but only through a chain of augmenting paths
+    let full = pattern(4, &[&[0, 1], &[0], &[1, 2], &[2, 3]]);
+    assert_eq!(full.structural_rank(), 4);
+    assert!(!full.is_structurally_singular());
+
+    // three majors only use two minors
+    let singular = pattern(4, &[&[0, 1], &[0], &[1], &[0, 1, 2, 3]]);
+    assert_eq!(singular.structural_rank(), 3);
+    assert!(singular.is_structurally_singular());
+
+    assert!(!SparsityPattern::identity(5).is_structurally_singular());
+}
+
+#[test]
+fn test_union() {
+    let a = pattern(5, &[&[0, 2], &[], &[1, 3, 4], &[4]]);
+    let b = pattern(5, &[&[1, 2], &[3], &[], &[0, 4]]);
+    let union = a.union(&b);
+    let expected = pattern(5, &[&[0, 1, 2], &[3], &[1, 3, 4], &[0, 4]]);
+    assert_eq!(union, expected);
+    assert_eq!(b.union(&a), expected);
+    assert_eq!(a.union(&a), a);
+}
+
+#[test]
+fn test_intersection_difference() {
+    let a = pattern(5, &[&[0, 2], &[], &[1, 3, 4], &[4]]);
+    let b = pattern(5, &[&[1, 2], &[3], &[3], &[0, 4]]);
+    assert_eq!(a.intersection(&b), pattern(5, &[&[2], &[], &[3], &[4]]));
+    assert_eq!(b.intersection(&a), a.intersection(&b));
+    assert_eq!(a.difference(&b), pattern(5, &[&[0], &[], &[1, 4], &[]]));
+    assert_eq!(b.difference(&a), pattern(5, &[&[1], &[3], &[], &[0]]));
+
+    // the intersection and both differences partition the union
+    let parts = a
+        .intersection(&b)
+        .union(&a.difference(&b))
+        .union(&b.difference(&a));
+    assert_eq!(parts, a.union(&b));
+    assert_eq!(a.difference(&a).nnz(), 0);
+}
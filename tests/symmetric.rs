@@ -0,0 +1,83 @@
+use sparse_lu::{Csc, SymmetricCsc};
+
+#[test]
+fn test_symmetric_vecmul() {
+    let a = Csc::from(vec![
+        vec![4., 1., 0., 2.],
+        vec![1., 3., -1., 0.],
+        vec![0., -1., 5., 0.5],
+        vec![2., 0., 0.5, 1.],
+    ]);
+    let sym = SymmetricCsc::from_full(&a);
+    assert_eq!(sym.nnz(), 8);
+    assert_eq!(sym.to_full(), a);
+    assert_eq!(SymmetricCsc::from_lower(sym.lower().clone()), sym);
+
+    let v = [1., -2., 0.5, 3.];
+    assert_eq!(sym.vecmul(&v), a.vecmul(&v));
+}
+
+#[test]
+#[should_panic]
+fn test_symmetric_from_lower_rejects_upper() {
+    let a = Csc::from(vec![vec![1., 2.], vec![0., 1.]]);
+    SymmetricCsc::from_lower(a);
+}
+
+#[test]
+fn test_is_likely_spd() {
+    // The 1D Laplacian is SPD.
+    let n = 6;
+    let mut t = vec![];
+    for i in 0..n {
+        t.push(([i, i], 2.));
+        if i + 1 < n {
+            t.push(([i, i + 1], -1.));
+            t.push(([i + 1, i], -1.));
+        }
+    }
+    let lap = Csc::from_triplets(n, n, &mut t).unwrap();
+    assert_eq!(lap.get(1, 0), Some(&-1.));
+    assert_eq!(lap.get(2, 0), None);
+    assert!(lap.is_likely_spd());
+
+    // Symmetric, but with eigenvalues 3 and -1.
+    let indefinite = Csc::from(vec![vec![1., 2.], vec![2., 1.]]);
+    assert!(!indefinite.is_likely_spd());
+
+    let negative_diag = Csc::from(vec![vec![2., 0.], vec![0., -1.]]);
+    assert!(!negative_diag.is_likely_spd());
+
+    let unsymmetric = Csc::from(vec![vec![2., 1.], vec![0., 2.]]);
+    assert!(!unsymmetric.is_likely_spd());
+
+    // Indefinite, but every 2x2 principal minor is positive so it can't be detected.
+    let undetected = Csc::from(vec![
+        vec![1., 0.9, 0.9],
+        vec![0.9, 1., -0.9],
+        vec![0.9, -0.9, 1.],
+    ]);
+    assert!(undetected.is_likely_spd());
+}
+
+#[test]
+fn test_symmetrize() {
+    let a = Csc::from(vec![vec![1., 2., 0.], vec![0., 3., 4.], vec![5., 0., 6.]]);
+    assert_eq!(a.transpose().transpose(), a);
+    let sym = a.symmetrize();
+    assert_eq!(sym, sym.transpose());
+    assert_eq!(sym.get(0, 1), Some(&1.));
+    assert_eq!(sym.get(2, 0), Some(&2.5));
+    assert_eq!(sym.get(1, 1), Some(&3.));
+
+    // already symmetric
+    assert_eq!(sym.symmetrize(), sym);
+
+    let pattern = a.symmetric_pattern();
+    assert_eq!(pattern.nnz(), 9);
+    assert_eq!(pattern.get(1, 0), Some(&0.));
+    assert_eq!(pattern.get(0, 1), Some(&2.));
+    for ([c, r], &v) in a.pattern().entries().zip(a.values()) {
+        assert_eq!(pattern.get(r, c), Some(&v));
+    }
+}
@@ -0,0 +1,32 @@
+#![cfg(feature = "test-utils")]
+use sparse_lu::test_utils::assert_matches_dense;
+use sparse_lu::{Csc, F};
+
+fn matrix() -> Csc<F> {
+    Csc::from_triplets(2, 3, &mut [([0, 0], 1.), ([2, 0], 2.), ([1, 1], 0.)]).unwrap()
+}
+
+#[test]
+fn test_matches_dense() {
+    let a = matrix();
+    assert_matches_dense(&a, &[1., 0., 2., 0., 0., 0.], 0.);
+    assert_matches_dense(&a, &[1.05, 0., 2., 0., 0., 0.], 0.1);
+}
+
+#[test]
+#[should_panic(expected = "entry (0, 2) is 2, expected 3")]
+fn test_mismatch() {
+    assert_matches_dense(&matrix(), &[1., 0., 3., 0., 0., 0.], 0.1);
+}
+
+#[test]
+#[should_panic(expected = "entry (1, 0) is 0, expected 1")]
+fn test_missing_entry() {
+    assert_matches_dense(&matrix(), &[1., 0., 2., 1., 0., 0.], 0.1);
+}
+
+#[test]
+#[should_panic(expected = "but the sparse matrix is 2x3")]
+fn test_wrong_size() {
+    assert_matches_dense(&matrix(), &[1., 0., 2., 0.], 0.);
+}
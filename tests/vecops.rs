@@ -0,0 +1,38 @@
+use sparse_lu::vecops::{axpy, dot, scal};
+#[cfg(feature = "std")]
+use sparse_lu::{F, vecops::norm2};
+
+#[test]
+fn test_dot() {
+    assert_eq!(dot(&[1., 2., 3.], &[4., -5., 6.]), 12.);
+    assert_eq!(dot(&[], &[]), 0.);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_norm2() {
+    assert_eq!(norm2(&[3., 4.]), 5.);
+    assert_eq!(norm2(&[0., 0.]), 0.);
+    assert_eq!(norm2(&[]), 0.);
+    // squaring these directly would overflow
+    let big = F::MAX / 4.;
+    let n = norm2(&[big, big]);
+    assert!(n.is_finite());
+    assert!((n / big - (2. as F).sqrt()).abs() < 1e-6);
+    assert!(norm2(&[1., F::NAN, 2.]).is_nan());
+    assert_eq!(norm2(&[1., F::NEG_INFINITY]), F::INFINITY);
+}
+
+#[test]
+fn test_axpy() {
+    let mut y = [1., 1., 1.];
+    axpy(2., &[1., -1., 0.5], &mut y);
+    assert_eq!(y, [3., -1., 2.]);
+}
+
+#[test]
+fn test_scal() {
+    let mut x = [1., -2., 0.5];
+    scal(-2., &mut x);
+    assert_eq!(x, [-2., 4., -1.]);
+}